@@ -1,9 +1,19 @@
 use actix_web::{web, HttpResponse, Responder};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use crate::models::{AppState, LoginQuery, LoginResponse, ApiResponseV2, DeviceStatus, ConnectDeviceRequest};
-use crate::connect_to_signalr;
+use crate::models::{AppState, LoginQuery, LoginResponse, LoginData, ApiResponseV2, Device, DeviceList, DeviceStatus, ConnectDeviceRequest, DisconnectDeviceRequest, UpdateDeviceStatusRequest, HubHealth};
+use crate::{connect_to_signalr, BACKEND_URL, REFRESH_TOKEN_PATH};
+use crate::peer_connection::PeerConnection;
 use crate::signalr_handler::SignalRHandler;
+use signalr_client::SignalRClient;
+
+// Hub method trả về danh sách thiết bị
+const HUB_LIST_DEVICES: &str = "ListDevices";
+// Hub method yêu cầu kết nối thiết bị
+const HUB_CONNECT_DEVICE: &str = "ConnectDevice";
+// Hub method giải phóng thiết bị
+const HUB_DISCONNECT_DEVICE: &str = "DisconnectDevice";
 
 pub async fn hello() -> impl Responder {
     let response = ApiResponseV2 {
@@ -35,12 +45,7 @@ pub async fn login(
 
     let key = &query.key;
     
-    let client = reqwest::Client::builder()
-        .danger_accept_invalid_certs(true)
-        .tls_built_in_root_certs(false)
-        .min_tls_version(reqwest::tls::Version::TLS_1_2)
-        .build()
-        .unwrap();
+    let client = create_http_client();
     
     let login_result = client
         .get(format!("{}/Octopus/login/{}", "https://api.maxcloudphone.com", key))
@@ -58,15 +63,16 @@ pub async fn login(
                     if login_data.succeeded {
                         let token = login_data.data.token;
                         
-                        // Lưu JWT token vào AppState
+                        // Lưu JWT token và refresh token vào AppState
                         let mut state = app_state.lock().await;
                         state.jwt_token = Some(token.clone());
+                        state.refresh_token = Some(login_data.data.refresh_token);
                         
                         // Kết nối đến SignalR
                         match connect_to_signalr(&token).await {
-                            Ok(mut hub_connection) => {
+                            Ok(hub_connection) => {
                                 // Đăng ký các handlers thông qua SignalRHandler
-                                SignalRHandler::register_handlers(&mut hub_connection);
+                                SignalRHandler::register_handlers(&hub_connection, app_state.get_ref().clone(), state.message_sender.clone());
                                 
                                 state.hub_connection = Some(hub_connection);
                                 
@@ -128,25 +134,217 @@ pub async fn login(
     }
 }
 
+// Tạo HTTP client dùng cho các request tới backend
+pub fn create_http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tls_built_in_root_certs(false)
+        .min_tls_version(reqwest::tls::Version::TLS_1_2)
+        .build()
+        .unwrap()
+}
+
+// Lấy JWT mới từ refresh token
+pub async fn refresh_access_token(refresh_token: &str) -> Result<LoginData, String> {
+    let response = create_http_client()
+        .post(format!("{}{}", BACKEND_URL, REFRESH_TOKEN_PATH))
+        .json(&serde_json::json!({ "refreshToken": refresh_token }))
+        .send()
+        .await
+        .map_err(|e| format!("Lỗi kết nối đến máy chủ xác thực: {}", e))?;
+
+    let login_data = response
+        .json::<LoginResponse>()
+        .await
+        .map_err(|e| format!("Lỗi khi xử lý phản hồi: {}", e))?;
+
+    if login_data.succeeded {
+        Ok(login_data.data)
+    } else {
+        Err(login_data.messages.first().cloned().unwrap_or("Làm mới token không thành công".to_string()))
+    }
+}
+
+// Endpoint để đăng xuất: ngắt kết nối SignalR và xóa trạng thái phiên
+pub async fn logout(
+    app_state: web::Data<Arc<Mutex<AppState>>>,
+) -> impl Responder {
+    let mut state = app_state.lock().await;
+
+    let peer_connection = state.peer_connection.take();
+    let hub_connection = state.hub_connection.take();
+
+    state.jwt_token = None;
+    state.refresh_token = None;
+
+    for device in state.devices.iter_mut() {
+        device.status = DeviceStatus::Offline;
+    }
+
+    // Không giữ lock AppState trong lúc đóng kết nối
+    drop(state);
+
+    // Đóng peer connection nếu có
+    if let Some(peer_connection) = peer_connection {
+        let _ = peer_connection.close().await;
+    }
+
+    // Ngắt kết nối SignalR
+    if let Some(hub_connection) = hub_connection {
+        hub_connection.disconnect();
+    }
+
+    let response = ApiResponseV2 {
+        status_code: 200,
+        message: "Đăng xuất thành công".to_string(),
+        success: true,
+        data: None::<()>,
+    };
+
+    HttpResponse::Ok().json(response)
+}
+
+// Endpoint SSE chuyển tiếp các MESSAGE từ hub tới client
+pub async fn events(
+    app_state: web::Data<Arc<Mutex<AppState>>>,
+) -> impl Responder {
+    let receiver = app_state.lock().await.message_sender.subscribe();
+
+    // Bỏ qua các message bị lỡ khi client đọc chậm
+    let stream = BroadcastStream::new(receiver)
+        .filter_map(|message| message.ok())
+        .map(|message| Ok::<_, actix_web::Error>(web::Bytes::from(format!("data: {}\n\n", message))));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+// Endpoint kiểm tra kết nối hub
+pub async fn health(
+    app_state: web::Data<Arc<Mutex<AppState>>>,
+) -> impl Responder {
+    // Không giữ lock AppState trong lúc chờ ping
+    let hub_connection = app_state.lock().await.hub_connection.clone();
+
+    let health = match hub_connection {
+        Some(hub_connection) if hub_connection.is_connected() => {
+            let rtt = match hub_connection.ping().await {
+                Ok(rtt) => Some(rtt.as_millis()),
+                Err(e) => {
+                    println!("Ping hub thất bại: {}", e);
+                    None
+                }
+            };
+
+            HubHealth {
+                connected: rtt.is_some(),
+                rtt_ms: rtt,
+                last_message_ms_ago: hub_connection.last_server_message_at().map(|at| at.elapsed().as_millis()),
+                pending_invocations: hub_connection.pending_invocations().len(),
+            }
+        }
+        Some(hub_connection) => HubHealth {
+            connected: false,
+            rtt_ms: None,
+            last_message_ms_ago: hub_connection.last_server_message_at().map(|at| at.elapsed().as_millis()),
+            pending_invocations: hub_connection.pending_invocations().len(),
+        },
+        None => HubHealth {
+            connected: false,
+            rtt_ms: None,
+            last_message_ms_ago: None,
+            pending_invocations: 0,
+        },
+    };
+
+    let (status_code, message) = if health.connected {
+        (200, "Hub đang hoạt động")
+    } else {
+        (503, "Không kết nối được hub")
+    };
+
+    let response = ApiResponseV2 {
+        status_code,
+        message: message.to_string(),
+        success: health.connected,
+        data: Some(health),
+    };
+
+    if status_code == 200 {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
+
 // Endpoint để lấy danh sách thiết bị
 pub async fn get_devices(
     app_state: web::Data<Arc<Mutex<AppState>>>,
 ) -> impl Responder {
-    // Lấy danh sách thiết bị từ state
-    let state = app_state.lock().await;
-    let devices = state.devices.clone();
+    // Không giữ lock AppState trong lúc chờ hub
+    let hub_connection = app_state.lock().await.hub_connection.clone();
+
+    // Lấy danh sách thiết bị mới nhất từ hub nếu đang kết nối
+    let hub_devices = match hub_connection {
+        Some(hub_connection) if hub_connection.is_connected() => {
+            match hub_connection.invoke::<Vec<Device>>(HUB_LIST_DEVICES.to_string()).await {
+                Ok(devices) => Some(devices),
+                Err(e) => {
+                    println!("Không lấy được danh sách thiết bị từ hub: {}", e);
+                    SignalRHandler::refresh_on_auth_error(app_state.get_ref().clone(), &e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let is_cached = hub_devices.is_none();
+    let mut state = app_state.lock().await;
+
+    if let Some(devices) = hub_devices {
+        state.devices = merge_devices(&state.devices, devices);
+    }
+
+    let message = if is_cached {
+        "Không kết nối được hub, trả về danh sách thiết bị đã lưu"
+    } else {
+        "Lấy danh sách thiết bị thành công"
+    };
     
     // Trả về response
     let response = ApiResponseV2 {
         status_code: 200,
-        message: "Lấy danh sách thiết bị thành công".to_string(),
+        message: message.to_string(),
         success: true,
-        data: Some(devices),
+        data: Some(DeviceList {
+            devices: state.devices.clone(),
+            is_cached,
+        }),
     };
     
     HttpResponse::Ok().json(response)
 }
 
+// Gộp danh sách thiết bị từ hub, giữ trạng thái kết nối local của các thiết bị đã biết
+// Thiết bị được hub liệt kê được coi là vừa thấy, nên giữ last_seen mới và bỏ trạng thái Offline local
+fn merge_devices(local: &[Device], hub_devices: Vec<Device>) -> Vec<Device> {
+    hub_devices
+        .into_iter()
+        .map(|mut device| {
+            if let Some(known) = local.iter().find(|d| d.device_id == device.device_id) {
+                if known.status != DeviceStatus::Offline {
+                    device.status = known.status.clone();
+                }
+            }
+
+            device
+        })
+        .collect()
+}
+
 // Endpoint để kết nối đến thiết bị
 pub async fn connect_device(
     req: web::Json<ConnectDeviceRequest>,
@@ -154,15 +352,17 @@ pub async fn connect_device(
 ) -> impl Responder {
     let device_id = &req.device_id;
     
-    let mut state = app_state.lock().await;
-    
-    // Kiểm tra xem thiết bị có tồn tại không
-    let device_index = state.devices.iter().position(|d| d.device_id == *device_id);
-    
-    if let Some(index) = device_index {
+    // Kiểm tra thiết bị và lấy client hub, không giữ lock AppState trong lúc chờ hub
+    let hub_connection = {
+        let state = app_state.lock().await;
+
+        // Kiểm tra xem thiết bị có tồn tại không
+        let device = match state.devices.iter().find(|d| d.device_id == *device_id) {
+            Some(device) => device,
+            None => return device_not_found(),
+        };
+
         // Kiểm tra xem thiết bị đã kết nối chưa
-        let device = &state.devices[index];
-        
         if device.status == DeviceStatus::Connected {
             let response = ApiResponseV2::<()> {
                 status_code: 400,
@@ -174,27 +374,300 @@ pub async fn connect_device(
             return HttpResponse::BadRequest().json(response);
         }
         
-        // Cập nhật trạng thái thiết bị (TODO: thêm logic kết nối thực tế)
-        state.devices[index].status = DeviceStatus::Connected;
-        
-        // Trả về response thành công
-        let response = ApiResponseV2 {
-            status_code: 200,
-            message: "Kết nối đến thiết bị thành công".to_string(),
-            success: true,
-            data: Some(state.devices[index].clone()),
+        match state.hub_connection.as_ref() {
+            Some(hub_connection) => hub_connection.clone(),
+            None => {
+                let response = ApiResponseV2::<()> {
+                    status_code: 401,
+                    message: "Chưa kết nối đến SignalR".to_string(),
+                    success: false,
+                    data: None,
+                };
+
+                return HttpResponse::Unauthorized().json(response);
+            }
+        }
+    };
+
+    // Yêu cầu hub kết nối thiết bị, chỉ tiếp tục khi hub trả về true
+    let accepted = hub_connection.invoke_with_args::<bool, _>(HUB_CONNECT_DEVICE.to_string(), |c| {
+        c.argument(device_id.clone());
+    }).await;
+
+    match accepted {
+        Ok(true) => {}
+        Ok(false) => {
+            let response = ApiResponseV2::<()> {
+                status_code: 409,
+                message: "Thiết bị từ chối kết nối".to_string(),
+                success: false,
+                data: None,
+            };
+
+            return HttpResponse::Conflict().json(response);
+        }
+        Err(e) => {
+            SignalRHandler::refresh_on_auth_error(app_state.get_ref().clone(), &e);
+
+            let response = ApiResponseV2::<()> {
+                status_code: 502,
+                message: format!("Lỗi khi yêu cầu kết nối thiết bị qua SignalR: {}", e),
+                success: false,
+                data: None,
+            };
+
+            return HttpResponse::BadGateway().json(response);
+        }
+    }
+
+    // Tạo peer connection và trao đổi SDP với thiết bị qua SignalR
+    let peer_connection = match create_peer_connection(device_id, &hub_connection).await {
+        Ok(peer_connection) => peer_connection,
+        Err(e) => {
+            let response = ApiResponseV2::<()> {
+                status_code: 500,
+                message: format!("Không thể kết nối WebRTC đến thiết bị: {}", e),
+                success: false,
+                data: None,
+            };
+
+            return HttpResponse::InternalServerError().json(response);
+        }
+    };
+
+    // Lock lại chỉ để ghi kết quả, thiết bị có thể đã bị xoá khỏi danh sách trong lúc chờ
+    let (device, previous) = {
+        let mut state = app_state.lock().await;
+
+        match state.devices.iter().position(|d| d.device_id == *device_id) {
+            Some(index) => {
+                let previous = state.peer_connection.replace(peer_connection);
+                state.devices[index].status = DeviceStatus::Connected;
+
+                (Some(state.devices[index].clone()), previous)
+            }
+            None => (None, Some(peer_connection)),
+        }
+    };
+
+    // Đóng peer connection cũ (hoặc peer connection mới nếu thiết bị không còn) ngoài lock
+    if let Some(previous) = previous {
+        let _ = previous.close().await;
+    }
+
+    match device {
+        Some(device) => {
+            // Trả về response thành công
+            let response = ApiResponseV2 {
+                status_code: 200,
+                message: "Kết nối đến thiết bị thành công".to_string(),
+                success: true,
+                data: Some(device),
+            };
+            
+            HttpResponse::Ok().json(response)
+        }
+        None => device_not_found(),
+    }
+}
+
+// Response khi thiết bị không tồn tại
+fn device_not_found() -> HttpResponse {
+    let response = ApiResponseV2::<()> {
+        status_code: 404,
+        message: "Không tìm thấy thiết bị".to_string(),
+        success: false,
+        data: None,
+    };
+    
+    HttpResponse::NotFound().json(response)
+}
+
+// Endpoint để ngắt kết nối thiết bị
+pub async fn disconnect_device(
+    req: web::Json<DisconnectDeviceRequest>,
+    app_state: web::Data<Arc<Mutex<AppState>>>,
+) -> impl Responder {
+    let device_id = &req.device_id;
+    
+    // Kiểm tra thiết bị và lấy client hub, không giữ lock AppState trong lúc chờ hub
+    let hub_connection = {
+        let state = app_state.lock().await;
+
+        // Kiểm tra xem thiết bị có tồn tại không
+        let device = match state.devices.iter().find(|d| d.device_id == *device_id) {
+            Some(device) => device,
+            None => return device_not_found(),
         };
+
+        // Kiểm tra xem thiết bị có đang kết nối không
+        if device.status != DeviceStatus::Connected {
+            let response = ApiResponseV2::<()> {
+                status_code: 400,
+                message: "Thiết bị chưa được kết nối".to_string(),
+                success: false,
+                data: None,
+            };
+            
+            return HttpResponse::BadRequest().json(response);
+        }
         
-        HttpResponse::Ok().json(response)
-    } else {
-        // Thiết bị không tồn tại
+        match state.hub_connection.as_ref() {
+            Some(hub_connection) => hub_connection.clone(),
+            None => {
+                let response = ApiResponseV2::<()> {
+                    status_code: 401,
+                    message: "Chưa kết nối đến SignalR".to_string(),
+                    success: false,
+                    data: None,
+                };
+
+                return HttpResponse::Unauthorized().json(response);
+            }
+        }
+    };
+
+    // Yêu cầu hub giải phóng thiết bị
+    let released = hub_connection.send_and_confirm_with_args(HUB_DISCONNECT_DEVICE.to_string(), |c| {
+        c.argument(device_id.clone());
+    }).await;
+
+    if let Err(e) = released {
+        SignalRHandler::refresh_on_auth_error(app_state.get_ref().clone(), &e);
+
         let response = ApiResponseV2::<()> {
-            status_code: 404,
-            message: "Không tìm thấy thiết bị".to_string(),
+            status_code: 502,
+            message: format!("Lỗi khi yêu cầu ngắt kết nối thiết bị qua SignalR: {}", e),
             success: false,
             data: None,
         };
-        
-        HttpResponse::NotFound().json(response)
+
+        return HttpResponse::BadGateway().json(response);
     }
-} 
\ No newline at end of file
+
+    // Lock lại chỉ để ghi kết quả, peer connection của thiết bị được đóng ngoài lock
+    let (device, peer_connection) = {
+        let mut state = app_state.lock().await;
+
+        let peer_connection = if state.peer_connection.as_ref().map_or(false, |p| p.device_id == *device_id) {
+            state.peer_connection.take()
+        } else {
+            None
+        };
+
+        let device = state.devices.iter_mut().find(|d| d.device_id == *device_id).map(|device| {
+            device.status = DeviceStatus::Disconnected;
+            device.clone()
+        });
+
+        (device, peer_connection)
+    };
+
+    // Đóng peer connection của thiết bị nếu có
+    if let Some(peer_connection) = peer_connection {
+        let _ = peer_connection.close().await;
+    }
+
+    match device {
+        Some(device) => {
+            // Trả về response thành công
+            let response = ApiResponseV2 {
+                status_code: 200,
+                message: "Ngắt kết nối thiết bị thành công".to_string(),
+                success: true,
+                data: Some(device),
+            };
+            
+            HttpResponse::Ok().json(response)
+        }
+        None => device_not_found(),
+    }
+}
+
+// Endpoint để operator đặt trạng thái thiết bị trực tiếp, không gọi hub
+pub async fn update_device_status(
+    path: web::Path<String>,
+    req: web::Json<UpdateDeviceStatusRequest>,
+    app_state: web::Data<Arc<Mutex<AppState>>>,
+) -> impl Responder {
+    let device_id = path.into_inner();
+    let target = req.into_inner().status;
+
+    let mut state = app_state.lock().await;
+
+    // Kiểm tra xem thiết bị có tồn tại không
+    let device_index = state.devices.iter().position(|d| d.device_id == device_id);
+
+    let index = match device_index {
+        Some(index) => index,
+        None => {
+            let response = ApiResponseV2::<()> {
+                status_code: 404,
+                message: "Không tìm thấy thiết bị".to_string(),
+                success: false,
+                data: None,
+            };
+
+            return HttpResponse::NotFound().json(response);
+        }
+    };
+
+    let current = state.devices[index].status.clone();
+
+    if !current.can_force(&target) {
+        let response = ApiResponseV2::<()> {
+            status_code: 409,
+            message: format!("Không thể chuyển thiết bị từ {:?} sang {:?}, hãy dùng /connect-device", current, target),
+            success: false,
+            data: None,
+        };
+
+        return HttpResponse::Conflict().json(response);
+    }
+
+    // Thiết bị không còn Connected thì peer connection của nó không còn dùng được
+    let mut stale_peer_connection = None;
+    if current == DeviceStatus::Connected && target != DeviceStatus::Connected
+        && state.peer_connection.as_ref().map_or(false, |p| p.device_id == device_id) {
+        stale_peer_connection = state.peer_connection.take();
+    }
+
+    // Thiết bị được đặt online lại không bị sweeper chuyển về Offline ngay
+    if current == DeviceStatus::Offline && target != DeviceStatus::Offline {
+        state.devices[index].last_seen = std::time::Instant::now();
+    }
+
+    state.devices[index].status = target;
+
+    let response = ApiResponseV2 {
+        status_code: 200,
+        message: "Cập nhật trạng thái thiết bị thành công".to_string(),
+        success: true,
+        data: Some(state.devices[index].clone()),
+    };
+
+    // Không giữ lock AppState trong lúc đóng peer connection
+    drop(state);
+
+    if let Some(peer_connection) = stale_peer_connection {
+        let _ = peer_connection.close().await;
+    }
+
+    HttpResponse::Ok().json(response)
+}
+
+// Tạo peer connection, mở data channel và gửi SDP offer qua hub.
+// SDP answer và ICE candidate của thiết bị được nhận qua callback ANSWER/ICE_CANDIDATE
+async fn create_peer_connection(
+    device_id: &str,
+    hub_connection: &SignalRClient,
+) -> Result<PeerConnection, Box<dyn std::error::Error>> {
+    let mut peer_connection = PeerConnection::new(device_id).await?;
+    peer_connection.open_data_channel("control").await?;
+    peer_connection.trickle_ice(hub_connection);
+
+    let sdp = peer_connection.create_offer().await?;
+    SignalRHandler::signal_offer(hub_connection, device_id, sdp).await?;
+
+    Ok(peer_connection)
+}
@@ -1,6 +1,7 @@
 mod models;
 mod middleware;
 mod handlers;
+mod peer_connection;
 mod signalr_handler;
 
 use actix_web::{web, App, HttpServer};
@@ -13,16 +14,20 @@ use signalr_handler::SignalRHandler;
 
 // URL backend
 const BACKEND_URL: &str = "https://api.maxcloudphone.com";
+// Đường dẫn lấy JWT mới từ refresh token
+const REFRESH_TOKEN_PATH: &str = "/Octopus/refresh-token";
+// URL SignalR hub
+const HUB_URL: &str = "wss://api.maxcloudphone.com:443/deviceRHub?type=client";
 
 async fn connect_to_signalr(token: &str) -> Result<SignalRClient, Box<dyn std::error::Error>> {
     println!("Connecting to SignalR hub...");
     println!("URL: {}", BACKEND_URL);
     
     let client = SignalRClient::connect_with("api.maxcloudphone.com", "deviceRHub", |c| {
-        c.with_port(443);
-        c.secure();  // Sử dụng HTTPS/WSS
-        c.with_query_param("type".to_string(), "client".to_string());
+        c.with_url(HUB_URL);  // Sử dụng WSS, cổng 443 và query type=client
+        c.dangerous_accept_invalid_certs();  // Giống cấu hình reqwest khi login
         c.with_access_token(token.to_string());
+        c.with_http_client(handlers::create_http_client());  // Dùng chung cấu hình TLS với login
     }).await?;
 
     println!("Connected to SignalR successfully!");
@@ -34,10 +39,12 @@ async fn main() -> std::io::Result<()> {
     println!("Khởi động server tại http://localhost:1510");
 
     let state = Arc::new(Mutex::new(AppState::new()));
-    let excluded_routes = vec!["/hello".to_string(), "/login".to_string()];
+    let excluded_routes = vec!["/hello".to_string(), "/login".to_string(), "/logout".to_string(), "/health".to_string()];
 
     // Khởi động task theo dõi kết nối SignalR
     SignalRHandler::start_reconnection_monitor(state.clone());
+    // Khởi động task chuyển thiết bị không còn heartbeat sang Offline
+    SignalRHandler::start_presence_sweeper(state.clone());
 
     HttpServer::new(move || {
         App::new()
@@ -47,9 +54,14 @@ async fn main() -> std::io::Result<()> {
                 app_state: state.clone(),
             })
             .service(web::resource("/hello").route(web::get().to(handlers::hello)))
+            .service(web::resource("/health").route(web::get().to(handlers::health)))
             .service(web::resource("/login").route(web::get().to(handlers::login)))
+            .service(web::resource("/logout").route(web::post().to(handlers::logout)))
+            .service(web::resource("/events").route(web::get().to(handlers::events)))
             .service(web::resource("/get-devices").route(web::get().to(handlers::get_devices)))
             .service(web::resource("/connect-device").route(web::post().to(handlers::connect_device)))
+            .service(web::resource("/disconnect-device").route(web::post().to(handlers::disconnect_device)))
+            .service(web::resource("/device/{id}/status").route(web::patch().to(handlers::update_device_status)))
     })
     .bind("127.0.0.1:1510")?
     .run()
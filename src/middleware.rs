@@ -7,60 +7,34 @@ use std::{
     pin::Pin,
     sync::Arc,
 };
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::Deserialize;
 use tokio::sync::Mutex;
 use crate::models::AppState;
 
-// Middleware xác thực
-pub struct AuthMiddleware<S> {
-    service: S,
-}
-
-// Factory để tạo AuthMiddleware
-pub struct AuthMiddlewareFactory {
-    pub exclude_routes: Vec<String>,
-    pub app_state: Arc<Mutex<AppState>>,
-}
-
-// Cài đặt Transform trait cho AuthMiddlewareFactory
-impl<S, B> Transform<S, ServiceRequest> for AuthMiddlewareFactory
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = actix_web::Error;
-    type Transform = AuthMiddleware<S>;
-    type InitError = ();
-    type Future = Ready<Result<Self::Transform, Self::InitError>>;
-
-    fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthMiddleware { service }))
-    }
+// Các claim cần kiểm tra trong JWT
+#[derive(Deserialize)]
+struct Claims {
+    #[allow(dead_code)]
+    exp: u64,
 }
 
-impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = actix_web::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
-
-    forward_ready!(service);
-
-    fn call(&self, req: ServiceRequest) -> Self::Future {
-        let fut = self.service.call(req);
-        Box::pin(async move {
-            // Lấy kết quả từ future
-            fut.await
-        })
-    }
+// Kiểm tra JWT còn hạn hay không.
+// Client không có secret của backend nên chỉ kiểm tra claim exp, chữ ký do backend xác thực
+fn validate_jwt(token: &str) -> Result<(), String> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = true;
+    // Token của backend có claim aud nhưng client không biết audience mong đợi, bỏ qua kiểm tra aud
+    validation.validate_aud = false;
+
+    decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
-// Đổi tên từ JwtAuthMiddleware thành AuthMiddleware
+// Middleware xác thực: yêu cầu đã đăng nhập, JWT còn hạn và kết nối SignalR còn sống.
+// Các route trong exclude_routes được bỏ qua
 pub struct AuthenticationMiddleware {
     pub exclude_routes: Vec<String>,
     pub app_state: Arc<Mutex<AppState>>,
@@ -120,16 +94,52 @@ where
         let fut = self.service.call(req);
 
         Box::pin(async move {
-            // Chỉ kiểm tra xem đã đăng nhập chưa (có phiên làm việc chưa)
-            // không cần xác thực JWT
+            // Kiểm tra xem đã đăng nhập chưa (có phiên làm việc chưa)
             let state = app_state.lock().await;
             if state.hub_connection.is_none() {
                 return Err(ErrorUnauthorized("Bạn cần đăng nhập để truy cập"));
             }
+
+            // Kiểm tra JWT còn hạn
+            match state.jwt_token.as_deref() {
+                Some(token) => {
+                    if let Err(e) = validate_jwt(token) {
+                        return Err(ErrorUnauthorized(format!("Phiên đăng nhập đã hết hạn, vui lòng đăng nhập lại: {}", e)));
+                    }
+                }
+                None => return Err(ErrorUnauthorized("Bạn cần đăng nhập để truy cập")),
+            }
+
+            // Kiểm tra kết nối SignalR còn sống hay đã bị ngắt
+            let is_connected = state.hub_connection.as_ref().map_or(false, |c| c.is_connected());
+            if !is_connected {
+                return Err(ErrorUnauthorized("Kết nối SignalR đã bị ngắt, vui lòng đăng nhập lại"));
+            }
             
             // Tiếp tục xử lý request
             drop(state);
             fut.await
         })
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::validate_jwt;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn token(exp: u64) -> String {
+        let claims = serde_json::json!({ "sub": "user-1", "aud": "maxcloud-api", "exp": exp });
+
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(b"backend-secret")).unwrap()
+    }
+
+    #[test]
+    fn test_validate_jwt_with_audience() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        assert!(validate_jwt(&token(now + 3600)).is_ok());
+        assert!(validate_jwt(&token(now - 3600)).is_err());
+    }
+}
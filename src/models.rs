@@ -1,30 +1,60 @@
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use signalr_client::SignalRClient;
+use tokio::sync::broadcast;
+use crate::peer_connection::PeerConnection;
 
-// Định nghĩa kiểu tạm thời cho PeerConnection (sẽ thay thế sau)
-pub type PeerConnection = Option<()>;
+// Số message tối đa giữ lại cho client SSE đọc chậm
+const MESSAGE_CHANNEL_CAPACITY: usize = 100;
 
 // Định nghĩa trạng thái thiết bị
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub enum DeviceStatus {
     Connected,
     Disconnected,
+    #[default]
     Offline,
 }
 
+impl DeviceStatus {
+    // Trạng thái có thể đặt trực tiếp không qua hub, Connected chỉ đạt được qua /connect-device
+    pub fn can_force(&self, target: &DeviceStatus) -> bool {
+        match target {
+            _ if self == target => true,
+            DeviceStatus::Connected => false,
+            DeviceStatus::Disconnected | DeviceStatus::Offline => true,
+        }
+    }
+}
+
 // Định nghĩa struct thiết bị
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
     pub device_id: String,
+    // Hub có thể không gửi trạng thái, mặc định là Offline
+    #[serde(default)]
     pub status: DeviceStatus,
+    // Lần cuối hub báo về thiết bị, không gửi cho client
+    #[serde(skip, default = "Instant::now")]
+    pub last_seen: Instant,
+}
+
+// Danh sách thiết bị trả về cho client, is_cached = true khi không lấy được từ hub
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceList {
+    pub devices: Vec<Device>,
+    pub is_cached: bool,
 }
 
 // Định nghĩa struct AppState để lưu trạng thái ứng dụng
 pub struct AppState {
-    pub peer_connection: PeerConnection,
+    pub peer_connection: Option<PeerConnection>,
     pub hub_connection: Option<SignalRClient>,
     pub jwt_token: Option<String>,
+    pub refresh_token: Option<String>,
     pub devices: Vec<Device>,
+    // Kênh phát các MESSAGE từ hub tới các client SSE
+    pub message_sender: broadcast::Sender<String>,
 }
 
 // Định nghĩa struct cho phản hồi đăng nhập
@@ -65,32 +95,74 @@ pub struct ApiResponseV2<T> {
     pub data: Option<T>,
 }
 
+// Trạng thái kết nối hub trả về cho /health
+#[derive(Serialize)]
+pub struct HubHealth {
+    pub connected: bool,
+    // Thời gian từ lúc gửi ping tới message kế tiếp của server (ms)
+    pub rtt_ms: Option<u128>,
+    // Thời gian từ message cuối cùng của server (ms)
+    pub last_message_ms_ago: Option<u128>,
+    // Số invocation/stream đang chờ hub trả lời (không tính callback)
+    pub pending_invocations: usize,
+}
+
 // Struct cho connect device request
 #[derive(Deserialize)]
 pub struct ConnectDeviceRequest {
     pub device_id: String,
 }
 
+// Struct cho disconnect device request
+#[derive(Deserialize)]
+pub struct DisconnectDeviceRequest {
+    pub device_id: String,
+}
+
+// Struct cho request đặt trạng thái thiết bị trực tiếp
+#[derive(Deserialize)]
+pub struct UpdateDeviceStatusRequest {
+    pub status: DeviceStatus,
+}
+
 impl AppState {
     pub fn new() -> Self {
         AppState {
             peer_connection: None,
             hub_connection: None,
             jwt_token: None,
-            devices: vec![
-                Device {
-                    device_id: "device001".to_string(),
-                    status: DeviceStatus::Offline,
-                },
-                Device {
-                    device_id: "device002".to_string(),
-                    status: DeviceStatus::Offline,
-                },
-                Device {
-                    device_id: "device003".to_string(), 
-                    status: DeviceStatus::Offline,
-                },
-            ],
+            refresh_token: None,
+            // Danh sách thiết bị được lấy từ hub qua get_devices
+            devices: Vec::new(),
+            message_sender: broadcast::channel(MESSAGE_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    // Cập nhật last_seen khi hub báo về thiết bị, thiết bị Offline được xem là online trở lại
+    pub fn mark_device_seen(&mut self, device_id: &str) {
+        if let Some(device) = self.devices.iter_mut().find(|d| d.device_id == device_id) {
+            device.last_seen = Instant::now();
+
+            if device.status == DeviceStatus::Offline {
+                device.status = DeviceStatus::Disconnected;
+            }
         }
     }
+
+    // Chuyển các thiết bị không được hub báo về quá ttl sang Offline, trả về các thiết bị bị chuyển
+    // Thiết bị đang Connected được bỏ qua vì peer connection của nó vẫn mở, chỉ ngắt kết nối mới đóng được
+    pub fn expire_devices(&mut self, ttl: Duration) -> Vec<String> {
+        let mut expired = Vec::new();
+
+        for device in self.devices.iter_mut() {
+            let expirable = device.status != DeviceStatus::Offline && device.status != DeviceStatus::Connected;
+
+            if expirable && device.last_seen.elapsed() > ttl {
+                device.status = DeviceStatus::Offline;
+                expired.push(device.device_id.clone());
+            }
+        }
+
+        expired
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use signalr_client::SignalRClient;
+use webrtc::api::interceptor_registry::register_default_interceptors;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::api::APIBuilder;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::ice_transport::ice_server::RTCIceServer;
+use webrtc::interceptor::registry::Registry;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::signalr_handler::SignalRHandler;
+
+// STUN server dùng để thu thập ICE candidate
+const STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+
+// Kết nối WebRTC tới một thiết bị
+#[derive(Clone)]
+pub struct PeerConnection {
+    pub device_id: String,
+    connection: Arc<RTCPeerConnection>,
+    data_channel: Option<Arc<RTCDataChannel>>,
+}
+
+impl PeerConnection {
+    // Tạo peer connection mới với cấu hình mặc định
+    pub async fn new(device_id: &str) -> Result<Self, webrtc::Error> {
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs()?;
+
+        let mut registry = Registry::new();
+        registry = register_default_interceptors(registry, &mut media_engine)?;
+
+        let api = APIBuilder::new()
+            .with_media_engine(media_engine)
+            .with_interceptor_registry(registry)
+            .build();
+
+        let config = RTCConfiguration {
+            ice_servers: vec![RTCIceServer {
+                urls: vec![STUN_SERVER.to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let connection = api.new_peer_connection(config).await?;
+
+        Ok(PeerConnection {
+            device_id: device_id.to_string(),
+            connection: Arc::new(connection),
+            data_channel: None,
+        })
+    }
+
+    // Tạo SDP offer, ICE candidate được gửi dần qua trickle_ice
+    pub async fn create_offer(&self) -> Result<String, webrtc::Error> {
+        let offer = self.connection.create_offer(None).await?;
+        let sdp = offer.sdp.clone();
+
+        self.connection.set_local_description(offer).await?;
+
+        Ok(sdp)
+    }
+
+    // Áp dụng SDP answer nhận được từ thiết bị
+    pub async fn set_remote_answer(&self, sdp: String) -> Result<(), webrtc::Error> {
+        let answer = RTCSessionDescription::answer(sdp)?;
+
+        self.connection.set_remote_description(answer).await
+    }
+
+    // Mở data channel, phải gọi trước khi tạo offer
+    pub async fn open_data_channel(&mut self, label: &str) -> Result<Arc<RTCDataChannel>, webrtc::Error> {
+        let channel = self.connection.create_data_channel(label, None).await?;
+        self.data_channel = Some(channel.clone());
+
+        Ok(channel)
+    }
+
+    pub fn data_channel(&self) -> Option<Arc<RTCDataChannel>> {
+        self.data_channel.clone()
+    }
+
+    // Thêm ICE candidate nhận được từ thiết bị
+    pub async fn add_ice_candidate(&self, candidate: RTCIceCandidateInit) -> Result<(), webrtc::Error> {
+        self.connection.add_ice_candidate(candidate).await
+    }
+
+    // Gửi từng ICE candidate local tới thiết bị qua SignalR ngay khi thu thập được
+    pub fn trickle_ice(&self, hub_connection: &SignalRClient) {
+        let hub_connection = hub_connection.clone();
+        let device_id = self.device_id.clone();
+
+        self.connection.on_ice_candidate(Box::new(move |candidate| {
+            let hub_connection = hub_connection.clone();
+            let device_id = device_id.clone();
+
+            Box::pin(async move {
+                // None nghĩa là đã thu thập xong
+                if let Some(candidate) = candidate {
+                    match candidate.to_json() {
+                        Ok(init) => {
+                            if let Err(e) = SignalRHandler::signal_ice_candidate(&hub_connection, &device_id, init).await {
+                                println!("Không thể gửi ICE candidate: {}", e);
+                            }
+                        }
+                        Err(e) => println!("ICE candidate không hợp lệ: {}", e),
+                    }
+                }
+            })
+        }));
+    }
+
+    pub async fn close(&self) -> Result<(), webrtc::Error> {
+        self.connection.close().await
+    }
+}
@@ -0,0 +1,177 @@
+//! A blocking facade over [`SignalRClient`] for callers outside of an async context.
+//!
+//! The blocking client owns a multi-threaded Tokio runtime: every call is executed with `Runtime::block_on`,
+//! while the receiving loop and callbacks keep running on the runtime's worker threads between calls.
+//! The blocking methods must not be called from within an async runtime, as `block_on` panics there.
+
+use std::{pin::Pin, sync::Arc};
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{execution::{ArgumentConfiguration, MaybeSend}, ConnectionConfiguration, SignalRClient};
+
+/// A blocking SignalR client, wrapping the async [`SignalRClient`] with an internal runtime.
+///
+/// # Examples
+///
+/// ```
+/// let client = BlockingSignalRClient::connect_with("localhost", "test", |c| {
+///     c.with_port(5220);
+///     c.unsecure();
+/// }).unwrap();
+///
+/// let entity: TestEntity = client.invoke("SingleEntity".to_string()).unwrap();
+///
+/// for entity in client.enumerate::<TestEntity>("HundredEntities".to_string()) {
+///     info!("Received entity: {}, {}", entity.text, entity.number);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BlockingSignalRClient {
+    _client: SignalRClient,
+    _runtime: Arc<Runtime>,
+}
+
+impl BlockingSignalRClient {
+    /// Connects to the hub on the given domain, see [`SignalRClient::connect`].
+    pub fn connect(domain: &str, hub: &str) -> Result<Self, String> {
+        let runtime = BlockingSignalRClient::create_runtime()?;
+        let client = runtime.block_on(SignalRClient::connect(domain, hub))?;
+
+        Ok(BlockingSignalRClient::create(client, runtime))
+    }
+
+    /// Connects to the hub on the given domain with custom options, see [`SignalRClient::connect_with`].
+    pub fn connect_with<F>(domain: &str, hub: &str, options: F) -> Result<Self, String>
+        where F: FnMut(&mut ConnectionConfiguration)
+    {
+        let runtime = BlockingSignalRClient::create_runtime()?;
+        let client = runtime.block_on(SignalRClient::connect_with(domain, hub, options))?;
+
+        Ok(BlockingSignalRClient::create(client, runtime))
+    }
+
+    /// Connects to the hub with a prepared configuration, see [`SignalRClient::connect_config`].
+    pub fn connect_config(config: ConnectionConfiguration) -> Result<Self, String> {
+        let runtime = BlockingSignalRClient::create_runtime()?;
+        let client = runtime.block_on(SignalRClient::connect_config(config))?;
+
+        Ok(BlockingSignalRClient::create(client, runtime))
+    }
+
+    fn create_runtime() -> Result<Runtime, String> {
+        Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Cannot create the runtime of the blocking client: {}", e))
+    }
+
+    fn create(client: SignalRClient, runtime: Runtime) -> Self {
+        BlockingSignalRClient {
+            _client: client,
+            _runtime: Arc::new(runtime),
+        }
+    }
+
+    /// Returns the underlying async client, e.g. to register callbacks.
+    ///
+    /// Callbacks are executed on the worker threads of the internal runtime.
+    pub fn client(&self) -> &SignalRClient {
+        &self._client
+    }
+
+    /// Invokes a target method and blocks until the response arrives, see [`SignalRClient::invoke`].
+    pub fn invoke<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> Result<T, String> {
+        self._runtime.block_on(self._client.invoke(target))
+    }
+
+    /// Invokes a target method with custom arguments and blocks until the response arrives, see [`SignalRClient::invoke_with_args`].
+    pub fn invoke_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> Result<T, String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        self._runtime.block_on(self._client.invoke_with_args(target, configuration))
+    }
+
+    /// Calls a target method without waiting for the response, see [`SignalRClient::send`].
+    pub fn send(&self, target: String) -> Result<(), String> {
+        self._runtime.block_on(self._client.send(target))
+    }
+
+    /// Calls a target method with custom arguments without waiting for the response, see [`SignalRClient::send_with_args`].
+    pub fn send_with_args<F>(&self, target: String, configuration: F) -> Result<(), String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        self._runtime.block_on(self._client.send_with_args(target, configuration))
+    }
+
+    /// Calls a streaming target method and returns an iterator blocking for every item, see [`SignalRClient::enumerate`].
+    pub fn enumerate<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> BlockingIter<'_, T> {
+        let stream = self._runtime.block_on(self._client.enumerate::<T>(target));
+
+        BlockingIter::create(stream, &self._runtime)
+    }
+
+    /// Calls a streaming target method with custom arguments and returns an iterator blocking for every item, see [`SignalRClient::enumerate_with_args`].
+    pub fn enumerate_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> BlockingIter<'_, T>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        let stream = self._runtime.block_on(self._client.enumerate_with_args::<T, F>(target, configuration));
+
+        BlockingIter::create(stream, &self._runtime)
+    }
+
+    /// Checks whether the underlying connection is alive.
+    pub fn is_connected(&self) -> bool {
+        self._client.is_connected()
+    }
+
+    /// Reconnects the underlying connection, see [`SignalRClient::reconnect`].
+    pub fn reconnect(&mut self) -> Result<(), String> {
+        self._runtime.block_on(self._client.reconnect())
+    }
+
+    /// Disconnects the client.
+    pub fn disconnect(self) {
+        let _guard = self._runtime.enter();
+
+        self._client.disconnect();
+    }
+}
+
+/// An iterator over the items of a hub stream, blocking until the next item arrives.
+///
+/// Dropping the iterator before the stream ends, e.g. after `take` or `break`, cancels the invocation on the hub.
+pub struct BlockingIter<'a, T> {
+    _stream: Option<Pin<Box<dyn Stream<Item = T> + 'a>>>,
+    _runtime: &'a Runtime,
+}
+
+impl<'a, T> BlockingIter<'a, T> {
+    fn create(stream: impl Stream<Item = T> + 'a, runtime: &'a Runtime) -> Self {
+        BlockingIter {
+            _stream: Some(Box::pin(stream)),
+            _runtime: runtime,
+        }
+    }
+}
+
+impl<'a, T> Iterator for BlockingIter<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let stream = self._stream.as_mut()?;
+
+        self._runtime.block_on(stream.next())
+    }
+}
+
+impl<'a, T> Drop for BlockingIter<'a, T> {
+    fn drop(&mut self) {
+        // the stream spawns the cancellation of an unfinished invocation, which needs the runtime
+        let _guard = self._runtime.enter();
+
+        self._stream.take();
+    }
+}
@@ -1,24 +1,33 @@
-use futures::Stream;
-use log::info;
+use core::future::Future;
+use std::{fmt, time::Duration};
+use futures::{sink, Sink, Stream};
+use log::{error, info};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::communication::{Communication, CommunicationClient, HttpClient};
-use crate::protocol::invoke::Invocation;
-use crate::execution::{ArgumentConfiguration, CallbackHandler, Storage, StorageUnregistrationHandler, UpdatableActionStorage};
+use crate::communication::{Communication, CommunicationClient, HttpClient, Instant};
+use crate::completer::ManualFuture;
 
-use super::{ConnectionConfiguration, InvocationContext};
+#[cfg(feature = "test-util")]
+use crate::communication::MockCommunication;
+#[cfg(feature = "metrics")]
+use super::InvocationTiming;
+use crate::protocol::{invoke::Invocation, messages::ParserConfig, negotiate::Ping};
+use crate::execution::{ArgumentConfiguration, CallbackHandler, MaybeSend, MaybeSync, Storage, StorageUnregistrationHandler, UpdatableActionStorage};
+
+use super::{ConnectionConfiguration, ConnectionInfo, CountedStream, InvocationContext, InvocationSpan, InvocationStream, ItemStream, Metrics, TimeoutStream};
 
 /// A client for connecting to and interacting with a SignalR hub.
 ///
 /// The `SignalRClient` can be used to invoke methods on the hub, send messages, and register callbacks.
 /// The client can be cloned and used freely across different parts of your application.
+/// Calling the hub needs a shared reference only, so the client can also be shared behind an `Arc` without a `Mutex`.
 ///
 /// # Examples
 ///
 /// ```
 /// // Connect to the SignalR server with custom configuration
-/// let mut client = SignalRClient::connect_with("localhost", "test", |c| {
+/// let client = SignalRClient::connect_with("localhost", "test", |c| {
 ///     c.with_port(5220); // Set the port to 5220
 ///     c.unsecure(); // Use an unsecure (HTTP) connection
 /// }).await.unwrap();
@@ -96,13 +105,14 @@ use super::{ConnectionConfiguration, InvocationContext};
 ///
 /// // Trigger the callbacks
 /// info!("Calling callback1");
-/// _ = client.send_with_args("TriggerEntityCallback".to_string(), |c| {
-///     c.argument("callback1".to_string());
+/// let triggered = client.send_and_confirm_with_args("TriggerEntityCallback".to_string(), |c| {
+///     c.str_arg("callback1");
 /// }).await;
+/// assert!(triggered.is_ok());
 ///
 /// info!("Calling callback2");
 /// let succ = client.invoke_with_args::<bool, _>("TriggerEntityResponse".to_string(), |c| {
-///     c.argument("callback2".to_string());
+///     c.str_arg("callback2");
 /// }).await;
 /// assert!(succ.unwrap());
 ///
@@ -115,6 +125,16 @@ use super::{ConnectionConfiguration, InvocationContext};
 /// let elapsed = now.elapsed();
 /// info!("1 million entities fetched in: {:.2?}", elapsed);
 ///
+/// // Breaking out early drops the stream, the hub is told to stop producing the remaining items
+/// {
+///     let mut me = client.enumerate::<TestEntity>("MillionEntities".to_string()).await;
+///     while let Some(entity) = me.next().await {
+///         if entity.number == 10 {
+///             break;
+///         }
+///     }
+/// }
+///
 /// // Unregister the callbacks and disconnect the client
 /// c1.unregister();
 /// c2.unregister();
@@ -125,6 +145,19 @@ pub struct SignalRClient {
     _connection: CommunicationClient,
 }
 
+impl fmt::Debug for SignalRClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let configuration = self._connection.get_configuration();
+
+        f.debug_struct("SignalRClient")
+            .field("endpoint", &ConnectionConfiguration::redact_url(&configuration.get_endpoint()))
+            .field("transport", &configuration.get_transport())
+            .field("connected", &self.is_connected())
+            .field("pending_invocations", &self._actions.pending_count())
+            .finish()
+    }
+}
+
 impl Drop for SignalRClient {
     fn drop(&mut self) {
         self._connection.disconnect();
@@ -178,6 +211,59 @@ impl SignalRClient {
         SignalRClient::connect_internal(domain, hub, Some(options)).await
     }
 
+    /// Connects to a SignalR hub with a pre-built connection configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - A `ConnectionConfiguration` holding the connection properties.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - On success, returns an instance of `Self`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut config = ConnectionConfiguration::new(String::new(), String::new());
+    /// config.with_url("wss://localhost:5220/test");
+    ///
+    /// let client = SignalRClient::connect_config(config).await.unwrap();
+    /// ```
+    pub async fn connect_config(config: ConnectionConfiguration) -> Result<Self, String> {
+        SignalRClient::connect_configured(config).await
+    }
+
+    /// Creates a client on top of an in-memory transport instead of a websocket.
+    ///
+    /// Intended for deterministic tests of completion, stream and callback routing: frames fed into the mock are handled as if received from the hub,
+    /// and messages sent by the client can be inspected. Available with the `test-util` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection` - A `MockCommunication` that the client sends to and receives from.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - On success, returns an instance of `Self`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mock = MockCommunication::new();
+    /// let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    ///
+    /// mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"hello\"]}\u{001E}");
+    /// ```
+    #[cfg(feature = "test-util")]
+    pub fn from_communication(connection: MockCommunication) -> Result<Self, String> {
+        let storage = connection.get_storage()?;
+
+        Ok(SignalRClient {
+            _actions: storage,
+            _connection: CommunicationClient::Mock(connection),
+        })
+    }
+
     async fn connect_internal<F>(domain: &str, hub: &str, options: Option<F>) -> Result<Self, String>
         where F: FnMut(&mut ConnectionConfiguration)
     {
@@ -188,13 +274,45 @@ impl SignalRClient {
             (ops)(&mut config);
         }
 
+        SignalRClient::connect_configured(config).await
+    }
+
+    async fn connect_configured(config: ConnectionConfiguration) -> Result<Self, String> {
+        config.validate()?;
+
+        let timeout = config.get_connect_timeout();
+
+        SignalRClient::with_timeout(timeout, SignalRClient::establish(config)).await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn with_timeout<T>(timeout: Duration, future: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        match tokio::time::timeout(timeout, future).await {
+            Ok(res) => res,
+            Err(_) => Err("connect timed out".to_string()),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn with_timeout<T>(timeout: Duration, future: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        let delay = wasm_timer::Delay::new(timeout);
+
+        match futures::future::select(Box::pin(future), delay).await {
+            futures::future::Either::Left((res, _)) => res,
+            futures::future::Either::Right(_) => Err("connect timed out".to_string()),
+        }
+    }
+
+    async fn establish(config: ConnectionConfiguration) -> Result<Self, String> {
+        let storage = UpdatableActionStorage::with_options(config.get_invocation_id_prefix(), config.get_parser_config(), config.get_serializer_options());
         let result = HttpClient::negotiate(config).await;
 
         if result.is_ok() {
             // debug!("Negotiate response returned {:?}", result);
             let configuration = result.unwrap();
             info!("Negotiation successfull: {:?}", configuration);
-            let res = CommunicationClient::connect(&configuration).await;
+            // the storage is owned by the client, so callbacks survive reconnects of the underlying connection
+            let res = CommunicationClient::connect(&configuration, storage).await;
 
             if res.is_ok() {
                 let client  = res.unwrap();
@@ -220,10 +338,15 @@ impl SignalRClient {
 
     /// Registers a callback that can be called by the SignalR hub.
     ///
+    /// Several callbacks can be registered for the same target, each of them is invoked in the order of registration.
+    /// The handlers manage their own callback only, unregistering one keeps the others of the target registered.
+    /// If the hub awaits a result, only one of the callbacks should complete the invocation.
+    ///
     /// # Arguments
     ///
     /// * `target` - A `String` specifying the name of the target method to register the callback for.
-    /// * `callback` - A closure that takes an `InvocationContext` as an argument and defines the callback logic.
+    /// * `callback` - A closure that takes an `InvocationContext` as an argument and defines the callback logic. The closure may mutate its captured state, invocations of the same target are never run concurrently.
+    ///   On Tokio the closure runs on the task reading the connection, so it must be `Send`.
     ///
     /// # Returns
     ///
@@ -244,16 +367,133 @@ impl SignalRClient {
     /// // Unregister the callback when it's no longer needed
     /// handler.unregister();
     /// ```   
-    pub fn register(&mut self, target: String, callback: impl Fn(InvocationContext) + 'static) -> impl CallbackHandler
+    pub fn register(&self, target: String, callback: impl FnMut(InvocationContext) + MaybeSend + 'static) -> impl CallbackHandler
     {
         // debug!("CLIENT registering invocation callback to {}", &target);
-        self._actions.add_callback(target.clone(), callback, self.clone());
+        let id = self._actions.add_callback(target.clone(), callback, self.clone());
+
+        StorageUnregistrationHandler::new(self._actions.clone(), target.clone(), id)
+    }
+
+    /// Waits for the next invocation of a target by the SignalR hub.
+    ///
+    /// A one-shot callback is registered for the target and removed once the invocation arrives, or when the returned future is dropped,
+    /// e.g. by the losing branch of `tokio::select!`. Callbacks registered with `register` for the same target keep receiving the invocation too.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to wait for.
+    ///
+    /// # Returns
+    ///
+    /// * `InvocationContext` - The context of the invocation, holding its arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    ///
+    /// tokio::select! {
+    ///     ctx = client.wait_for("ANSWER".to_string()) => {
+    ///         let sdp = ctx.argument::<String>(1).unwrap();
+    ///         info!("Answer received: {}", sdp);
+    ///     }
+    ///     _ = tokio::time::sleep(Duration::from_secs(10)) => {
+    ///         warn!("No answer arrived");
+    ///     }
+    /// }
+    /// ```
+    pub async fn wait_for(&self, target: String) -> InvocationContext {
+        let (future, completer) = ManualFuture::<InvocationContext>::new();
+        let mut completer = Some(completer);
+
+        let handler = self.register(target, move |ctx| {
+            // invocations arriving before the callback is removed are ignored
+            if let Some(completer) = completer.take() {
+                completer.complete(ctx);
+            }
+        });
+
+        // the callback is removed even if the future is dropped before the invocation arrives
+        let _registration = OneShotRegistration(Some(handler));
+
+        future.await
+    }
+
+    /// Registers an async callback that can be called by the SignalR hub.
+    ///
+    /// The future returned by the closure is driven by the runtime (`tokio::spawn` or `spawn_local` on WASM), so it can await, e.g. `ctx.complete(...)`, without spawning manually.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to register the callback for.
+    /// * `callback` - A closure that takes an `InvocationContext` as an argument and returns the future running the callback logic.
+    ///
+    /// # Returns
+    ///
+    /// * `impl CallbackHandler` - Returns an implementation of `CallbackHandler` that can be used to manage the callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let handler = client.register_async("callback2".to_string(), |mut ctx| async move {
+    ///     if let Ok(entity) = ctx.argument::<TestEntity>(0) {
+    ///         let _ = ctx.complete(entity).await;
+    ///     }
+    /// });
+    /// ```
+    pub fn register_async<F, R>(&self, target: String, mut callback: F) -> impl CallbackHandler
+        where F: FnMut(InvocationContext) -> R + MaybeSend + 'static,
+              R: Future<Output = ()> + MaybeSend + 'static
+    {
+        self.register(target, move |ctx| InvocationContext::spawn(callback(ctx)))
+    }
+
+    /// Registers a stream producer that can be called by the SignalR hub with a stream invocation.
+    ///
+    /// When the hub asks the client for a stream, the closure creates the stream from the invocation context, its items are sent back to the hub followed by a completion (see `InvocationContext::stream_result`).
+    /// Producers share the targets with the callbacks, registering both for the same target invokes both, although the hub accepts a single answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to register the producer for.
+    /// * `producer` - A closure that takes an `InvocationContext` reference and returns the stream of results.
+    ///
+    /// # Returns
+    ///
+    /// * `impl CallbackHandler` - Returns an implementation of `CallbackHandler` that can be used to manage the producer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let handler = client.register_stream_producer("Counter".to_string(), |ctx| {
+    ///     let count = ctx.argument::<i32>(0).unwrap_or(0);
+    ///
+    ///     futures::stream::iter(0..count)
+    /// });
+    /// ```
+    pub fn register_stream_producer<F, S, T>(&self, target: String, mut producer: F) -> impl CallbackHandler
+        where F: FnMut(&InvocationContext) -> S + MaybeSend + 'static,
+              S: Stream<Item = T> + MaybeSend + 'static,
+              T: Serialize + MaybeSend + MaybeSync + 'static
+    {
+        self.register_async(target, move |mut ctx| {
+            let items = producer(&ctx);
 
-        StorageUnregistrationHandler::new(self._actions.clone(), target.clone())
+            async move {
+                if let Err(e) = ctx.stream_result(items).await {
+                    error!("Stream result cannot be sent: {}", e);
+                }
+            }
+        })
     }
 
     /// Invokes a specific target method on the SignalR hub and waits for the response.
     ///
+    /// When the hub fails the invocation, the headers of the completion, e.g. an error code, follow the error text as `[key=value, ...]`.
+    ///
     /// # Arguments
     ///
     /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
@@ -264,7 +504,7 @@ impl SignalRClient {
     ///
     /// # Type Parameters
     ///
-    /// * `T` - The type of the response, which must implement `DeserializeOwned` and `Unpin`.
+    /// * `T` - The type of the response, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
     ///
     /// # Examples
     ///
@@ -280,8 +520,8 @@ impl SignalRClient {
     ///     }
     /// }
     /// ```    
-    pub async fn invoke<T: 'static + DeserializeOwned + Unpin>(&mut self, target: String) -> Result<T, String> {
-        return self.invoke_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
+    pub async fn invoke<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> Result<T, String> {
+        return self.invoke_internal(target, None::<fn(&mut ArgumentConfiguration)>, None).await;
     }
 
     /// Invokes a specific target method on the SignalR hub with custom arguments and waits for the response.
@@ -297,7 +537,7 @@ impl SignalRClient {
     ///
     /// # Type Parameters
     ///
-    /// * `T` - The type of the response, which must implement `DeserializeOwned` and `Unpin`.
+    /// * `T` - The type of the response, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
     ///
     /// # Examples
     ///
@@ -321,35 +561,238 @@ impl SignalRClient {
     ///     }
     /// }
     /// ```    
-    pub async fn invoke_with_args<T: 'static + DeserializeOwned + Unpin, F>(&mut self, target: String, configuration: F) -> Result<T, String>
+    pub async fn invoke_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> Result<T, String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        return self.invoke_internal(target, Some(configuration), None).await;
+    }
+
+    /// Invokes a specific target method on the SignalR hub and waits for the response at most for the given time.
+    ///
+    /// When the timeout elapses, the invocation is removed and a completion arriving later is ignored.
+    /// If a timeout header is configured with `with_timeout_header`, the timeout is sent to the hub in milliseconds, so the hub can cancel the call as well.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
+    /// * `timeout` - A `Duration` specifying how long to wait for the response.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T, String>` - On success, returns the response of type `T`. On failure or timeout, returns an error message as a `String`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the response, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_timeout_header("timeout-ms".to_string());
+    /// }).await.unwrap();
+    /// let response = client.invoke_with_timeout::<TestEntity>("SingleEntity".to_string(), Duration::from_secs(5)).await;
+    /// ```
+    pub async fn invoke_with_timeout<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String, timeout: Duration) -> Result<T, String> {
+        self.invoke_internal(target, None::<fn(&mut ArgumentConfiguration)>, Some(timeout)).await
+    }
+
+    /// Invokes a specific target method on the SignalR hub with custom arguments and waits for the response at most for the given time.
+    ///
+    /// Behaves like `invoke_with_timeout`, including the timeout header sent when configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
+    /// * `timeout` - A `Duration` specifying how long to wait for the response.
+    /// * `configuration` - A mutable closure that allows the user to configure the arguments for the method call.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T, String>` - On success, returns the response of type `T`. On failure or timeout, returns an error message as a `String`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the response, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let response = client.invoke_with_args_and_timeout::<TestEntity, _>("PushEntity".to_string(), Duration::from_secs(5), |c| {
+    ///     c.argument(TestEntity {
+    ///         text: "entity1".to_string(),
+    ///         number: 200,
+    ///     });
+    /// }).await;
+    /// ```
+    pub async fn invoke_with_args_and_timeout<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, timeout: Duration, configuration: F) -> Result<T, String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        self.invoke_internal(target, Some(configuration), Some(timeout)).await
+    }
+
+    /// Invokes a hub method returning a list, e.g. `Task<List<T>>`, and waits for the whole list to arrive.
+    ///
+    /// The list arrives in a single completion, unlike the items of `enumerate`, which must be used for methods returning `IAsyncEnumerable<T>` or `ChannelReader<T>`.
+    /// A `null` result is returned as an empty list.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<T>, String>` - On success, returns the items of the list. On failure, returns an error message as a `String`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the list items, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response = client.invoke_array::<TestEntity>("EntityList".to_string()).await;
+    /// match response {
+    ///     Ok(entities) => {
+    ///         info!("Received {} entities", entities.len());
+    ///     }
+    ///     Err(e) => {
+    ///         error!("Failed to invoke method: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn invoke_array<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> Result<Vec<T>, String> {
+        let res = self.invoke_internal::<Option<Vec<T>>, _>(target, None::<fn(&mut ArgumentConfiguration)>, None).await;
+
+        res.map(|items| items.unwrap_or_default())
+    }
+
+    /// Invokes a hub method returning a list with custom arguments, and waits for the whole list to arrive.
+    ///
+    /// The list arrives in a single completion, unlike the items of `enumerate_with_args`.
+    /// A `null` result is returned as an empty list.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
+    /// * `configuration` - A mutable closure that allows the user to configure the arguments for the method call.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<T>, String>` - On success, returns the items of the list. On failure, returns an error message as a `String`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the list items, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response = client.invoke_array_with_args::<TestEntity, _>("EntitiesAbove".to_string(), |c| {
+    ///     c.argument(100);
+    /// }).await;
+    /// ```
+    pub async fn invoke_array_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> Result<Vec<T>, String>
         where F : FnMut(&mut ArgumentConfiguration)
     {
-        return self.invoke_internal(target, Some(configuration)).await;
+        let res = self.invoke_internal::<Option<Vec<T>>, _>(target, Some(configuration), None).await;
+
+        res.map(|items| items.unwrap_or_default())
+    }
+
+    /// Invokes a specific target method on the SignalR hub with raw JSON arguments and waits for the JSON response.
+    ///
+    /// Useful when the payload is not known at compile time, e.g. when forwarding arbitrary JSON.
+    /// The arguments are sent as they are, without going through `ArgumentConfiguration`.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to invoke on the hub.
+    /// * `arguments` - A `Vec<serde_json::Value>` holding the arguments of the hub method in order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<serde_json::Value, String>` - On success, returns the response as a JSON value. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response = client.invoke_json("PushEntity".to_string(), vec![serde_json::json!({ "text": "entity", "number": 1 })]).await;
+    /// match response {
+    ///     Ok(value) => {
+    ///         info!("Received: {}", value);
+    ///     }
+    ///     Err(e) => {
+    ///         error!("Failed to invoke method: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn invoke_json(&self, target: String, arguments: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {
+        let mut actions = self._actions.clone();
+        let invocation_id = actions.create_key(target.clone());
+        let ret = actions.add_invocation::<serde_json::Value>(invocation_id.clone());
+
+        let span = InvocationSpan::new(&target, &invocation_id, &self._connection.get_counters());
+        let mut invocation = Invocation::create_single(target);
+        invocation.with_invocation_id(invocation_id).with_raw_arguments(arguments);
+
+        span.run(async {
+            let res = self._connection.send(&invocation).await;
+
+            if res.is_ok() {
+                ret.await
+            } else {
+                Err(res.err().unwrap())
+            }
+        }).await
     }
 
-    async fn invoke_internal<T: 'static + DeserializeOwned + Unpin, F>(&mut self, target: String, configuration: Option<F>) -> Result<T, String>
+    async fn invoke_internal<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: Option<F>, timeout: Option<Duration>) -> Result<T, String>
         where F : FnMut(&mut ArgumentConfiguration)
     {
-        let invocation_id = self._actions.create_key(target.clone());
-        let ret = self._actions.add_invocation::<T>(invocation_id.clone());
+        let mut actions = self._actions.clone();
+        let invocation_id = actions.create_key(target.clone());
+        let ret = actions.add_invocation::<T>(invocation_id.clone());
+        let span = InvocationSpan::new(&target, &invocation_id, &self._connection.get_counters());
 
         let mut invocation = Invocation::create_single(target.clone());
-        invocation.with_invocation_id(invocation_id);
+        invocation.with_invocation_id(invocation_id.clone());
 
         if configuration.is_some() {
-            let mut args = ArgumentConfiguration::new(invocation);
+            let mut args = ArgumentConfiguration::new(invocation, self._actions.get_serializer_options());
             configuration.unwrap()(&mut args);
 
             invocation = args.build_invocation();
         }
 
-        let res = self._connection.send(&invocation).await;
-
-        if res.is_ok() {
-            Ok(ret.await)
-        } else {
-            Err(res.err().unwrap())
+        if let (Some(timeout), Some(header)) = (timeout, self._connection.get_configuration().get_timeout_header()) {
+            invocation.with_header(header, timeout.as_millis());
         }
+
+        span.run(async {
+            let res = self._connection.send(&invocation).await;
+
+            if res.is_err() {
+                return Err(res.err().unwrap());
+            }
+
+            match timeout {
+                Some(timeout) => {
+                    // the outer error is the elapsed timeout, the inner result is the completion of the hub
+                    let res = SignalRClient::with_timeout(timeout, async { Ok(ret.await) }).await;
+
+                    res.unwrap_or_else(|_| {
+                        actions.remove(invocation_id.clone());
+
+                        Err(format!("Invocation {} timed out after {:?}", invocation_id, timeout))
+                    })
+                },
+                None => ret.await,
+            }
+        }).await
     }
 
     /// Calls a specific target method on the SignalR hub without waiting for the response.
@@ -376,7 +819,7 @@ impl SignalRClient {
     ///     }
     /// }
     /// ```
-    pub async fn send(&mut self, target: String) -> Result<(), String>
+    pub async fn send(&self, target: String) -> Result<(), String>
     {
         return self.send_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
     }
@@ -397,7 +840,7 @@ impl SignalRClient {
     /// ```
     /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
     /// let result = client.send_with_args("TriggerEntityCallback".to_string(), |c| {
-    ///     c.argument("callback1".to_string());
+    ///     c.str_arg("callback1");
     /// }).await;
     /// match result {
     ///     Ok(_) => {
@@ -408,20 +851,44 @@ impl SignalRClient {
     ///     }
     /// }
     /// ```    
-    pub async fn send_with_args<F>(&mut self, target: String, configuration: F) -> Result<(), String>
+    pub async fn send_with_args<F>(&self, target: String, configuration: F) -> Result<(), String>
         where F : FnMut(&mut ArgumentConfiguration)
     {
         return self.send_internal(target, Some(configuration)).await;
     }
 
-    async fn send_internal<F>(&mut self, target: String, configuration: Option<F>) -> Result<(), String>
-        where F : FnMut(&mut ArgumentConfiguration)
+    /// Calls a specific target method on the SignalR hub with raw JSON arguments without waiting for the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    /// * `arguments` - A `Vec<serde_json::Value>` holding the arguments of the hub method in order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let result = client.send_json("TriggerEntityCallback".to_string(), vec![serde_json::json!("callback1")]).await;
+    /// ```
+    pub async fn send_json(&self, target: String, arguments: Vec<serde_json::Value>) -> Result<(), String> {
+        let mut invocation = Invocation::create_single(target);
+        invocation.with_raw_arguments(arguments);
+
+        self._connection.send(&invocation).await
+    }
+
+    async fn send_internal<F>(&self, target: String, configuration: Option<F>) -> Result<(), String>
+        where F : FnMut(&mut ArgumentConfiguration)
     {
         // debug!("CLIENT creating actual invocation data");
         let mut invocation = Invocation::create_single(target.clone());
 
         if configuration.is_some() {
-            let mut args = ArgumentConfiguration::new(invocation);
+            let mut args = ArgumentConfiguration::new(invocation, self._actions.get_serializer_options());
             configuration.unwrap()(&mut args);
 
             invocation = args.build_invocation();
@@ -431,7 +898,214 @@ impl SignalRClient {
         ret
     }
 
-    pub(crate) async fn send_direct<T: Serialize>(&mut self, data: T) -> Result<(), String>
+    /// Calls a specific target method on the SignalR hub and waits for the hub to acknowledge it.
+    ///
+    /// Unlike `send`, the call carries an invocation id, so the hub reports back when the method has been processed.
+    /// Any result returned by the hub method is ignored, which makes this method suitable for hub methods returning `Task` (void).
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, including errors reported by the hub, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let result = client.send_and_confirm("TriggerCallback".to_string()).await;
+    /// match result {
+    ///     Ok(_) => {
+    ///         info!("Method processed by the hub");
+    ///     }
+    ///     Err(e) => {
+    ///         error!("Hub failed to process the method: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn send_and_confirm(&self, target: String) -> Result<(), String>
+    {
+        return self.send_and_confirm_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
+    }
+
+    /// Calls a specific target method on the SignalR hub with custom arguments and waits for the hub to acknowledge it.
+    ///
+    /// Unlike `send_with_args`, the call carries an invocation id, so the hub reports back when the method has been processed.
+    /// Any result returned by the hub method is ignored, which makes this method suitable for hub methods returning `Task` (void).
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    /// * `configuration` - A closure that allows the user to configure the arguments for the method call.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, including errors reported by the hub, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let result = client.send_and_confirm_with_args("TriggerEntityCallback".to_string(), |c| {
+    ///     c.str_arg("callback1");
+    /// }).await;
+    /// match result {
+    ///     Ok(_) => {
+    ///         info!("Method processed by the hub");
+    ///     }
+    ///     Err(e) => {
+    ///         error!("Hub failed to process the method: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn send_and_confirm_with_args<F>(&self, target: String, configuration: F) -> Result<(), String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        return self.send_and_confirm_internal(target, Some(configuration)).await;
+    }
+
+    async fn send_and_confirm_internal<F>(&self, target: String, configuration: Option<F>) -> Result<(), String>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        let mut actions = self._actions.clone();
+        let invocation_id = actions.create_key(target.clone());
+        let ret = actions.add_confirmation(invocation_id.clone());
+        let span = InvocationSpan::new(&target, &invocation_id, &self._connection.get_counters());
+
+        let mut invocation = Invocation::create_single(target.clone());
+        invocation.with_invocation_id(invocation_id);
+
+        if configuration.is_some() {
+            let mut args = ArgumentConfiguration::new(invocation, self._actions.get_serializer_options());
+            configuration.unwrap()(&mut args);
+
+            invocation = args.build_invocation();
+        }
+
+        span.run(async {
+            let res = self._connection.send(&invocation).await;
+
+            if res.is_ok() {
+                ret.await
+            } else {
+                Err(res.err().unwrap())
+            }
+        }).await
+    }
+
+    /// Sends multiple invocations to the SignalR hub in a single websocket frame without waiting for the responses.
+    ///
+    /// The invocations are serialized one after the other, separated by the record separator of the protocol.
+    /// Batching reduces the number of socket writes when many messages are sent at once. The invocations are fire-and-forget,
+    /// completions sent back by the hub for invocations having an invocation id are not tracked.
+    ///
+    /// # Arguments
+    ///
+    /// * `invocations` - The invocations to send, in order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let invocations = events.iter().map(|event| {
+    ///     let mut invocation = Invocation::create_single("PushEntity");
+    ///     let _ = invocation.with_argument(event);
+    ///     invocation
+    /// });
+    /// let result = client.send_batch(invocations).await;
+    /// ```
+    pub async fn send_batch(&self, invocations: impl IntoIterator<Item = Invocation>) -> Result<(), String>
+    {
+        let invocations: Vec<Invocation> = invocations.into_iter().collect();
+
+        if invocations.is_empty() {
+            return Ok(());
+        }
+
+        self._connection.send_many(invocations).await
+    }
+
+    /// Creates a sink sending the invocations to the SignalR hub without waiting for the responses.
+    ///
+    /// Every invocation is sent as soon as the sink accepts it, so a stream of invocations can be forwarded to the hub using the `futures` combinators.
+    /// The invocations are fire-and-forget, completions sent back by the hub are not tracked.
+    ///
+    /// # Returns
+    ///
+    /// * `impl Sink<Invocation, Error = String>` - Returns a sink of invocations, which fails with the error of the first unsuccessful send.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let commands = futures::stream::iter(events).map(|event| {
+    ///     let mut invocation = Invocation::create_single("PushEntity");
+    ///     let _ = invocation.with_argument(event);
+    ///     Ok(invocation)
+    /// });
+    /// let result = commands.forward(client.invocation_sink()).await;
+    /// ```
+    pub fn invocation_sink(&self) -> impl Sink<Invocation, Error = String> {
+        sink::unfold(self.clone(), |client, invocation: Invocation| async move {
+            client.send_direct(invocation).await?;
+
+            Ok(client)
+        })
+    }
+
+    /// Sends an already constructed protocol message to the hub as is.
+    ///
+    /// Meant for custom message flows, e.g. completing an invocation of the hub with a `Completion` or streaming `StreamItem`s
+    /// outside of a callback. The message bypasses the invocation id bookkeeping of the client: results sent by the hub for
+    /// an invocation sent this way are not routed anywhere, and the hub may reject messages with unknown or duplicate invocation ids.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to send, which must implement `Serialize` and serialize to a valid SignalR protocol message.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let completion = Completion::create_result(invocation_id, TestEntity {
+    ///     text: "completed".to_string(),
+    ///     number: 123,
+    /// });
+    /// let result = client.send_message(completion).await;
+    /// ```
+    pub async fn send_message<T: Serialize>(&self, message: T) -> Result<(), String> {
+        self.send_direct(message).await
+    }
+
+    /// Writes out the messages buffered by the connection.
+    ///
+    /// Sending already flushes every message, so this is only needed after messages are queued without flushing,
+    /// e.g. before disconnecting gracefully. On WASM the messages are never buffered and this is a no-op.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, e.g. when the client is not connected, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// client.send("Notify".to_string()).await.unwrap();
+    /// client.flush().await.unwrap();
+    /// ```
+    pub async fn flush(&self) -> Result<(), String> {
+        self._connection.flush().await
+    }
+
+    pub(crate) async fn send_direct<T: Serialize>(&self, data: T) -> Result<(), String>
     {
         let ret = self._connection.send(&data).await;
         
@@ -441,6 +1115,7 @@ impl SignalRClient {
     /// Calls a specific target method on the SignalR hub and returns a stream for receiving data asynchronously.
     ///
     /// The target method on the hub should return an `IAsyncEnumerable` to send back data asynchronously.
+    /// The stream does not borrow the client, so the client can be used for other calls, or even dropped, while enumerating.
     ///
     /// # Arguments
     ///
@@ -448,11 +1123,11 @@ impl SignalRClient {
     ///
     /// # Returns
     ///
-    /// * `impl Stream<Item = T>` - Returns a stream of items of type `T`.
+    /// * `ItemStream<T>` - Returns a stream of items of type `T`.
     ///
     /// # Type Parameters
     ///
-    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned` and `Unpin`.
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
     ///
     /// # Examples
     ///
@@ -463,8 +1138,10 @@ impl SignalRClient {
     ///     info!("Received entity: {}, {}", entity.text, entity.number);
     /// }
     /// ```
-    pub async fn enumerate<T: 'static + DeserializeOwned + Unpin>(&mut self, target: String) -> impl Stream<Item = T> {
-        return self.enumerate_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
+    pub async fn enumerate<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> ItemStream<T> {
+        let stream = self.enumerate_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
+
+        ItemStream::new(stream)
     }
 
     /// Calls a specific target method on the SignalR hub with custom arguments and returns a stream for receiving data asynchronously.
@@ -478,51 +1155,698 @@ impl SignalRClient {
     ///
     /// # Returns
     ///
-    /// * `impl Stream<Item = T>` - Returns a stream of items of type `T`.
+    /// * `ItemStream<T>` - Returns a stream of items of type `T`.
     ///
     /// # Type Parameters
     ///
-    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned` and `Unpin`.
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
     ///
     /// # Examples
     ///
     /// ```
     /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
     /// let mut stream = client.enumerate_with_args::<TestEntity, _>("HundredEntities".to_string(), |c| {
-    ///     c.argument("some_argument".to_string());
+    ///     c.str_arg("some_argument");
     /// }).await;
     /// while let Some(entity) = stream.next().await {
     ///     info!("Received entity: {}, {}", entity.text, entity.number);
     /// }
     /// ```    
-    pub async fn enumerate_with_args<T: 'static + DeserializeOwned + Unpin, F>(&mut self, target: String, configuration: F) -> impl Stream<Item = T>
+    pub async fn enumerate_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> ItemStream<T>
         where F : FnMut(&mut ArgumentConfiguration)
     {
-        return self.enumerate_internal(target, Some(configuration)).await;
+        let stream = self.enumerate_internal(target, Some(configuration)).await;
+
+        ItemStream::new(stream)
+    }
+
+    /// Calls a specific target method on the SignalR hub and returns a stream for receiving data asynchronously, including the streaming error.
+    ///
+    /// Unlike `enumerate`, a stream faulted by the hub yields a final `Err` item with the error sent by the hub before the stream ends,
+    /// so the failure can be distinguished from a normal end of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `InvocationStream<T>` - Returns a stream of items of type `T`, ending with an error message if the hub failed.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut stream = client.try_enumerate::<TestEntity>("HundredEntities".to_string()).await;
+    /// while let Some(item) = stream.next().await {
+    ///     match item {
+    ///         Ok(entity) => info!("Received entity: {}, {}", entity.text, entity.number),
+    ///         Err(e) => error!("Streaming failed: {}", e),
+    ///     }
+    /// }
+    /// ```
+    pub async fn try_enumerate<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> InvocationStream<T> {
+        return self.enumerate_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
     }
 
-    async fn enumerate_internal<T: 'static + DeserializeOwned + Unpin, F>(&mut self, target: String, configuration: Option<F>) -> impl Stream<Item = T>
+    /// Calls a specific target method on the SignalR hub with custom arguments and returns a stream for receiving data asynchronously, including the streaming error.
+    ///
+    /// Unlike `enumerate_with_args`, a stream faulted by the hub yields a final `Err` item with the error sent by the hub before the stream ends,
+    /// so the failure can be distinguished from a normal end of the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    /// * `configuration` - A mutable closure that allows the user to configure the arguments for the method call.
+    ///
+    /// # Returns
+    ///
+    /// * `InvocationStream<T>` - Returns a stream of items of type `T`, ending with an error message if the hub failed.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut stream = client.try_enumerate_with_args::<TestEntity, _>("HundredEntities".to_string(), |c| {
+    ///     c.str_arg("some_argument");
+    /// }).await;
+    /// while let Some(item) = stream.next().await {
+    ///     match item {
+    ///         Ok(entity) => info!("Received entity: {}, {}", entity.text, entity.number),
+    ///         Err(e) => error!("Streaming failed: {}", e),
+    ///     }
+    /// }
+    /// ```
+    pub async fn try_enumerate_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> InvocationStream<T>
         where F : FnMut(&mut ArgumentConfiguration)
     {
-        let invocation_id = self._actions.create_key(target.clone());
-        let res = self._actions.add_stream::<T>(invocation_id.clone());        
-        let mut invocation = Invocation::create_multiple(target.clone());
-        invocation.with_invocation_id(invocation_id);
+        return self.enumerate_internal(target, Some(configuration)).await;
+    }
 
-        if configuration.is_some() {
-            let mut args = ArgumentConfiguration::new(invocation);
-            configuration.unwrap()(&mut args);
+    /// Calls a specific target method on the SignalR hub and returns a stream which ends if the hub stalls.
+    ///
+    /// Unlike `enumerate`, the stream ends when no item arrives within `per_item` after the call or the previous item,
+    /// and a `CancelInvocation` message is sent to the hub, so a stalled server cannot make the caller wait forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    /// * `per_item` - A `Duration` specifying the longest wait for each item.
+    ///
+    /// # Returns
+    ///
+    /// * `TimeoutStream<T>` - Returns a stream of items of type `T`, which tells whether it ended because of the timeout.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut stream = client.enumerate_with_item_timeout::<TestEntity>("HundredEntities".to_string(), Duration::from_secs(5)).await;
+    /// while let Some(entity) = stream.next().await {
+    ///     info!("Received entity: {}, {}", entity.text, entity.number);
+    /// }
+    /// ```
+    pub async fn enumerate_with_item_timeout<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String, per_item: Duration) -> TimeoutStream<T> {
+        let stream = self.enumerate_internal(target, None::<fn(&mut ArgumentConfiguration)>).await;
 
-            invocation = args.build_invocation();
-        }
+        TimeoutStream::new(stream, per_item)
+    }
 
-        let _ = self._connection.send(&invocation).await;
+    /// Calls a specific target method on the SignalR hub and returns a stream which knows the total count of the items sent by the hub.
+    ///
+    /// The hub must send the total count as the first item of the stream, followed by the items themselves.
+    /// The count is not yielded, it is exposed by `total` and `size_hint` of the returned stream, e.g. to report the progress of long streams.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `CountedStream<T>` - Returns a stream of items of type `T`, ending with an error message if the hub failed.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut stream = client.enumerate_counted::<TestEntity>("HundredEntities".to_string()).await;
+    /// while let Some(Ok(entity)) = stream.next().await {
+    ///     info!("Received entity {}, {} remaining", entity.number, stream.size_hint().0);
+    /// }
+    /// ```
+    pub async fn enumerate_counted<T: 'static + DeserializeOwned + Unpin + MaybeSend>(&self, target: String) -> CountedStream<T> {
+        let stream = self.enumerate_internal::<serde_json::Value, _>(target, None::<fn(&mut ArgumentConfiguration)>).await;
 
-        res
+        CountedStream::new(stream, self.get_parser_config())
     }
 
-    pub fn disconnect(mut self) {
-        self._connection.disconnect();
+    /// Calls a specific target method on the SignalR hub with custom arguments and returns a stream which knows the total count of the items sent by the hub.
+    ///
+    /// The hub must send the total count as the first item of the stream, followed by the items themselves.
+    /// The count is not yielded, it is exposed by `total` and `size_hint` of the returned stream, e.g. to report the progress of long streams.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - A `String` specifying the name of the target method to call on the hub.
+    /// * `configuration` - A mutable closure that allows the user to configure the arguments for the method call.
+    ///
+    /// # Returns
+    ///
+    /// * `CountedStream<T>` - Returns a stream of items of type `T`, ending with an error message if the hub failed.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the items in the stream, which must implement `DeserializeOwned`, `Unpin` and `MaybeSend`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut stream = client.enumerate_counted_with_args::<TestEntity, _>("MillionEntities".to_string(), |c| {
+    ///     c.str_arg("some_argument");
+    /// }).await;
+    /// while let Some(Ok(entity)) = stream.next().await {
+    ///     info!("Received entity {} of {:?}", stream.received(), stream.total());
+    /// }
+    /// ```
+    pub async fn enumerate_counted_with_args<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: F) -> CountedStream<T>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        let stream = self.enumerate_internal::<serde_json::Value, _>(target, Some(configuration)).await;
+
+        CountedStream::new(stream, self.get_parser_config())
+    }
+
+    async fn enumerate_internal<T: 'static + DeserializeOwned + Unpin + MaybeSend, F>(&self, target: String, configuration: Option<F>) -> InvocationStream<T>
+        where F : FnMut(&mut ArgumentConfiguration)
+    {
+        let mut actions = self._actions.clone();
+        let invocation_id = actions.create_key(target.clone());
+        let res = actions.add_stream::<T>(invocation_id.clone());        
+        let mut invocation = Invocation::create_multiple(target.clone());
+        invocation.with_invocation_id(invocation_id.clone());
+
+        if configuration.is_some() {
+            let mut args = ArgumentConfiguration::new(invocation, self._actions.get_serializer_options());
+            configuration.unwrap()(&mut args);
+
+            invocation = args.build_invocation();
+        }
+
+        let span = InvocationSpan::new(&target, &invocation_id, &self._connection.get_counters());
+
+        if let Err(e) = self._connection.send(&invocation).await {
+            span.fail(&e);
+        }
+
+        InvocationStream::new(res, invocation_id, self.clone(), span)
+    }
+
+    pub(crate) fn get_parser_config(&self) -> ParserConfig {
+        self._actions.get_parser_config()
+    }
+
+    pub(crate) fn release_invocation(&self, invocation_id: String) -> bool {
+        if self._actions.contains(invocation_id.clone()) {
+            self._actions.clone().remove(invocation_id);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Waits until the handshake of the current connection attempt completes.
+    ///
+    /// `connect` returns after the handshake already, this method lets other clones of the client, e.g. UI code,
+    /// tell a connecting state apart from a connected one while `reconnect` is in progress.
+    /// On WASM the outcome comes from the handshake driven by the polling loop, on Tokio the handshake is part of opening the socket.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` once the handshake succeeded, `false` if the connection attempt failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut reconnecting = client.clone();
+    /// spawn(async move {
+    ///     let _ = reconnecting.reconnect().await;
+    /// });
+    ///
+    /// show_connecting();
+    /// if client.ready().await {
+    ///     show_connected();
+    /// }
+    /// ```
+    pub async fn ready(&self) -> bool {
+        let ready = self._connection.get_ready().wait();
+
+        ready.await
+    }
+
+    /// Returns whether the underlying connection to the hub is alive.
+    ///
+    /// A client instance may outlive its connection, e.g. when the server closes the socket or the network drops.
+    /// This method inspects the actual connection state and the message receiver instead of the client's existence.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the connection is established and messages are being received, `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// if !client.is_connected() {
+    ///     error!("The hub connection is lost");
+    /// }
+    /// ```
+    pub fn is_connected(&self) -> bool {
+        self._connection.is_connected()
+    }
+
+    /// Returns the error that closed the connection, if any.
+    ///
+    /// The reason is set when the connection fails while receiving, e.g. a message exceeds the size configured with `with_max_message_size`.
+    /// It is cleared when the client reconnects.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The connection error, or `None` if the connection has not failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// if let Some(reason) = client.close_reason() {
+    ///     error!("The hub connection failed: {}", reason);
+    /// }
+    /// ```
+    pub fn close_reason(&self) -> Option<String> {
+        self._connection.get_close_reason()
+    }
+
+    /// Returns the code of the websocket close frame sent by the server, if any.
+    ///
+    /// Helps to tell apart a normal close (1000), a server going away (1001) or a policy violation (1008) from a network drop, where no close frame arrives.
+    /// The code is also part of the close reason passed to the `on_close` callback, unless the hub reported a more specific error.
+    /// It is cleared when the client reconnects. Close frames are not exposed on WASM, the code is always `None` there.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u16>` - The close code, or `None` if no close frame was received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// if client.close_code() == Some(1008) {
+    ///     error!("The hub rejected the connection: {:?}", client.close_reason());
+    /// }
+    /// ```
+    pub fn close_code(&self) -> Option<u16> {
+        self._connection.get_close_code()
+    }
+
+    /// Returns whether the hub allows the client to reconnect, as sent in the `allowReconnect` field of its `Close` message.
+    ///
+    /// Returns `true` when the hub did not say otherwise, e.g. the connection was lost without a `Close` message.
+    /// It is cleared when the client reconnects.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Returns `false` only if the hub closed the connection with `allowReconnect: false`.
+    pub fn allows_reconnect(&self) -> bool {
+        self._connection.get_reconnect_hint().0 != Some(false)
+    }
+
+    /// Returns the delay the hub asked for before the client reconnects, read from the `Retry-After` header of its `Close` message.
+    ///
+    /// Reconnect loops should wait the delay before the next attempt instead of their own backoff, so the clients of a restarting server
+    /// do not reconnect all at once. It is cleared when the client reconnects.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The requested delay, or `None` if the hub sent no hint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// if !client.is_connected() {
+    ///     tokio::time::sleep(client.reconnect_delay().unwrap_or(Duration::from_secs(5))).await;
+    ///     let _ = client.reconnect().await;
+    /// }
+    /// ```
+    pub fn reconnect_delay(&self) -> Option<Duration> {
+        self._connection.get_reconnect_hint().1
+    }
+
+    /// Returns the transport used by the connection.
+    ///
+    /// Only the `WebSockets` transport is supported. When negotiation is enabled with `with_negotiate`, connecting fails if the server does not offer it.
+    ///
+    /// # Returns
+    ///
+    /// * `&str` - The name of the transport, e.g. `WebSockets`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// assert_eq!(client.transport(), "WebSockets");
+    /// ```
+    pub fn transport(&self) -> &str {
+        self._connection.get_configuration().get_transport()
+    }
+
+    /// Returns the transfer format used by the connection.
+    ///
+    /// Only the `Text` transfer format is supported. When negotiation is enabled with `with_negotiate`, connecting fails if the server does not offer it.
+    ///
+    /// # Returns
+    ///
+    /// * `&str` - The name of the transfer format, e.g. `Text`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// assert_eq!(client.transfer_format(), "Text");
+    /// ```
+    pub fn transfer_format(&self) -> &str {
+        self._connection.get_configuration().get_transfer_format()
+    }
+
+    /// Sends a ping to the hub and measures the time until the next message arrives from the server.
+    ///
+    /// The hub does not answer pings, so the result is an approximate round-trip time: any message, including the keep-alive pings of the server, ends the measurement.
+    /// Fails if nothing arrives within the server timeout configured with `with_server_timeout`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Duration, String>` - On success, returns the elapsed time. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// match client.ping().await {
+    ///     Ok(rtt) => info!("The hub is alive, rtt {:?}", rtt),
+    ///     Err(e) => error!("The hub is not responding: {}", e),
+    /// }
+    /// ```
+    pub async fn ping(&self) -> Result<Duration, String> {
+        let timeout = self._connection.get_configuration().get_server_timeout();
+        let next = self._connection.get_counters().next_received();
+        let started = Instant::now();
+
+        self._connection.send(&Ping::new()).await?;
+
+        SignalRClient::with_timeout(timeout, async {
+            next.await;
+
+            Ok(())
+        }).await.map_err(|_| format!("No message arrived from the server within {:?}", timeout))?;
+
+        Ok(started.elapsed())
+    }
+
+    /// Returns when the last message arrived from the server.
+    ///
+    /// The server sends keep-alive pings regularly, so an old timestamp indicates a dead connection. The instant is a `std::time::Instant`, or a `wasm_timer::Instant` on WASM.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Instant>` - The arrival of the last message, or `None` if nothing has been received yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// if let Some(at) = client.last_server_message_at() {
+    ///     info!("Last message from the hub {:?} ago", at.elapsed());
+    /// }
+    /// ```
+    pub fn last_server_message_at(&self) -> Option<Instant> {
+        self._connection.get_counters().get_last_received()
+    }
+
+    /// Returns the details of the underlying socket, e.g. to confirm which server node the client is connected to.
+    ///
+    /// The details are captured when the connection is opened and are replaced when the client reconnects.
+    ///
+    /// # Returns
+    ///
+    /// * `ConnectionInfo` - The remote and local addresses of the socket and the negotiated TLS version, where known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let info = client.connection_info();
+    /// info!("Connected to {:?} from {:?}, TLS {:?}", info.remote_addr, info.local_addr, info.tls_version);
+    /// ```
+    pub fn connection_info(&self) -> ConnectionInfo {
+        self._connection.get_connection_info()
+    }
+
+    /// Returns a snapshot of the message counters of the client.
+    ///
+    /// The counters are meant for diagnostics, e.g. measuring throughput of large streams.
+    ///
+    /// # Returns
+    ///
+    /// * `Metrics` - The number of sent and received messages, received bytes, pending invocations and dropped stream items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let metrics = client.metrics();
+    /// info!("Received {} messages, {} bytes", metrics.messages_received, metrics.bytes_received);
+    /// ```
+    pub fn metrics(&self) -> Metrics {
+        let counters = self._connection.get_counters();
+
+        Metrics {
+            messages_sent: counters.get_messages_sent(),
+            messages_received: counters.get_messages_received(),
+            bytes_received: counters.get_bytes_received(),
+            pending_invocations: self._actions.pending_count(),
+            dropped_stream_items: self._actions.dropped_count(),
+        }
+    }
+
+    /// Registers a callback receiving the elapsed time of every finished invocation.
+    ///
+    /// The time is measured from sending the invocation until its completion, error, timeout or cancellation. Only one callback is kept, registering another one replaces it.
+    /// Available with the `metrics` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `observer` - A closure receiving the target, the invocation id, the elapsed time and whether the invocation succeeded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// client.on_invocation_timing(|timing| {
+    ///     info!("{} took {:?}", timing.target, timing.elapsed);
+    /// });
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn on_invocation_timing(&self, observer: impl FnMut(&InvocationTiming) + MaybeSend + 'static) {
+        self._connection.get_counters().set_timing_observer(Box::new(observer));
+    }
+
+    /// Lists the invocation ids of the invocations and streams still waiting for the hub.
+    ///
+    /// Registered callbacks are not listed. Meant for debugging calls which never complete, the order of the ids is not specified.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - The invocation ids of the pending invocations and streams.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    ///
+    /// for id in client.pending_invocations() {
+    ///     warn!("Invocation {} is still pending", id);
+    /// }
+    /// ```
+    pub fn pending_invocations(&self) -> Vec<String> {
+        self._actions.pending_keys()
+    }
+
+    /// Checks whether the invocation or stream with the given invocation id is still waiting for the hub.
+    ///
+    /// # Arguments
+    ///
+    /// * `invocation_id` - The invocation id, e.g. from `InvocationStream::invocation_id`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the invocation is pending, `false` if it is completed, cancelled or not known.
+    pub fn is_pending(&self, invocation_id: &str) -> bool {
+        self.pending_invocations().iter().any(|id| id == invocation_id)
+    }
+
+    /// Registers a callback receiving the messages whose type is not known by the client.
+    ///
+    /// Later versions of the SignalR protocol may introduce new message types, which are dropped by default.
+    /// The callback receives the raw JSON of every such message, without the record separator.
+    /// Only one callback is kept, registering again replaces the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - A closure receiving the raw text of the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// client.on_unknown_message(|raw| {
+    ///     warn!("Unknown message received: {}", raw);
+    /// });
+    /// ```
+    pub fn on_unknown_message(&self, callback: impl FnMut(&str) + MaybeSend + 'static) {
+        self._actions.clone().set_unknown_handler(Box::new(callback));
+    }
+
+    /// Creates an empty argument configuration, which can be filled once and reused as a template of common arguments.
+    ///
+    /// The template serializes the arguments with the serializer options of the client. Add its arguments to a call with `ArgumentConfiguration::arguments_from`.
+    ///
+    /// # Returns
+    ///
+    /// * `ArgumentConfiguration` - Returns a configuration without arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut tenant = client.argument_template();
+    /// tenant.argument("tenant-1");
+    ///
+    /// client.send_with_args("Notify".to_string(), |c| {
+    ///     c.arguments_from(&tenant).argument("hello");
+    /// }).await.unwrap();
+    /// ```
+    pub fn argument_template(&self) -> ArgumentConfiguration {
+        ArgumentConfiguration::new(Invocation::create_single(String::new()), self._actions.get_serializer_options())
+    }
+
+    /// Registers a callback receiving the binary frames sent by the server.
+    ///
+    /// The JSON protocol only uses text frames, so binary frames carry data of a custom side channel, e.g. raw WebRTC stats.
+    /// Without a callback they are logged and ignored. Only one callback is kept, registering again replaces the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - A closure receiving the payload of the frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// client.on_binary_frame(|frame| {
+    ///     info!("Binary frame of {} bytes received", frame.len());
+    /// });
+    /// ```
+    pub fn on_binary_frame(&self, callback: impl FnMut(&[u8]) + MaybeSend + 'static) {
+        self._actions.clone().set_binary_handler(Box::new(callback));
+    }
+
+    /// Registers a callback invoked when the connection to the hub ends.
+    ///
+    /// The callback fires once per connection, whether the server closed it, the socket failed, or the client was disconnected.
+    /// The reason carries the error sent by the server in its `Close` message or the socket error, and is `None` for a clean close.
+    /// Only one callback is kept, registering again replaces the previous one. The callback survives reconnects.
+    ///
+    /// # Arguments
+    ///
+    /// * `callback` - A closure receiving the close reason as an `Option<String>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// client.on_close(|reason| {
+    ///     warn!("The hub connection is closed: {:?}", reason);
+    /// });
+    /// ```
+    pub fn on_close(&self, callback: impl FnMut(Option<String>) + MaybeSend + 'static) {
+        self._connection.on_close(Box::new(callback));
+    }
+
+    /// Re-establishes the connection to the SignalR hub.
+    ///
+    /// The registered callbacks are kept, so handlers registered before the connection was lost keep firing after reconnecting.
+    /// The new connection is shared by all clones of the client. Invocations and streams pending on the previous connection are not resumed.
+    ///
+    /// Resuming the previous connection id is not supported, it needs stateful reconnect (`useStatefulReconnect` with sequence and ack messages),
+    /// which this client does not implement. Every reconnect opens a fresh connection, negotiating a new connection id when `with_negotiate` is enabled,
+    /// so a load balancer with sticky sessions may route it to another server.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// if !client.is_connected() {
+    ///     client.reconnect().await.unwrap();
+    /// }
+    /// ```
+    pub async fn reconnect(&mut self) -> Result<(), String> {
+        self._connection.reconnect().await
+    }
+
+    /// Disconnects the client.
+    ///
+    /// The underlying connection is closed once the last clone is disconnected. Only then every awaiting `invoke` and `send_and_confirm` resolves with an error
+    /// and every stream ends, so disconnecting one clone keeps the invocations of the other clones running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// client.disconnect();
+    /// ```
+    pub fn disconnect(mut self) {
+        self._connection.disconnect();
+    }
+}
+
+/// Removes the callback of `wait_for` when dropped.
+struct OneShotRegistration<H: CallbackHandler>(Option<H>);
+
+impl<H: CallbackHandler> Drop for OneShotRegistration<H> {
+    fn drop(&mut self) {
+        if let Some(handler) = self.0.take() {
+            handler.unregister();
+        }
     }
 }
 
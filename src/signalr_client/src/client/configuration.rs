@@ -1,3 +1,31 @@
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use crate::{execution::{MaybeSend, MaybeSync}, protocol::{messages::{Framing, ParserConfig}, serializer::SerializerOptions}};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_SERVER_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_PROTOCOL_VERSION: u8 = 1;
+pub(crate) const ACCESS_TOKEN_PARAM: &str = "access_token";
+const REDACTED: &str = "***";
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type AccessTokenFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type AccessTokenFuture = Pin<Box<dyn Future<Output = Result<String, String>>>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type AccessTokenProvider = Arc<dyn Fn() -> AccessTokenFuture + Send + Sync>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type AccessTokenProvider = Arc<dyn Fn() -> AccessTokenFuture>;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type FrameInterceptor = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type FrameInterceptor = Arc<dyn Fn(String) -> String>;
+
 #[derive(Clone)]
 pub(crate) enum Authentication {
     None,
@@ -10,17 +38,117 @@ pub(crate) enum Authentication {
     },
 } 
 
+impl fmt::Debug for Authentication {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Authentication::None => f.write_str("None"),
+            Authentication::Basic { user, password } => f.debug_struct("Basic")
+                .field("user", user)
+                .field("password", &password.as_ref().map(|_| REDACTED))
+                .finish(),
+            Authentication::Bearer { .. } => f.debug_struct("Bearer")
+                .field("token", &REDACTED)
+                .finish(),
+        }
+    }
+}
+
 pub struct ConnectionConfiguration {
     _secure: bool,
     _domain: String,
     _hub: String,
-    _port: Option<i32>,
+    _port: Option<u16>,
     _authentication: Authentication,
     _query_params: Vec<(String, String)>,
+    _root_certificates: Option<Vec<u8>>,
+    _accept_invalid_certs: bool,
+    _max_message_size: Option<usize>,
+    _invocation_id_prefix: Option<String>,
+    _timeout_header: Option<String>,
+    _subprotocol: Option<String>,
+    _sni_hostname: Option<String>,
+    _framing: Framing,
+    _protocol_version: u8,
+    _connect_timeout: Duration,
+    _server_timeout: Duration,
+    _concurrent_callbacks: bool,
+    _negotiate: bool,
+    _negotiate_retries: u32,
+    _negotiate_backoff: Duration,
+    _access_token_provider: Option<AccessTokenProvider>,
+    _outbound_interceptor: Option<FrameInterceptor>,
+    _inbound_interceptor: Option<FrameInterceptor>,
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    _http_client: Option<reqwest::Client>,
+    #[cfg(not(target_arch = "wasm32"))]
+    _runtime: Option<tokio::runtime::Handle>,
+    _arbitrary_precision: bool,
+    _serializer: SerializerOptions,
+    _error: Option<String>,
+}
+
+impl fmt::Debug for ConnectionConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query_params: Vec<(&str, &str)> = self._query_params
+            .iter()
+            .map(|(k, v)| (k.as_str(), if k == ACCESS_TOKEN_PARAM { REDACTED } else { v.as_str() }))
+            .collect();
+
+        f.debug_struct("ConnectionConfiguration")
+            .field("domain", &self._domain)
+            .field("hub", &self._hub)
+            .field("port", &self._port)
+            .field("secure", &self._secure)
+            .field("authentication", &self._authentication)
+            .field("query_params", &query_params)
+            .field("root_certificates", &self._root_certificates.is_some())
+            .field("accept_invalid_certs", &self._accept_invalid_certs)
+            .field("max_message_size", &self._max_message_size)
+            .field("invocation_id_prefix", &self._invocation_id_prefix)
+            .field("timeout_header", &self._timeout_header)
+            .field("subprotocol", &self._subprotocol)
+            .field("sni_hostname", &self._sni_hostname)
+            .field("framing", &self._framing)
+            .field("protocol_version", &self._protocol_version)
+            .field("connect_timeout", &self._connect_timeout)
+            .field("server_timeout", &self._server_timeout)
+            .field("concurrent_callbacks", &self._concurrent_callbacks)
+            .field("negotiate", &self._negotiate)
+            .field("negotiate_retries", &self._negotiate_retries)
+            .field("negotiate_backoff", &self._negotiate_backoff)
+            .field("access_token_provider", &self._access_token_provider.is_some())
+            .field("outbound_interceptor", &self._outbound_interceptor.is_some())
+            .field("inbound_interceptor", &self._inbound_interceptor.is_some())
+            .field("arbitrary_precision", &self._arbitrary_precision)
+            .field("serializer", &self._serializer)
+            .field("error", &self._error)
+            .finish()
+    }
 }
 
 impl ConnectionConfiguration {
-    pub(crate) fn new(domain: String, hub: String) -> Self {
+    /// Creates a connection configuration with the default settings.
+    ///
+    /// The configuration can be built in advance, e.g. from a settings file, and passed to `SignalRClient::connect_config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - A `String` that holds the domain of the SignalR server.
+    /// * `hub` - A `String` that holds the name of the hub to connect to.
+    ///
+    /// # Returns
+    ///
+    /// * `ConnectionConfiguration` - Returns a secure configuration without authentication.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    /// config.with_port(5220);
+    ///
+    /// let client = SignalRClient::connect_config(config).await.unwrap();
+    /// ```
+    pub fn new(domain: String, hub: String) -> Self {
         ConnectionConfiguration {
             _authentication: Authentication::None,
             _domain: domain,
@@ -28,6 +156,31 @@ impl ConnectionConfiguration {
             _hub: hub,
             _port: None,
             _query_params: Vec::new(),
+            _root_certificates: None,
+            _accept_invalid_certs: false,
+            _max_message_size: None,
+            _invocation_id_prefix: None,
+            _timeout_header: None,
+            _subprotocol: None,
+            _sni_hostname: None,
+            _framing: Framing::default(),
+            _protocol_version: DEFAULT_PROTOCOL_VERSION,
+            _connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            _server_timeout: DEFAULT_SERVER_TIMEOUT,
+            _concurrent_callbacks: false,
+            _negotiate: false,
+            _negotiate_retries: 0,
+            _negotiate_backoff: Duration::ZERO,
+            _access_token_provider: None,
+            _outbound_interceptor: None,
+            _inbound_interceptor: None,
+            #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+            _http_client: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            _runtime: None,
+            _arbitrary_precision: false,
+            _serializer: SerializerOptions::default(),
+            _error: None,
         }
     }
 
@@ -35,7 +188,7 @@ impl ConnectionConfiguration {
     ///
     /// # Arguments
     ///
-    /// * `port` - An integer specifying the port number to use for the connection.
+    /// * `port` - A `u16` specifying the port number to use for the connection, port 0 is rejected when connecting.
     ///
     /// # Returns
     ///
@@ -48,7 +201,7 @@ impl ConnectionConfiguration {
     ///     c.with_port(5220);
     /// }).await.unwrap();
     /// ```
-    pub fn with_port(&mut self, port: i32) -> &ConnectionConfiguration {
+    pub fn with_port(&mut self, port: u16) -> &ConnectionConfiguration {
         self._port = Some(port);
 
         self
@@ -77,6 +230,44 @@ impl ConnectionConfiguration {
         self
     }
 
+    /// Sets the domain, port, protocol, hub and query parameters of the connection from a full endpoint url.
+    ///
+    /// The scheme must be one of `http`, `https`, `ws` or `wss`; `https` and `wss` configure a secure connection.
+    /// The path of the url is used as the hub name and the query parameters are added to the existing ones.
+    /// An invalid url makes the connection fail with the parsing error.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A string slice that holds the endpoint url of the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_url("wss://localhost:5220/test?type=client");
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_url(&mut self, url: &str) -> &ConnectionConfiguration {
+        match ConnectionConfiguration::parse_url(url) {
+            Ok((secure, domain, port, hub, params)) => {
+                self._secure = secure;
+                self._domain = domain;
+                self._port = port;
+                self._hub = hub;
+                self._query_params.extend(params);
+            },
+            Err(error) => {
+                self._error = Some(error);
+            },
+        }
+
+        self
+    }
+
     /// Configures the connection to use a secure (HTTPS) protocol.
     ///
     /// # Returns
@@ -117,6 +308,8 @@ impl ConnectionConfiguration {
 
     /// Configures the connection to use basic authentication.
     ///
+    /// The credentials are sent in an `Authorization: Basic` header with the negotiate request and, on non WASM targets, with the websocket upgrade request.
+    ///
     /// # Arguments
     ///
     /// * `user` - A `String` specifying the username for authentication.
@@ -141,6 +334,9 @@ impl ConnectionConfiguration {
 
     /// Configures the connection to use bearer token authentication.
     ///
+    /// The token is sent in an `Authorization: Bearer` header with the negotiate request and, on non WASM targets, with the websocket upgrade request.
+    /// Browsers cannot set headers on websockets, use `with_access_token` on WASM targets instead.
+    ///
     /// # Arguments
     ///
     /// * `token` - A `String` specifying the bearer token for authentication.
@@ -163,55 +359,918 @@ impl ConnectionConfiguration {
     }
 
     pub fn with_query_param(&mut self, key: String, value: String) -> &ConnectionConfiguration {
+        self.add_query_param(key, value)
+    }
+
+    /// Appends a query parameter to the connection url, keeping the existing parameters with the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A `String` specifying the name of the query parameter.
+    /// * `value` - A `String` specifying the value of the query parameter.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.add_query_param("tag".to_string(), "a".to_string());
+    ///     c.add_query_param("tag".to_string(), "b".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn add_query_param(&mut self, key: String, value: String) -> &ConnectionConfiguration {
+        self._query_params.push((key, value));
+        self
+    }
+
+    /// Sets a query parameter of the connection url, replacing every existing parameter with the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A `String` specifying the name of the query parameter.
+    /// * `value` - A `String` specifying the value of the query parameter.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.set_query_param("type".to_string(), "client".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn set_query_param(&mut self, key: String, value: String) -> &ConnectionConfiguration {
+        self._query_params.retain(|(k, _)| *k != key);
         self._query_params.push((key, value));
         self
     }
 
+    /// Sets the access token sent as the `access_token` query parameter, replacing the one set before.
     pub fn with_access_token(&mut self, token: String) -> &ConnectionConfiguration {
-        self.with_query_param("access_token".to_string(), token)
+        self.set_query_param("access_token".to_string(), token)
     }
 
-    pub(crate) fn get_web_url(&self) -> String {
-        let base_url = format!("{}://{}/{}", self.get_http_schema(), self.get_domain(), self._hub);
-        if self._query_params.is_empty() {
-            base_url
-        } else {
-            let params = self._query_params
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<_>>()
-                .join("&");
-            format!("{}?{}", base_url, params)
-        }
+    /// Sets a provider fetching a fresh access token before each connect and reconnect.
+    ///
+    /// The token is sent as the `access_token` query parameter, replacing the one set with `with_access_token`, so reconnecting does not reuse an expired token.
+    /// Connecting fails with the error of the provider if no token can be fetched.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - A closure returning a future that resolves to the token, or an error message as a `String`.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_access_token_provider(|| async {
+    ///         refresh_access_token().await
+    ///     });
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_access_token_provider<F, R>(&mut self, provider: F) -> &ConnectionConfiguration
+        where F: Fn() -> R + MaybeSend + MaybeSync + 'static,
+              R: Future<Output = Result<String, String>> + MaybeSend + 'static
+    {
+        self._access_token_provider = Some(Arc::new(move || Box::pin(provider())));
+
+        self
     }
 
-    pub(crate) fn get_socket_url(&self) -> String {
-        let base_url = format!("{}://{}/{}", self.get_socket_schema(), self.get_domain(), self._hub);
-        if self._query_params.is_empty() {
-            base_url
-        } else {
-            let params = self._query_params
-                .iter()
-                .map(|(k, v)| format!("{}={}", k, v))
-                .collect::<Vec<_>>()
-                .join("&");
-            format!("{}?{}", base_url, params)
-        }
+    /// Sets a function transforming every frame before it is sent to the hub, e.g. to encrypt the payloads.
+    ///
+    /// The function receives the text of the frame after the messages are serialized and delimited, including the handshake,
+    /// so the receiving side, e.g. a gateway in front of the hub, must reverse it before splitting the records.
+    ///
+    /// # Arguments
+    ///
+    /// * `interceptor` - A closure receiving the serialized frame and returning the text to send instead.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_outbound_interceptor(|frame| encrypt(&frame));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_outbound_interceptor<F>(&mut self, interceptor: F) -> &ConnectionConfiguration
+        where F: Fn(String) -> String + MaybeSend + MaybeSync + 'static
+    {
+        self._outbound_interceptor = Some(Arc::new(interceptor));
+
+        self
     }
 
-    pub(crate) fn get_negotiate_url(&self) -> String {
-        let mut url = self.get_web_url();
-        // Nếu đã có query params, thêm negotiate với &, ngược lại thêm với ?
-        if url.contains('?') {
-            url = format!("{}&negotiate", url);
-        } else {
-            url = format!("{}/negotiate", url);
-        }
-        url
+    /// Sets a function transforming every frame received from the hub, e.g. to decrypt the payloads.
+    ///
+    /// The function receives the raw text of the frame before it is split into records, including the handshake response,
+    /// and must return the delimited messages of the SignalR protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `interceptor` - A closure receiving the raw frame and returning the text to process instead.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_inbound_interceptor(|frame| decrypt(&frame));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_inbound_interceptor<F>(&mut self, interceptor: F) -> &ConnectionConfiguration
+        where F: Fn(String) -> String + MaybeSend + MaybeSync + 'static
+    {
+        self._inbound_interceptor = Some(Arc::new(interceptor));
+
+        self
     }
 
-    pub(crate) fn get_authentication(&self) -> Authentication {
-        self._authentication.clone()
+    /// Adds trusted root certificates for secure (WSS) connections.
+    ///
+    /// The certificates are trusted in addition to the built-in roots of the selected TLS backend,
+    /// which makes it possible to connect to servers with self-signed certificates. This setting has no effect on WASM targets, where the browser validates the certificates.
+    ///
+    /// # Arguments
+    ///
+    /// * `pem` - A `Vec<u8>` holding one or more PEM encoded certificates.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_root_certificates(std::fs::read("dev-ca.pem").unwrap());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_root_certificates(&mut self, pem: Vec<u8>) -> &ConnectionConfiguration {
+        self._root_certificates = Some(pem);
+
+        self
+    }
+
+    /// Disables the validation of the server certificate for secure (WSS) connections.
+    ///
+    /// **This is meant for development only.** Any certificate is accepted, including self-signed, expired
+    /// and mismatched ones, which leaves the connection open to man-in-the-middle attacks. Never use it against production servers;
+    /// prefer `with_root_certificates` when the certificate authority of the server is known. This setting has no effect on WASM targets.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.dangerous_accept_invalid_certs();
+    /// }).await.unwrap();
+    /// ```
+    pub fn dangerous_accept_invalid_certs(&mut self) -> &ConnectionConfiguration {
+        self._accept_invalid_certs = true;
+
+        self
+    }
+
+    /// Sets the maximum size of a message received from the hub.
+    ///
+    /// Messages exceeding the limit close the connection with an error, which is reported as the close reason of the client.
+    /// When not set, the default limit of the websocket implementation applies. This setting has no effect on WASM targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - A `usize` specifying the maximum payload size of a received message in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_max_message_size(256 * 1024 * 1024);
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_max_message_size(&mut self, bytes: usize) -> &ConnectionConfiguration {
+        self._max_message_size = Some(bytes);
+
+        self
+    }
+
+    /// Sets the websocket subprotocol requested by the client.
+    ///
+    /// The value is sent in the `Sec-WebSocket-Protocol` header of the upgrade request, e.g. for reverse proxies routing by subprotocol.
+    /// The WASM client cannot set the header, the subprotocol is ignored there with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `subprotocol` - A `String` holding the subprotocol, or a comma separated list of subprotocols.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_subprotocol("device.v1".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_subprotocol(&mut self, subprotocol: String) -> &ConnectionConfiguration {
+        self._subprotocol = Some(subprotocol);
+
+        self
+    }
+
+    /// Sets the separator terminating the messages sent and received on the connection, instead of the standard `\u{001E}`.
+    ///
+    /// Only meant for interop with non-standard gateways, SignalR servers always use the standard separator.
+    /// Connecting fails if the separator is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `separator` - A `String` holding the record separator.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_record_separator("\n".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_record_separator(&mut self, separator: String) -> &ConnectionConfiguration {
+        self._framing = Framing::Separator(separator);
+
+        self
+    }
+
+    /// Sends and receives every message in a websocket frame of its own, without record separators.
+    ///
+    /// Only meant for interop with non-standard gateways, SignalR servers always terminate the messages with a separator.
+    /// Batches sent with `send_batch` are split into one frame per message.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_message_per_frame();
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_message_per_frame(&mut self) -> &ConnectionConfiguration {
+        self._framing = Framing::MessagePerFrame;
+
+        self
+    }
+
+    /// Sets the hostname presented in the TLS handshake of secure connections, instead of the configured domain.
+    ///
+    /// The TCP connection still targets the configured domain, which can be an IP address, e.g. a load balancer routing by SNI.
+    /// The server certificate is verified against this hostname, not against the domain, so the client trusts any server at the
+    /// configured address that presents a valid certificate for the hostname. Only use an address from a trusted source, and never combine it
+    /// with `dangerous_accept_invalid_certs`, which leaves no protection against a man in the middle. The WASM client cannot set the SNI,
+    /// the hostname is ignored there with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `hostname` - A `String` holding the server name sent in the TLS handshake.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("10.0.0.12", "test", |c| {
+    ///     c.with_sni_hostname("hub.example.com".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_sni_hostname(&mut self, hostname: String) -> &ConnectionConfiguration {
+        self._sni_hostname = Some(hostname);
+
+        self
+    }
+
+    /// Sets a prefix for the invocation ids generated by the client.
+    ///
+    /// Ids are built as `{prefix}_{target}_{index}`, or with a random GUID instead of the index when the `uuid` feature is enabled.
+    /// A distinct prefix per client makes ids unique across clients and easy to find in server logs.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - A `String` prepended to every invocation id.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_invocation_id_prefix("device-42".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_invocation_id_prefix(&mut self, prefix: String) -> &ConnectionConfiguration {
+        self._invocation_id_prefix = Some(prefix);
+
+        self
+    }
+
+    /// Sets the name of the invocation header carrying the timeout of `invoke_with_timeout` to the hub.
+    ///
+    /// The header holds the timeout in milliseconds, e.g. `timeout-ms: 5000`, so a hub reading it can cancel the call on its side too.
+    /// By default no header is sent and the timeout is only applied by the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - A `String` specifying the name of the header, e.g. `timeout-ms`.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_timeout_header("timeout-ms".to_string());
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_timeout_header(&mut self, name: String) -> &ConnectionConfiguration {
+        self._timeout_header = Some(name);
+
+        self
+    }
+
+    /// Sets the maximum time allowed for establishing the connection.
+    ///
+    /// The timeout covers the whole connect sequence, including negotiation, the websocket upgrade and the handshake.
+    /// When it elapses, connecting fails with a `connect timed out` error. Defaults to 30 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - A `Duration` specifying the connect timeout.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_connect_timeout(Duration::from_secs(10));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_connect_timeout(&mut self, timeout: Duration) -> &ConnectionConfiguration {
+        self._connect_timeout = timeout;
+
+        self
+    }
+
+    /// Negotiates every connection with the hub before opening the websocket.
+    ///
+    /// By default the client skips negotiation and opens the websocket directly, which needs a hub accepting connections without negotiate.
+    /// When enabled, the client posts to the `negotiate` endpoint of the hub before each connect and reconnect, and opens the websocket with the returned connection id.
+    /// Connecting fails if the request fails, see `with_negotiate_retries` for retrying transient failures.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_negotiate();
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_negotiate(&mut self) -> &ConnectionConfiguration {
+        self._negotiate = true;
+
+        self
+    }
+
+    /// Sets how many times the negotiate request is retried on transient failures.
+    ///
+    /// Connection errors and `5xx` responses are retried, any other status, e.g. `401` or `403`, fails immediately.
+    /// The client waits `backoff` before the first retry and doubles the wait after every further attempt. By default the request is not retried.
+    /// Only applies when negotiation is enabled with `with_negotiate`.
+    ///
+    /// # Arguments
+    ///
+    /// * `retries` - A `u32` specifying the number of retries after the first attempt.
+    /// * `backoff` - A `Duration` specifying the wait before the first retry.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_negotiate();
+    ///     c.with_negotiate_retries(3, Duration::from_millis(500));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_negotiate_retries(&mut self, retries: u32, backoff: Duration) -> &ConnectionConfiguration {
+        self._negotiate_retries = retries;
+        self._negotiate_backoff = backoff;
+
+        self
+    }
+
+    /// Sets the Tokio runtime driving the websocket of the client.
+    ///
+    /// The socket is opened and the messages of the hub are received on the given runtime, isolating the hub I/O from the runtime of the application,
+    /// e.g. the system runtime of an actix server. When not set, the runtime calling `connect` is used. Available on non-WASM targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - A `tokio::runtime::Handle` of the runtime to run the connection on.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let runtime = tokio::runtime::Builder::new_multi_thread()
+    ///     .worker_threads(1)
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_runtime(runtime.handle().clone());
+    /// }).await.unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_runtime(&mut self, runtime: tokio::runtime::Handle) -> &ConnectionConfiguration {
+        self._runtime = Some(runtime);
+
+        self
+    }
+
+    /// Sets the HTTP client used for the negotiate request preceding the websocket connection, see `with_negotiate`.
+    ///
+    /// Passing the client of the application keeps the TLS policy consistent between the application and the hub connection.
+    /// The same client is reused by every connect and reconnect of the connection. When not set, a default client is built once per connection.
+    /// Available with the `reqwest` feature on non-WASM targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - A `reqwest::Client` to send the requests with.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let http = reqwest::Client::builder()
+    ///     .min_tls_version(reqwest::tls::Version::TLS_1_2)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_negotiate();
+    ///     c.with_http_client(http.clone());
+    /// }).await.unwrap();
+    /// ```
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    pub fn with_http_client(&mut self, client: reqwest::Client) -> &ConnectionConfiguration {
+        self._http_client = Some(client);
+
+        self
+    }
+
+    /// Sets the maximum time allowed without receiving anything from the server.
+    ///
+    /// The server sends pings regularly, so a silent connection is considered dead, e.g. a half-open TCP connection.
+    /// When the timeout elapses, the connection is closed, which notifies the `on_close` callback and lets the client reconnect. Defaults to 30 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - A `Duration` specifying the server timeout, it should be at least double the keep-alive interval of the server.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_server_timeout(Duration::from_secs(60));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_server_timeout(&mut self, timeout: Duration) -> &ConnectionConfiguration {
+        self._server_timeout = timeout;
+
+        self
+    }
+
+    /// Runs the callbacks registered with `register` apart from the loop reading the connection.
+    ///
+    /// By default every received message is handled by the read loop, so a slow callback delays the completions and stream items arriving after it.
+    /// When enabled, the invocations of the hub are handed over to a separate task, which runs the callbacks one by one in the order they arrived.
+    /// Completions and stream items are still handled by the read loop in order, so the ordering per invocation id is kept.
+    /// The WASM client always handles the messages in its polling loop, the option is ignored there with a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `concurrent` - A `bool` indicating whether the callbacks run apart from the read loop.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_concurrent_callbacks(true);
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_concurrent_callbacks(&mut self, concurrent: bool) -> &ConnectionConfiguration {
+        self._concurrent_callbacks = concurrent;
+
+        self
+    }
+
+    /// Sets the version of the hub protocol requested in the handshake, 1 by default.
+    ///
+    /// Meant for servers expecting another revision of the protocol, e.g. custom servers speaking version 0.
+    /// Connecting fails when the server rejects the version or answers with a different one.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - A `u8` holding the protocol version sent in the handshake.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_protocol_version(0);
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_protocol_version(&mut self, version: u8) -> &ConnectionConfiguration {
+        self._protocol_version = version;
+
+        self
+    }
+
+    /// Keeps the exact digits of the numbers received from the hub.
+    ///
+    /// Needed when the hub sends numbers that do not fit into `u64`, `i64` or `f64`, e.g. large ids or decimals, and they are read as `serde_json::Value`.
+    /// Requires the `arbitrary_precision` feature, connecting fails otherwise. Clients without this setting keep normalized numbers even when the feature is enabled.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_arbitrary_precision();
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_arbitrary_precision(&mut self) -> &ConnectionConfiguration {
+        self._arbitrary_precision = true;
+
+        self
+    }
+
+    /// Sets how the arguments of the invocations are serialized, e.g. to match the enum contract of the hub.
+    ///
+    /// By default the enum variants are sent by their Rust name, as `serde_json` writes them.
+    /// Hubs with integer enums or a naming policy for string enums expect another representation.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The `SerializerOptions` to apply to the arguments passed to `ArgumentConfiguration`.
+    ///
+    /// # Returns
+    ///
+    /// * `&ConnectionConfiguration` - Returns a reference to the updated connection configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect_with("localhost", "test", |c| {
+    ///     c.with_serializer_options(SerializerOptions::new(EnumRepresentation::ScreamingSnakeCase));
+    /// }).await.unwrap();
+    /// ```
+    pub fn with_serializer_options(&mut self, options: SerializerOptions) -> &ConnectionConfiguration {
+        self._serializer = options;
+
+        self
+    }
+
+    /// Returns the domain of the SignalR server, without the port.
+    pub fn domain(&self) -> &str {
+        &self._domain
+    }
+
+    /// Returns the name of the hub to connect to.
+    pub fn hub(&self) -> &str {
+        &self._hub
+    }
+
+    /// Returns the port set for the connection, or `None` if the default port of the protocol is used.
+    pub fn port(&self) -> Option<u16> {
+        self._port
+    }
+
+    /// Returns whether the connection uses `https` and `wss`.
+    pub fn is_secure(&self) -> bool {
+        self._secure
+    }
+
+    /// Returns the query parameters of the connection url in the order they are sent.
+    ///
+    /// The value of the `access_token` parameter is replaced with `***`, so the parameters can be displayed or logged.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<(String, String)>` - The key and value pairs of the query parameters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    /// config.with_access_token("secret".to_string());
+    ///
+    /// assert_eq!(config.query_params(), vec![("access_token".to_string(), "***".to_string())]);
+    /// ```
+    pub fn query_params(&self) -> Vec<(String, String)> {
+        self._query_params
+            .iter()
+            .map(|(k, v)| (k.clone(), if k == ACCESS_TOKEN_PARAM { REDACTED.to_string() } else { v.clone() }))
+            .collect()
+    }
+
+    /// Returns a description of the authentication of the connection with the password and the token replaced with `***`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    /// config.authenticate_bearer("secret".to_string());
+    ///
+    /// assert_eq!(config.authentication(), "Bearer { token: \"***\" }");
+    /// ```
+    pub fn authentication(&self) -> String {
+        format!("{:?}", self._authentication)
+    }
+
+    /// Hides the access token in the query of the url, so it can be logged
+    pub(crate) fn redact_url(url: &str) -> String {
+        match url.split_once('?') {
+            Some((base, query)) => {
+                let params = query
+                    .split('&')
+                    .map(|param| match param.split_once('=') {
+                        Some((key, _)) if key == ACCESS_TOKEN_PARAM => format!("{}={}", key, REDACTED),
+                        _ => param.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("&");
+
+                format!("{}?{}", base, params)
+            },
+            None => url.to_string(),
+        }
+    }
+
+    pub(crate) fn get_web_url(&self) -> String {
+        let base_url = format!("{}://{}/{}", self.get_http_schema(), self.get_domain(), self._hub);
+        if self._query_params.is_empty() {
+            base_url
+        } else {
+            let params = self._query_params
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join("&");
+            format!("{}?{}", base_url, params)
+        }
+    }
+
+    pub(crate) fn get_socket_url(&self) -> String {
+        let base_url = format!("{}://{}/{}", self.get_socket_schema(), self.get_domain(), self._hub);
+        if self._query_params.is_empty() {
+            base_url
+        } else {
+            let params = self._query_params
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join("&");
+            format!("{}?{}", base_url, params)
+        }
+    }
+
+    pub(crate) fn get_negotiate_url(&self) -> String {
+        let url = self.get_web_url();
+        // negotiate là path của hub, query params giữ nguyên phía sau
+        match url.split_once('?') {
+            Some((base, query)) => format!("{}/negotiate?{}", base, query),
+            None => format!("{}/negotiate", url),
+        }
+    }
+
+    pub(crate) fn get_authentication(&self) -> Authentication {
+        self._authentication.clone()
+    }
+
+    pub(crate) fn get_root_certificates(&self) -> Option<Vec<u8>> {
+        self._root_certificates.clone()
+    }
+
+    pub(crate) fn get_accept_invalid_certs(&self) -> bool {
+        self._accept_invalid_certs
+    }
+
+    pub(crate) fn get_max_message_size(&self) -> Option<usize> {
+        self._max_message_size
+    }
+
+    pub(crate) fn get_invocation_id_prefix(&self) -> Option<String> {
+        self._invocation_id_prefix.clone()
+    }
+
+    pub(crate) fn get_timeout_header(&self) -> Option<String> {
+        self._timeout_header.clone()
+    }
+
+    pub(crate) fn get_subprotocol(&self) -> Option<String> {
+        self._subprotocol.clone()
+    }
+
+    pub(crate) fn get_sni_hostname(&self) -> Option<String> {
+        self._sni_hostname.clone()
+    }
+
+    pub(crate) fn get_framing(&self) -> Framing {
+        self._framing.clone()
+    }
+
+    pub(crate) fn get_protocol_version(&self) -> u8 {
+        self._protocol_version
+    }
+
+    pub(crate) fn get_connect_timeout(&self) -> Duration {
+        self._connect_timeout
+    }
+
+    pub(crate) fn get_server_timeout(&self) -> Duration {
+        self._server_timeout
+    }
+
+    pub(crate) fn get_concurrent_callbacks(&self) -> bool {
+        self._concurrent_callbacks
+    }
+
+    pub(crate) fn get_access_token_provider(&self) -> Option<AccessTokenProvider> {
+        self._access_token_provider.clone()
+    }
+
+    pub(crate) fn get_outbound_interceptor(&self) -> Option<FrameInterceptor> {
+        self._outbound_interceptor.clone()
+    }
+
+    pub(crate) fn get_inbound_interceptor(&self) -> Option<FrameInterceptor> {
+        self._inbound_interceptor.clone()
+    }
+
+    pub(crate) fn get_negotiate(&self) -> bool {
+        self._negotiate
+    }
+
+    pub(crate) fn get_negotiate_retries(&self) -> (u32, Duration) {
+        (self._negotiate_retries, self._negotiate_backoff)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn get_runtime(&self) -> Option<tokio::runtime::Handle> {
+        self._runtime.clone()
+    }
+
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    pub(crate) fn get_http_client(&self) -> reqwest::Client {
+        self._http_client.clone().unwrap_or_default()
+    }
+
+    pub(crate) fn get_parser_config(&self) -> ParserConfig {
+        ParserConfig::new(self._arbitrary_precision)
+    }
+
+    pub(crate) fn get_serializer_options(&self) -> SerializerOptions {
+        self._serializer
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if let Some(error) = &self._error {
+            return Err(error.clone());
+        }
+
+        if self._port == Some(0) {
+            return Err("The port must be in the range 1..=65535".to_string());
+        }
+
+        if self._framing == Framing::Separator(String::new()) {
+            return Err("The record separator must not be empty".to_string());
+        }
+
+        if self._arbitrary_precision && !cfg!(feature = "arbitrary_precision") {
+            return Err("Arbitrary precision requires the `arbitrary_precision` feature".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn parse_url(url: &str) -> Result<(bool, String, Option<u16>, String, Vec<(String, String)>), String> {
+        let (scheme, rest) = url.split_once("://").ok_or(format!("The url {} has no scheme", url))?;
+
+        let secure = match scheme.to_lowercase().as_str() {
+            "https" | "wss" => true,
+            "http" | "ws" => false,
+            other => return Err(format!("The scheme {} is not supported, use http, https, ws or wss", other)),
+        };
+
+        let (address, query) = match rest.split_once('?') {
+            Some((address, query)) => (address, query),
+            None => (rest, ""),
+        };
+
+        let (authority, path) = match address.split_once('/') {
+            Some((authority, path)) => (authority, path),
+            None => (address, ""),
+        };
+
+        let (domain, port) = match authority.rsplit_once(':') {
+            Some((domain, port)) => {
+                let port = port.parse::<u16>().ok().filter(|p| *p != 0).ok_or(format!("The port {} of the url {} is invalid", port, url))?;
+
+                (domain, Some(port))
+            },
+            None => (authority, None),
+        };
+
+        if domain.is_empty() {
+            return Err(format!("The url {} has no host", url));
+        }
+
+        let params = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (pair.to_string(), String::new()),
+            })
+            .collect();
+
+        Ok((secure, domain.to_string(), port, path.trim_end_matches('/').to_string(), params))
     }
 
     fn get_http_schema(&self) -> String {
@@ -0,0 +1,16 @@
+use std::net::SocketAddr;
+
+/// Details of the underlying socket of a client, captured when the connection is opened.
+///
+/// The addresses are not known on WASM, where the browser owns the socket.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// The address of the server the socket is connected to, e.g. the edge node behind a load balancer name.
+    pub remote_addr: Option<SocketAddr>,
+    /// The local address of the socket.
+    pub local_addr: Option<SocketAddr>,
+    /// Whether the connection is encrypted with TLS.
+    pub secure: bool,
+    /// The negotiated TLS protocol version, e.g. `TLSv1_3`. Only reported with the `rustls` feature.
+    pub tls_version: Option<String>,
+}
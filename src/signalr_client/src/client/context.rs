@@ -1,8 +1,9 @@
 
 use core::future::Future;
 
+use futures::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
-use crate::protocol::{messages, invoke::{Completion, Invocation}};
+use crate::protocol::{messages, invoke::{Completion, Invocation}, streaming::StreamItem};
 use self::messages::MessageParser;
 use super::SignalRClient;
 
@@ -84,6 +85,14 @@ use super::SignalRClient;
 /// }
 /// ```
 ///
+/// ## `can_complete`
+///
+/// Checks whether the hub awaits a result from the callback, i.e. the invocation has an invocation id.
+///
+/// ### Returns
+///
+/// * `bool` - Returns `true` if the invocation can be completed with a result, otherwise `false`.
+///
 /// ## `complete`
 ///
 /// Returns a specific result from the callback to the hub.
@@ -118,6 +127,21 @@ use super::SignalRClient;
 ///     }
 /// }
 /// ```
+///
+/// ## `stream_result`
+///
+/// Returns a stream of results from the callback to the hub, followed by a terminal completion.
+///
+/// This method should be used only when the hub invokes the callback and awaits a streamed response.
+///
+/// ### Arguments
+///
+/// * `items` - The stream of results to return, whose items must implement `Serialize`.
+///
+/// ### Returns
+///
+/// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+#[derive(Debug)]
 pub struct InvocationContext {
     pub client: SignalRClient,
     invocation: Invocation,
@@ -169,13 +193,13 @@ impl InvocationContext {
 
                 if arg.is_some() {
                     let value = arg.unwrap();
-                    let strvalue = value.to_string();
-                    let res = MessageParser::parse_message::<T>(&strvalue);
+                    // deserializing the value directly avoids a string round-trip for large payloads
+                    let res = MessageParser::parse_value_with::<T>(value, &self.client.get_parser_config());
 
                     if res.is_ok() {
                         return Ok(res.unwrap());
                     } else {
-                        return Err(format!("The argument cannot be deserialized to the requested type {:?}", arg.unwrap().as_str().unwrap()));
+                        return Err(format!("The argument cannot be deserialized to the requested type: {}", res.err().unwrap()));
                     }
                 } else {
                     return Err(format!("The argument does not exist at the given index {}", index));
@@ -188,6 +212,28 @@ impl InvocationContext {
         }        
     }
 
+    /// Checks whether the hub awaits a result from the callback.
+    ///
+    /// Invocations sent without an invocation id are fire-and-forget, so `complete` and `stream_result` would fail for them.
+    /// Callbacks can check it before doing expensive work to produce a result.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Returns `true` if the invocation can be completed with a result, otherwise `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// if ctx.can_complete() {
+    ///     let _ = ctx.complete(compute_result()).await;
+    /// } else {
+    ///     info!("The hub does not await a result");
+    /// }
+    /// ```
+    pub fn can_complete(&self) -> bool {
+        self.invocation.get_invocation_id().is_some()
+    }
+
     /// Returns a specific result from the callback to the hub.
     ///
     /// This method should be used only when the hub invokes the callback and awaits the response to arrive.
@@ -221,9 +267,8 @@ impl InvocationContext {
     /// }
     /// ```    
     pub async fn complete<T: Serialize>(&mut self, result: T) -> Result<(), String> {
-        let invocation_id = self.invocation.get_invocation_id();
-
-        if invocation_id.is_some() {
+        if self.can_complete() {
+            let invocation_id = self.invocation.get_invocation_id();
             let completion = Completion::create_result(invocation_id.unwrap(), result);
 
             return self.client.send_direct(completion).await;
@@ -232,6 +277,56 @@ impl InvocationContext {
         }
     }
 
+    /// Returns a stream of results from the callback to the hub.
+    ///
+    /// Every item is sent as a `StreamItem` tagged with the invocation id, followed by a terminal `Completion` once the stream ends.
+    /// This method should be used only when the hub invokes the callback and awaits a streamed response, e.g. a callback returning `IAsyncEnumerable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - The stream of results to return, whose items must implement `Serialize`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - On success, returns `Ok(())`. On failure, returns an error message as a `String`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - The type of the stream items, which must implement `Serialize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let items = futures::stream::iter((0..10).map(|i| TestEntity {
+    ///     text: format!("item {}", i),
+    ///     number: i,
+    /// }));
+    ///
+    /// let result = ctx.stream_result(items).await;
+    /// match result {
+    ///     Ok(_) => {
+    ///         info!("Callback stream completed successfully");
+    ///     }
+    ///     Err(e) => {
+    ///         error!("Failed to stream callback results: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub async fn stream_result<T: Serialize>(&mut self, items: impl Stream<Item = T>) -> Result<(), String> {
+        if self.can_complete() {
+            let invocation_id = self.invocation.get_invocation_id().unwrap();
+            let mut items = Box::pin(items);
+
+            while let Some(item) = items.next().await {
+                self.client.send_direct(StreamItem::new(invocation_id.clone(), item)).await?;
+            }
+
+            return self.client.send_direct(Completion::<()>::create_empty(invocation_id)).await;
+        } else {
+            return Err(format!("The stream cannot be sent, because there was no invocation id for the call"));
+        }
+    }
+
     /// Spawns the given async block into a new thread.
     ///
     /// This method is a convenience method for writing cross-platform code, as the package supports both Tokio and WASM.
@@ -252,7 +347,7 @@ impl InvocationContext {
     ///     info!("Async block executed");
     /// });
     /// ```    
-    #[cfg(not(target_family = "wasm"))]
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn spawn<F>(future: F)
         where F: Future<Output = ()> + Send + 'static
     {
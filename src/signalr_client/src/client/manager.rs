@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc};
+
+use futures::lock::Mutex;
+use log::info;
+
+use super::{ConnectionConfiguration, SignalRClient};
+
+#[cfg(not(target_arch = "wasm32"))]
+type ConfigurationFn = Arc<dyn Fn(&mut ConnectionConfiguration) + Send + Sync>;
+
+#[cfg(target_arch = "wasm32")]
+type ConfigurationFn = Arc<dyn Fn(&mut ConnectionConfiguration)>;
+
+/// Hands out clients of the hubs of one server, opening a single connection per hub.
+///
+/// The first request for a hub connects to it, later requests get clones of the same client, so every clone shares the connection and the registered callbacks.
+/// A client which lost its connection is reconnected when it is requested again. Clones of the manager share the cached clients.
+#[derive(Clone)]
+pub struct HubConnectionManager {
+    _domain: String,
+    _options: Option<ConfigurationFn>,
+    _clients: Arc<Mutex<HashMap<String, SignalRClient>>>,
+}
+
+impl HubConnectionManager {
+    /// Creates a manager connecting to the hubs of the server with the default connection configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - A string slice that holds the domain of the SignalR server.
+    ///
+    /// # Returns
+    ///
+    /// * `HubConnectionManager` - Returns a manager without any connected hub.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let manager = HubConnectionManager::new("localhost");
+    /// let client = manager.client_for("test").await.unwrap();
+    /// ```
+    pub fn new(domain: &str) -> Self {
+        HubConnectionManager {
+            _domain: domain.to_string(),
+            _options: None,
+            _clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates a manager connecting to the hubs of the server with custom connection properties.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - A string slice that holds the domain of the SignalR server.
+    /// * `options` - A closure configuring the connection of every hub, e.g. the port and the authentication.
+    ///
+    /// # Returns
+    ///
+    /// * `HubConnectionManager` - Returns a manager without any connected hub.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let manager = HubConnectionManager::with_options("localhost", |c| {
+    ///     c.with_port(5220);
+    ///     c.unsecure();
+    /// });
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_options(domain: &str, options: impl Fn(&mut ConnectionConfiguration) + Send + Sync + 'static) -> Self {
+        HubConnectionManager {
+            _domain: domain.to_string(),
+            _options: Some(Arc::new(options)),
+            _clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates a manager connecting to the hubs of the server with custom connection properties.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - A string slice that holds the domain of the SignalR server.
+    /// * `options` - A closure configuring the connection of every hub, e.g. the port and the authentication.
+    ///
+    /// # Returns
+    ///
+    /// * `HubConnectionManager` - Returns a manager without any connected hub.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let manager = HubConnectionManager::with_options("localhost", |c| {
+    ///     c.with_port(5220);
+    ///     c.unsecure();
+    /// });
+    /// ```
+    #[cfg(target_arch = "wasm32")]
+    pub fn with_options(domain: &str, options: impl Fn(&mut ConnectionConfiguration) + 'static) -> Self {
+        HubConnectionManager {
+            _domain: domain.to_string(),
+            _options: Some(Arc::new(options)),
+            _clients: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns a client of the hub, connecting to it on the first request.
+    ///
+    /// Requests are served one after another, so concurrent requests for the same hub open a single connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `hub` - A string slice that holds the name of the hub.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SignalRClient, String>` - On success, returns a clone of the client of the hub. On failure, returns an error message as a `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let manager = HubConnectionManager::new("localhost");
+    /// let devices = manager.client_for("deviceRHub").await.unwrap();
+    /// let same = manager.client_for("deviceRHub").await.unwrap();
+    /// ```
+    pub async fn client_for(&self, hub: &str) -> Result<SignalRClient, String> {
+        let mut clients = self._clients.lock().await;
+
+        if let Some(client) = clients.get_mut(hub) {
+            if !client.is_connected() {
+                info!("Client of hub {} is disconnected, reconnecting", hub);
+                client.reconnect().await?;
+            }
+
+            return Ok(client.clone());
+        }
+
+        let client = match &self._options {
+            Some(options) => {
+                let options = options.clone();
+
+                SignalRClient::connect_with(&self._domain, hub, move |c| options(c)).await?
+            },
+            None => SignalRClient::connect(&self._domain, hub).await?,
+        };
+
+        info!("Connected to hub {} of {}", hub, self._domain);
+        clients.insert(hub.to_string(), client.clone());
+
+        Ok(client)
+    }
+
+    /// Forgets the clients of every hub, the next request connects again.
+    ///
+    /// Clones handed out before keep working, the connection of a hub is closed when its last clone is dropped.
+    pub async fn clear(&self) {
+        self._clients.lock().await.clear();
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(feature = "metrics")]
+use std::time::Duration;
+
+/// A snapshot of the message counters of a client.
+///
+/// Counters are shared by all clones of a client and are not reset when the client reconnects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of messages sent to the hub.
+    pub messages_sent: usize,
+    /// The number of messages received from the hub.
+    pub messages_received: usize,
+    /// The number of bytes received from the hub.
+    pub bytes_received: usize,
+    /// The number of invocations and streams waiting for the hub to respond.
+    pub pending_invocations: usize,
+    /// The number of stream items dropped because they had no invocation id.
+    pub dropped_stream_items: usize,
+}
+
+/// The time an invocation took from sending it until the hub completed it, reported with the `metrics` feature.
+///
+/// Streams are reported when they end, invocations which could not be sent are reported as failed.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvocationTiming {
+    /// The name of the hub method.
+    pub target: String,
+    /// The invocation id of the call.
+    pub invocation_id: String,
+    /// The time elapsed since the invocation was sent.
+    pub elapsed: Duration,
+    /// `true` if the hub completed the invocation, `false` if it failed, timed out or was cancelled.
+    pub succeeded: bool,
+}
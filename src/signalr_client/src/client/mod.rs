@@ -1,8 +1,20 @@
 mod client;
 mod context;
 mod configuration;
+mod connection_info;
+mod manager;
+mod metrics;
+mod span;
+mod stream;
 
 pub use client::SignalRClient;
 pub use context::InvocationContext;
 pub use configuration::ConnectionConfiguration;
-pub(crate) use configuration::Authentication;
\ No newline at end of file
+pub use connection_info::ConnectionInfo;
+pub use manager::HubConnectionManager;
+pub use metrics::Metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::InvocationTiming;
+pub use stream::{CountedStream, InvocationStream, ItemStream, TimeoutStream};
+pub(crate) use configuration::{AccessTokenProvider, Authentication, FrameInterceptor, ACCESS_TOKEN_PARAM};
+pub(crate) use span::InvocationSpan;
\ No newline at end of file
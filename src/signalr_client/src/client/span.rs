@@ -0,0 +1,111 @@
+use core::future::Future;
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::communication::ConnectionCounters;
+#[cfg(feature = "metrics")]
+use crate::communication::Instant;
+#[cfg(feature = "metrics")]
+use super::InvocationTiming;
+
+/// A span covering an invocation from sending it until the hub completes it.
+///
+/// The span records the target, the invocation id and the outcome of the invocation, only the first outcome is kept.
+/// With the `metrics` feature the elapsed time is reported to the timing observer of the connection along with the outcome.
+/// Without the `tracing` and `metrics` features every method is a no-op.
+pub(crate) struct InvocationSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    finished: AtomicBool,
+    #[cfg(feature = "metrics")]
+    timing: (String, String, Instant, Arc<ConnectionCounters>),
+}
+
+impl InvocationSpan {
+    pub(crate) fn new(target: &str, invocation_id: &str, counters: &Arc<ConnectionCounters>) -> Self {
+        #[cfg(not(feature = "metrics"))]
+        let _ = counters;
+
+        #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+        let _ = (target, invocation_id);
+
+        InvocationSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("invocation", target = target, invocation_id = invocation_id, outcome = tracing::field::Empty, error = tracing::field::Empty),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            finished: AtomicBool::new(false),
+            #[cfg(feature = "metrics")]
+            timing: (target.to_string(), invocation_id.to_string(), Instant::now(), counters.clone()),
+        }
+    }
+
+    /// Runs the future inside the span and records its outcome
+    pub(crate) async fn run<T>(&self, future: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        #[cfg(feature = "tracing")]
+        let result = tracing::Instrument::instrument(future, self.span.clone()).await;
+
+        #[cfg(not(feature = "tracing"))]
+        let result = future.await;
+
+        match &result {
+            Ok(_) => self.complete(),
+            Err(e) => self.fail(e),
+        }
+
+        result
+    }
+
+    /// Runs the closure inside the span, e.g. polling a stream
+    pub(crate) fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "tracing")]
+        return self.span.in_scope(f);
+
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+
+    pub(crate) fn complete(&self) {
+        self.record_outcome("completed", None);
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.record_outcome("cancelled", None);
+    }
+
+    pub(crate) fn fail(&self, error: &str) {
+        self.record_outcome("failed", Some(error));
+    }
+
+    fn record_outcome(&self, outcome: &str, error: Option<&str>) {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("outcome", outcome);
+
+            if let Some(error) = error {
+                self.span.record("error", error);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let (target, invocation_id, started, counters) = &self.timing;
+
+            counters.record_timing(InvocationTiming {
+                target: target.clone(),
+                invocation_id: invocation_id.clone(),
+                elapsed: started.elapsed(),
+                succeeded: outcome == "completed",
+            });
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (outcome, error);
+    }
+}
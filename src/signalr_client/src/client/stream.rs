@@ -0,0 +1,350 @@
+use std::{future::Future, marker::PhantomData, pin::Pin, task::{Context, Poll}, time::Duration};
+
+use futures::{stream::FusedStream, Stream};
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+
+use crate::{completer::ManualStream, protocol::{invoke::CancelInvocation, messages::{MessageParser, ParserConfig}}};
+use super::{InvocationContext, InvocationSpan, SignalRClient};
+
+/// A stream of the items sent by the hub for a streaming invocation.
+///
+/// Dropping the stream before the hub completes it removes the pending invocation from the client
+/// and sends a `CancelInvocation` message to the hub, so the hub stops producing the remaining items.
+///
+/// # Examples
+///
+/// ```
+/// let mut stream = client.try_enumerate::<TestEntity>("MillionEntities".to_string()).await;
+/// while let Some(Ok(entity)) = stream.next().await {
+///     if entity.number == 10 {
+///         break; // the hub is notified when the stream is dropped
+///     }
+/// }
+/// ```
+pub struct InvocationStream<T> {
+    stream: ManualStream<Result<T, String>>,
+    invocation_id: String,
+    client: Option<SignalRClient>,
+    span: InvocationSpan,
+}
+
+impl<T> InvocationStream<T> {
+    pub(crate) fn new(stream: ManualStream<Result<T, String>>, invocation_id: String, client: SignalRClient, span: InvocationSpan) -> Self {
+        InvocationStream {
+            stream: stream,
+            invocation_id: invocation_id,
+            client: Some(client),
+            span: span,
+        }
+    }
+
+    /// Returns the invocation id of the streaming invocation.
+    pub fn invocation_id(&self) -> &str {
+        &self.invocation_id
+    }
+}
+
+impl<T> Stream for InvocationStream<T> {
+    type Item = Result<T, String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let span = &this.span;
+        let stream = &mut this.stream;
+        let poll = span.in_scope(|| Pin::new(stream).poll_next(cx));
+
+        match &poll {
+            Poll::Ready(Some(Err(e))) => span.fail(e),
+            Poll::Ready(None) => span.complete(),
+            _ => {},
+        }
+
+        poll
+    }
+}
+
+impl<T> FusedStream for InvocationStream<T> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+impl<T> Drop for InvocationStream<T> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            if client.release_invocation(self.invocation_id.clone()) {
+                debug!("Stream {} is dropped before completion, cancelling...", self.invocation_id);
+                self.span.cancel();
+                let cancel = CancelInvocation::new(self.invocation_id.clone());
+
+                InvocationContext::spawn(async move {
+                    let _ = client.send_direct(cancel).await;
+                });
+            }
+        }
+    }
+}
+
+/// A stream of the items sent by the hub for a streaming invocation, skipping the error the hub may end the stream with.
+///
+/// Returned by `enumerate`. The stream holds its own clone of the client, so it does not borrow the client it was created with.
+/// Dropping the stream cancels the invocation on the hub just like `InvocationStream`.
+pub struct ItemStream<T> {
+    stream: InvocationStream<T>,
+}
+
+impl<T> ItemStream<T> {
+    pub(crate) fn new(stream: InvocationStream<T>) -> Self {
+        ItemStream { stream: stream }
+    }
+
+    /// Returns the invocation id of the streaming invocation.
+    pub fn invocation_id(&self) -> &str {
+        self.stream.invocation_id()
+    }
+}
+
+impl<T> Stream for ItemStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => return Poll::Ready(Some(item)),
+                Poll::Ready(Some(Err(_))) => continue,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> FusedStream for ItemStream<T> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+/// A stream of the items sent by the hub for a streaming invocation, where the hub sends the total count of the items first.
+///
+/// The first item of the hub stream is read as the count and is not yielded, so `size_hint` reports the exact number
+/// of remaining items once the count has arrived. Dropping the stream cancels the invocation on the hub just like `InvocationStream`.
+///
+/// # Examples
+///
+/// ```
+/// let mut stream = client.enumerate_counted::<TestEntity>("HundredEntities".to_string()).await;
+/// while let Some(Ok(entity)) = stream.next().await {
+///     if let Some(total) = stream.total() {
+///         info!("Received {} of {} entities", stream.received(), total);
+///     }
+/// }
+/// ```
+pub struct CountedStream<T> {
+    stream: InvocationStream<serde_json::Value>,
+    parser: ParserConfig,
+    total: Option<usize>,
+    counted: bool,
+    received: usize,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T> CountedStream<T> {
+    pub(crate) fn new(stream: InvocationStream<serde_json::Value>, parser: ParserConfig) -> Self {
+        CountedStream {
+            stream: stream,
+            parser: parser,
+            total: None,
+            counted: false,
+            received: 0,
+            _item: PhantomData,
+        }
+    }
+
+    /// Returns the invocation id of the streaming invocation.
+    pub fn invocation_id(&self) -> &str {
+        self.stream.invocation_id()
+    }
+
+    /// Returns the total count of the items sent by the hub, or `None` if the count has not arrived yet.
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    /// Returns the number of items yielded so far, not including the leading count.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+}
+
+impl<T: DeserializeOwned> Stream for CountedStream<T> {
+    type Item = Result<T, String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(value))) => {
+                    if !this.counted {
+                        this.counted = true;
+
+                        match MessageParser::parse_value_with::<usize>(&value, &this.parser) {
+                            Ok(total) => {
+                                this.total = Some(total);
+                                continue;
+                            },
+                            Err(e) => return Poll::Ready(Some(Err(format!("The leading stream item is not a count: {}", e)))),
+                        }
+                    }
+
+                    this.received += 1;
+
+                    return Poll::Ready(Some(MessageParser::parse_value_with::<T>(&value, &this.parser)));
+                },
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.total {
+            Some(total) => {
+                let remaining = total.saturating_sub(self.received);
+
+                (remaining, Some(remaining))
+            },
+            None => (0, None),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> FusedStream for CountedStream<T> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+/// Restartable timer of `TimeoutStream`, a tokio `Sleep` or a `wasm_timer::Delay` on WASM.
+struct ItemTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    sleep: Pin<Box<tokio::time::Sleep>>,
+    #[cfg(target_arch = "wasm32")]
+    sleep: wasm_timer::Delay,
+    duration: Duration,
+}
+
+impl ItemTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new(duration: Duration) -> Self {
+        ItemTimer { sleep: Box::pin(tokio::time::sleep(duration)), duration: duration }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn new(duration: Duration) -> Self {
+        ItemTimer { sleep: wasm_timer::Delay::new(duration), duration: duration }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restart(&mut self) {
+        self.sleep.as_mut().reset(tokio::time::Instant::now() + self.duration);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn restart(&mut self) {
+        self.sleep.reset(wasm_timer::Instant::now() + self.duration);
+    }
+
+    fn poll_elapsed(&mut self, cx: &mut Context<'_>) -> bool {
+        Pin::new(&mut self.sleep).poll(cx).is_ready()
+    }
+}
+
+/// A stream of the items sent by the hub for a streaming invocation, which ends if the hub does not send the next item in time.
+///
+/// The timer restarts with every item. When it elapses the stream ends and the invocation is cancelled on the hub, like dropping an `InvocationStream`.
+/// Items faulted by the hub are skipped, like in `enumerate`.
+///
+/// # Examples
+///
+/// ```
+/// let mut stream = client.enumerate_with_item_timeout::<TestEntity>("MillionEntities".to_string(), Duration::from_secs(5)).await;
+/// while let Some(entity) = stream.next().await {
+///     info!("Received entity: {}, {}", entity.text, entity.number);
+/// }
+/// if stream.is_timed_out() {
+///     error!("The hub stalled");
+/// }
+/// ```
+pub struct TimeoutStream<T> {
+    stream: Option<InvocationStream<T>>,
+    timer: ItemTimer,
+    timed_out: bool,
+}
+
+impl<T> TimeoutStream<T> {
+    pub(crate) fn new(stream: InvocationStream<T>, per_item: Duration) -> Self {
+        TimeoutStream {
+            stream: Some(stream),
+            timer: ItemTimer::new(per_item),
+            timed_out: false,
+        }
+    }
+
+    /// Returns whether the stream ended because no item arrived in time.
+    pub fn is_timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+impl<T> Stream for TimeoutStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let stream = match this.stream.as_mut() {
+                Some(stream) => stream,
+                None => return Poll::Ready(None),
+            };
+
+            match Pin::new(stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => {
+                    this.timer.restart();
+
+                    return Poll::Ready(Some(item));
+                },
+                Poll::Ready(Some(Err(_))) => continue,
+                Poll::Ready(None) => {
+                    this.stream = None;
+
+                    return Poll::Ready(None);
+                },
+                Poll::Pending => {
+                    if this.timer.poll_elapsed(cx) {
+                        warn!("No stream item arrived in {:?}, cancelling the stream", this.timer.duration);
+                        this.timed_out = true;
+                        // dropping the invocation stream sends the cancellation to the hub
+                        this.stream = None;
+
+                        return Poll::Ready(None);
+                    }
+
+                    return Poll::Pending;
+                },
+            }
+        }
+    }
+}
+
+impl<T> FusedStream for TimeoutStream<T> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_none()
+    }
+}
@@ -0,0 +1,356 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::Duration};
+
+use log::{error, info};
+
+use crate::{client::{ConnectionConfiguration, ConnectionInfo}, execution::{Storage, UpdatableActionStorage}, protocol::{close::Close, messages::{Framing, MessageParser}, negotiate::{MessageType, Ping}}};
+
+use super::{intercept_outbound, CloseCallback, CloseHandler, Communication, ConnectionCounters, ConnectionData, ReadySignal, RecordBuffer};
+
+#[cfg(target_arch = "wasm32")]
+use super::client_wasm::CommunicationClient as SocketClient;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::client_tokio::CommunicationClient as SocketClient;
+
+/// An in-memory transport for testing the client without a hub.
+///
+/// Frames fed into the mock are routed to the client the same way as frames received from a websocket,
+/// and every message sent by the client is recorded as JSON, without the record separator.
+///
+/// # Examples
+///
+/// ```
+/// let mock = MockCommunication::new();
+/// let client = SignalRClient::from_communication(mock.clone()).unwrap();
+///
+/// client.send("Notify".to_string()).await.unwrap();
+/// assert_eq!(mock.sent().len(), 1);
+///
+/// mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[]}\u{001E}");
+/// ```
+#[derive(Clone)]
+pub struct MockCommunication {
+    _configuration: ConnectionData,
+    _actions: UpdatableActionStorage,
+    _buffer: Arc<Mutex<RecordBuffer>>,
+    _sent: Arc<Mutex<Vec<String>>>,
+    _connected: Arc<AtomicBool>,
+    _close: Arc<Mutex<CloseHandler>>,
+    _counters: Arc<ConnectionCounters>,
+    _ready: Arc<ReadySignal>,
+}
+
+impl MockCommunication {
+    pub fn new() -> Self {
+        MockCommunication::with_configuration(&ConnectionConfiguration::new("mock".to_string(), "mock".to_string()))
+    }
+
+    /// Creates a mock applying the options of the given configuration, e.g. the serializer options or the frame interceptors.
+    pub fn with_configuration(options: &ConnectionConfiguration) -> Self {
+        let storage = UpdatableActionStorage::with_options(options.get_invocation_id_prefix(), options.get_parser_config(), options.get_serializer_options());
+
+        MockCommunication::create(&ConnectionData::new("mock://mock/mock".to_string(), String::new(), options), storage)
+    }
+
+    fn create(configuration: &ConnectionData, storage: UpdatableActionStorage) -> Self {
+        let mock = MockCommunication {
+            _configuration: configuration.clone(),
+            _actions: storage,
+            _buffer: Arc::new(Mutex::new(RecordBuffer::new(Framing::default()).with_interceptor(configuration.get_inbound_interceptor()))),
+            _sent: Arc::new(Mutex::new(Vec::new())),
+            _connected: Arc::new(AtomicBool::new(true)),
+            _close: Arc::new(Mutex::new(CloseHandler::new())),
+            _counters: Arc::new(ConnectionCounters::new()),
+            _ready: Arc::new(ReadySignal::new()),
+        };
+
+        // the mock is connected from the start
+        mock._ready.set(true);
+
+        mock
+    }
+
+    /// Routes a frame to the client as if it was received from the hub, records must end with the record separator.
+    pub fn feed(&self, frame: &str) {
+        let messages = self._buffer.lock().unwrap().push(frame);
+        self._counters.add_received(messages.len(), frame.len());
+
+        let mut storage = self._actions.clone();
+
+        for message in messages {
+            match MessageParser::parse_message::<Ping>(&message) {
+                Ok(ping) => {
+                    if ping.message_type() == MessageType::Close {
+                        if let Ok(close) = MessageParser::parse_message::<Close>(&message) {
+                            self._close.lock().unwrap().set_close_message(&close);
+                        }
+                    }
+
+                    let res = storage.process_message(message, ping.message_type());
+
+                    if res.is_err() {
+                        error!("Message could not be processed: {}", res.unwrap_err());
+                    }
+                },
+                Err(_) => error!("Message could not be parsed: {:?}", message),
+            }
+        }
+    }
+
+    /// Routes a binary frame to the client as if it was received from the hub.
+    pub fn feed_binary(&self, frame: &[u8]) {
+        self._counters.add_received(0, frame.len());
+
+        if !self._actions.clone().notify_binary(frame) {
+            info!("Binary frame of {} bytes is ignored", frame.len());
+        }
+    }
+
+    /// Returns the messages sent by the client so far.
+    pub fn sent(&self) -> Vec<String> {
+        self._sent.lock().unwrap().clone()
+    }
+
+    /// Simulates the hub closing the connection with the given reason.
+    pub fn close(&self, reason: Option<String>) {
+        self._connected.store(false, Ordering::Relaxed);
+
+        self.notify_close(reason);
+    }
+
+    /// Simulates the server sending a websocket close frame with the given code and reason.
+    pub fn close_with_code(&self, code: u16, reason: &str) {
+        self._connected.store(false, Ordering::Relaxed);
+        self._close.lock().unwrap().set_close_frame(code, reason);
+
+        self.notify_close(None);
+    }
+
+    fn notify_close(&self, reason: Option<String>) {
+        if let Some(reason) = &reason {
+            self._close.lock().unwrap().set_reason(reason.clone());
+        }
+
+        let description = self._close.lock().unwrap().describe();
+        self._actions.clone().cancel_pending(description);
+
+        let notification = self._close.lock().unwrap().close(reason);
+
+        if let Some((mut callback, reason)) = notification {
+            callback(reason);
+            self._close.lock().unwrap().restore(callback);
+        }
+    }
+
+    fn record<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
+        if !self._connected.load(Ordering::Relaxed) {
+            return Err(format!("Client is not connected, cannot send"));
+        }
+
+        let json = MessageParser::to_json(&data).map_err(|e| e.to_string())?;
+        let json = intercept_outbound(&self._configuration.get_outbound_interceptor(), json);
+        self._sent.lock().unwrap().push(MessageParser::strip_record_separator(&json).to_string());
+        self._counters.add_sent(1);
+
+        Ok(())
+    }
+}
+
+impl Communication for MockCommunication {
+    async fn connect(configuration: &ConnectionData, storage: UpdatableActionStorage) -> Result<Self, String> {
+        Ok(MockCommunication::create(configuration, storage))
+    }
+
+    async fn reconnect(&mut self) -> Result<(), String> {
+        self._close.lock().unwrap().reset();
+        self._ready.reset();
+        self._connected.store(true, Ordering::Relaxed);
+        self._ready.set(true);
+
+        Ok(())
+    }
+
+    async fn send<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
+        self.record(data)
+    }
+
+    async fn send_many<T: serde::Serialize>(&self, data: Vec<T>) -> Result<(), String> {
+        for item in data {
+            self.record(item)?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<(), String> {
+        if !self._connected.load(Ordering::Relaxed) {
+            return Err(format!("Client is not connected, cannot flush"));
+        }
+
+        Ok(())
+    }
+
+    fn get_storage(&self) -> Result<UpdatableActionStorage, String> {
+        Ok(self._actions.clone())
+    }
+
+    fn is_connected(&self) -> bool {
+        self._connected.load(Ordering::Relaxed)
+    }
+
+    fn get_close_reason(&self) -> Option<String> {
+        self._close.lock().unwrap().get_reason()
+    }
+
+    fn get_close_code(&self) -> Option<u16> {
+        self._close.lock().unwrap().get_code()
+    }
+
+    fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>) {
+        self._close.lock().unwrap().get_reconnect_hint()
+    }
+
+    fn get_connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo::default()
+    }
+
+    fn on_close(&self, callback: CloseCallback) {
+        self._close.lock().unwrap().set_callback(callback);
+    }
+
+    fn get_counters(&self) -> Arc<ConnectionCounters> {
+        self._counters.clone()
+    }
+
+    fn get_ready(&self) -> Arc<ReadySignal> {
+        self._ready.clone()
+    }
+
+    fn get_configuration(&self) -> &ConnectionData {
+        &self._configuration
+    }
+
+    fn disconnect(&mut self) {
+        if Arc::strong_count(&self._connected) == 1 {
+            self.close(None);
+        }
+    }
+}
+
+/// The transport of a client, either a websocket or a mock injected by tests.
+#[derive(Clone)]
+pub enum CommunicationClient {
+    Socket(SocketClient),
+    Mock(MockCommunication),
+}
+
+impl Communication for CommunicationClient {
+    async fn connect(configuration: &ConnectionData, storage: UpdatableActionStorage) -> Result<Self, String> {
+        Ok(CommunicationClient::Socket(SocketClient::connect(configuration, storage).await?))
+    }
+
+    async fn reconnect(&mut self) -> Result<(), String> {
+        match self {
+            CommunicationClient::Socket(client) => client.reconnect().await,
+            CommunicationClient::Mock(client) => client.reconnect().await,
+        }
+    }
+
+    async fn send<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
+        match self {
+            CommunicationClient::Socket(client) => client.send(data).await,
+            CommunicationClient::Mock(client) => client.send(data).await,
+        }
+    }
+
+    async fn send_many<T: serde::Serialize>(&self, data: Vec<T>) -> Result<(), String> {
+        match self {
+            CommunicationClient::Socket(client) => client.send_many(data).await,
+            CommunicationClient::Mock(client) => client.send_many(data).await,
+        }
+    }
+
+    async fn flush(&self) -> Result<(), String> {
+        match self {
+            CommunicationClient::Socket(client) => client.flush().await,
+            CommunicationClient::Mock(client) => client.flush().await,
+        }
+    }
+
+    fn get_storage(&self) -> Result<UpdatableActionStorage, String> {
+        match self {
+            CommunicationClient::Socket(client) => client.get_storage(),
+            CommunicationClient::Mock(client) => client.get_storage(),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        match self {
+            CommunicationClient::Socket(client) => client.is_connected(),
+            CommunicationClient::Mock(client) => client.is_connected(),
+        }
+    }
+
+    fn get_close_reason(&self) -> Option<String> {
+        match self {
+            CommunicationClient::Socket(client) => client.get_close_reason(),
+            CommunicationClient::Mock(client) => client.get_close_reason(),
+        }
+    }
+
+    fn get_close_code(&self) -> Option<u16> {
+        match self {
+            CommunicationClient::Socket(client) => client.get_close_code(),
+            CommunicationClient::Mock(client) => client.get_close_code(),
+        }
+    }
+
+    fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>) {
+        match self {
+            CommunicationClient::Socket(client) => client.get_reconnect_hint(),
+            CommunicationClient::Mock(client) => client.get_reconnect_hint(),
+        }
+    }
+
+    fn get_connection_info(&self) -> ConnectionInfo {
+        match self {
+            CommunicationClient::Socket(client) => client.get_connection_info(),
+            CommunicationClient::Mock(client) => client.get_connection_info(),
+        }
+    }
+
+    fn on_close(&self, callback: CloseCallback) {
+        match self {
+            CommunicationClient::Socket(client) => client.on_close(callback),
+            CommunicationClient::Mock(client) => client.on_close(callback),
+        }
+    }
+
+    fn get_counters(&self) -> Arc<ConnectionCounters> {
+        match self {
+            CommunicationClient::Socket(client) => client.get_counters(),
+            CommunicationClient::Mock(client) => client.get_counters(),
+        }
+    }
+
+    fn get_ready(&self) -> Arc<ReadySignal> {
+        match self {
+            CommunicationClient::Socket(client) => client.get_ready(),
+            CommunicationClient::Mock(client) => client.get_ready(),
+        }
+    }
+
+    fn get_configuration(&self) -> &ConnectionData {
+        match self {
+            CommunicationClient::Socket(client) => client.get_configuration(),
+            CommunicationClient::Mock(client) => client.get_configuration(),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        match self {
+            CommunicationClient::Socket(client) => client.disconnect(),
+            CommunicationClient::Mock(client) => client.disconnect(),
+        }
+    }
+}
@@ -1,49 +1,186 @@
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::{Arc, Mutex as SyncMutex}, time::Duration};
 
-use crate::{execution::{Storage, UpdatableActionStorage}, protocol::{messages::{MessageParser, RECORD_SEPARATOR}, negotiate::{HandshakeRequest, Ping}}};
+use crate::{client::{ConnectionConfiguration, ConnectionInfo, FrameInterceptor}, execution::{Storage, UpdatableActionStorage}, protocol::{close::Close, messages::{Framing, MessageParser}, negotiate::{HandshakeRequest, HandshakeResponse, MessageType, Ping}}};
 
-use super::Communication;
+use super::{intercept_outbound, CloseCallback, CloseHandler, Communication, ConnectionCounters, ConnectionData, ReadySignal, RecordBuffer};
 use futures::{stream::{SplitSink, SplitStream}, SinkExt, StreamExt};
-use http::Uri;
+use http::{header::{AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL}, HeaderValue, Uri};
 use log::{error, info};
-use tokio::{net::TcpStream, sync::Mutex, task::JoinHandle};
-use tokio_native_tls::native_tls::TlsConnector;
-use tokio_websockets::{ClientBuilder, MaybeTlsStream, Message, WebSocketStream};
+use tokio::{net::TcpStream, sync::{mpsc, Mutex}, task::JoinHandle};
+use tokio_websockets::{ClientBuilder, Connector, Limits, MaybeTlsStream, Message, WebSocketStream};
 
 struct CommunicationConnection {
     _sink: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
     _receiver: Option<JoinHandle<()>>,
+    _counters: Arc<ConnectionCounters>,
+    _framing: Framing,
+    _outbound: Option<FrameInterceptor>,
+    _inbound: Option<FrameInterceptor>,
 }
 
 impl CommunicationConnection {
-    fn start_receiving(&mut self, mut stream: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>, mut storage: impl Storage + Send + 'static) {
+    fn start_receiving(&mut self, mut stream: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>, mut storage: impl Storage + Send + 'static, close: Arc<SyncMutex<CloseHandler>>, server_timeout: Duration, concurrent_callbacks: bool) {
+        let counters = self._counters.clone();
+        let framing = self._framing.clone();
+        let inbound = self._inbound.clone();
+        let dispatcher = if concurrent_callbacks {
+            Some(CommunicationConnection::start_dispatching(storage.clone()))
+        } else {
+            None
+        };
         let handle = tokio::spawn(async move {
-            while let Some(item) = stream.next().await {
-                if item.is_ok() {
-                    for message in CommunicationClient::get_messages(item.unwrap()) {
-                        let ping = MessageParser::parse_message::<Ping>(&message);
+            let mut buffer = RecordBuffer::new(framing).with_interceptor(inbound);
+
+            loop {
+                // every frame, including the pings of the server, restarts the timeout
+                let item = match tokio::time::timeout(server_timeout, stream.next()).await {
+                    Ok(Some(item)) => item,
+                    Ok(None) => break,
+                    Err(_) => {
+                        let reason = format!("Server timeout elapsed without receiving a message in {:?}", server_timeout);
+                        error!("{}", reason);
+                        close.lock().unwrap().set_reason(reason);
+
+                        break;
+                    },
+                };
+
+                match item {
+                    Ok(item) => {
+                        if let Some((code, reason)) = item.as_close() {
+                            info!("Close frame received with code {:?}: {}", code, reason);
+                            close.lock().unwrap().set_close_frame(u16::from(code), reason);
+                        }
+
+                        let size = item.as_payload().len();
 
-                        if ping.is_ok() {
-                            let res = storage.process_message(message, ping.unwrap().message_type());
+                        if item.is_binary() {
+                            // binary frames are not messages of the JSON protocol, e.g. a custom side channel of the server
+                            counters.add_received(0, size);
 
-                            if res.is_err() {
-                                error!("Error occured parsing message {}", res.unwrap_err());
+                            if !storage.notify_binary(item.as_payload()) {
+                                info!("Binary frame of {} bytes is ignored", size);
                             }
-                        } else {
-                            error!("Message could not be parsed: {:?}", message);
+
+                            continue;
                         }
-                    }
+
+                        let messages = CommunicationClient::get_messages(&mut buffer, item);
+                        counters.add_received(messages.len(), size);
+
+                        for message in messages {
+                            let ping = MessageParser::parse_message::<Ping>(&message);
+
+                            if ping.is_ok() {
+                                let message_type = ping.unwrap().message_type();
+
+                                if message_type == MessageType::Close {
+                                    if let Ok(message) = MessageParser::parse_message::<Close>(&message) {
+                                        close.lock().unwrap().set_close_message(&message);
+                                    }
+                                }
+
+                                let res = match &dispatcher {
+                                    Some(sender) if CommunicationConnection::is_dispatched(message_type) => {
+                                        sender.send((message, message_type)).map_err(|e| format!("Callback dispatcher has ended: {}", e))
+                                    },
+                                    _ => storage.process_message(message, message_type),
+                                };
+
+                                if res.is_err() {
+                                    error!("Error occured parsing message {}", res.unwrap_err());
+                                }
+                            } else {
+                                error!("Message could not be parsed: {:?}", message);
+                            }
+                        }
+                    },
+                    Err(e) => {
+                        // e.g. a frame exceeding the configured message size, the socket is unusable afterwards
+                        let reason = format!("Connection error: {}", e.to_string());
+                        error!("{}", reason);
+                        close.lock().unwrap().set_reason(reason);
+
+                        break;
+                    },
                 }
             }
+
+            info!("Receiver loop has ended");
+            let reason = close.lock().unwrap().describe();
+            storage.cancel_pending(reason);
+            CommunicationClient::notify_close(&close, None);
         });
 
         self._receiver = Some(handle);
     }
 
+    /// Runs the callbacks in a task of their own, one message after another, until the read loop drops the sender
+    fn start_dispatching(mut storage: impl Storage + Send + 'static) -> mpsc::UnboundedSender<(String, MessageType)> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(String, MessageType)>();
+
+        tokio::spawn(async move {
+            while let Some((message, message_type)) = receiver.recv().await {
+                let res = storage.process_message(message, message_type);
+
+                if res.is_err() {
+                    error!("Error occured parsing message {}", res.unwrap_err());
+                }
+            }
+
+            info!("Callback dispatcher has ended");
+        });
+
+        sender
+    }
+
+    /// Invocations of the hub and their cancellations run callbacks, every other message completes the actions of the client
+    fn is_dispatched(message_type: MessageType) -> bool {
+        matches!(message_type, MessageType::Invocation | MessageType::StreamInvocation | MessageType::CancelInvocation)
+    }
+
     async fn send<T: serde::Serialize>(&mut self, data: T) -> Result<(), String> {
-        let json = MessageParser::to_json(&data).unwrap();
+        let json = MessageParser::to_json_framed(&data, &self._framing).map_err(|e| e.to_string())?;
+        let json = intercept_outbound(&self._outbound, json);
         
-        self._sink.send(Message::text(json)).await.map_err(|e| e.to_string())
+        self._sink.send(Message::text(json)).await.map_err(|e| e.to_string())?;
+        self._counters.add_sent(1);
+
+        Ok(())
+    }
+
+    async fn send_many<T: serde::Serialize>(&mut self, data: Vec<T>) -> Result<(), String> {
+        if self._framing == Framing::MessagePerFrame {
+            for item in data {
+                self.send(item).await?;
+            }
+
+            return Ok(());
+        }
+
+        let mut json = String::new();
+        let count = data.len();
+
+        for item in data {
+            json.push_str(&MessageParser::to_json_framed(&item, &self._framing).map_err(|e| e.to_string())?);
+        }
+
+        let json = intercept_outbound(&self._outbound, json);
+        self._sink.send(Message::text(json)).await.map_err(|e| e.to_string())?;
+        self._counters.add_sent(count);
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), String> {
+        self._sink.flush().await.map_err(|e| e.to_string())
+    }
+
+    fn is_receiving(&self) -> bool {
+        match &self._receiver {
+            Some(handle) => !handle.is_finished(),
+            None => false,
+        }
     }
 
     fn stop_receiving(&mut self) {
@@ -65,6 +202,48 @@ impl Drop for CommunicationConnection {
     }
 }
 
+/// Accepts any server certificate, used only when `dangerous_accept_invalid_certs` is configured.
+/// Handshake signatures are still verified, so the peer must own the key of the presented certificate.
+#[cfg(feature = "rustls")]
+#[derive(Debug)]
+struct NoCertificateVerification(Arc<rustls::crypto::CryptoProvider>);
+
+#[cfg(feature = "rustls")]
+impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
 enum ConnectionState {
     NotConnected,
     Connected(Arc<Mutex<CommunicationConnection>>)
@@ -80,24 +259,32 @@ impl Clone for ConnectionState{
 }
 
 pub struct CommunicationClient {
-    _endpoint: Uri,
+    _configuration: ConnectionData,
     _state : ConnectionState,
     _actions: UpdatableActionStorage,
+    _close: Arc<SyncMutex<CloseHandler>>,
+    _counters: Arc<ConnectionCounters>,
+    _info: Arc<SyncMutex<ConnectionInfo>>,
+    _ready: Arc<ReadySignal>,
 }
 
 impl Clone for CommunicationClient {
     fn clone(&self) -> Self {
         Self { 
-            _endpoint: self._endpoint.clone(), 
+            _configuration: self._configuration.clone(),
             _state: self._state.clone(),
             _actions: self._actions.clone(),
+            _close: self._close.clone(),
+            _counters: self._counters.clone(),
+            _info: self._info.clone(),
+            _ready: self._ready.clone(),
         }
     }
 }
 
 impl Communication for CommunicationClient {
-    async fn connect(configuration: &super::ConnectionData) -> Result<Self, String> {
-        let mut ret = CommunicationClient::create(configuration);
+    async fn connect(configuration: &super::ConnectionData, storage: UpdatableActionStorage) -> Result<Self, String> {
+        let mut ret = CommunicationClient::create(configuration, storage);
 
         let res = ret.connect_internal().await;
 
@@ -108,11 +295,17 @@ impl Communication for CommunicationClient {
         }
     }
 
+    async fn reconnect(&mut self) -> Result<(), String> {
+        self._ready.reset();
+
+        self.connect_internal().await
+    }
+
     fn get_storage(&self) -> Result<crate::execution::UpdatableActionStorage, String> {
         Ok(self._actions.clone())
     }
     
-    async fn send<T: serde::Serialize>(&mut self, data: T) -> Result<(), String> {
+    async fn send<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
         match &self._state {
             ConnectionState::NotConnected => Err(format!("Client is not connected, cannot send")),
             ConnectionState::Connected(mutex) => {
@@ -123,6 +316,73 @@ impl Communication for CommunicationClient {
         }
     }
 
+    async fn send_many<T: serde::Serialize>(&self, data: Vec<T>) -> Result<(), String> {
+        match &self._state {
+            ConnectionState::NotConnected => Err(format!("Client is not connected, cannot send")),
+            ConnectionState::Connected(mutex) => {
+                let mut connection = mutex.lock().await;
+
+                connection.send_many(data).await
+            },
+        }
+    }
+
+    async fn flush(&self) -> Result<(), String> {
+        match &self._state {
+            ConnectionState::NotConnected => Err(format!("Client is not connected, cannot flush")),
+            ConnectionState::Connected(mutex) => {
+                let mut connection = mutex.lock().await;
+
+                connection.flush().await
+            },
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        match &self._state {
+            ConnectionState::NotConnected => false,
+            ConnectionState::Connected(mutex) => {
+                match mutex.try_lock() {
+                    Ok(connection) => connection.is_receiving(),
+                    // the lock is only held while sending, which needs a live connection
+                    Err(_) => true,
+                }
+            },
+        }
+    }
+
+    fn get_close_reason(&self) -> Option<String> {
+        self._close.lock().unwrap().get_reason()
+    }
+
+    fn get_close_code(&self) -> Option<u16> {
+        self._close.lock().unwrap().get_code()
+    }
+
+    fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>) {
+        self._close.lock().unwrap().get_reconnect_hint()
+    }
+
+    fn get_connection_info(&self) -> ConnectionInfo {
+        self._info.lock().unwrap().clone()
+    }
+
+    fn on_close(&self, callback: CloseCallback) {
+        self._close.lock().unwrap().set_callback(callback);
+    }
+
+    fn get_counters(&self) -> Arc<ConnectionCounters> {
+        self._counters.clone()
+    }
+
+    fn get_ready(&self) -> Arc<ReadySignal> {
+        self._ready.clone()
+    }
+
+    fn get_configuration(&self) -> &ConnectionData {
+        &self._configuration
+    }
+
     fn disconnect(&mut self) {
         let mut drop = false;
 
@@ -144,62 +404,140 @@ impl Communication for CommunicationClient {
 
         if drop {
             self._state = ConnectionState::NotConnected;
+            self._actions.cancel_pending("The connection is closed".to_string());
+            CommunicationClient::notify_close(&self._close, None);
         }
     }    
 }
 
 impl CommunicationClient {
-    fn create(configuration: &super::ConnectionData) -> Self {
-        info!("Creating communication client to {}", &configuration.get_endpoint());
-        let endpoint = Uri::from_str(&configuration.get_endpoint()).expect(&format!("The endpoint Uri {:?} is invalid", configuration.get_endpoint().as_str()));
+    fn create(configuration: &super::ConnectionData, storage: UpdatableActionStorage) -> Self {
+        info!("Creating communication client to {}", ConnectionConfiguration::redact_url(&configuration.get_endpoint()));
 
         CommunicationClient {
-            _endpoint: endpoint,           
+            _configuration: configuration.clone(),
             _state: ConnectionState::NotConnected,
-            _actions: UpdatableActionStorage::new(),
+            _actions: storage,
+            _close: Arc::new(SyncMutex::new(CloseHandler::new())),
+            _counters: Arc::new(ConnectionCounters::new()),
+            _info: Arc::new(SyncMutex::new(ConnectionInfo::default())),
+            _ready: Arc::new(ReadySignal::new()),
         }
     }
 
     async fn connect_internal(&mut self) -> Result<(), String> {
+        self._close.lock().unwrap().reset();
+        let connection = self.open_connection_on_runtime().await;
+        // the handshake is part of opening the connection
+        self._ready.set(connection.is_ok());
+        let connection = connection?;
+
+        if let ConnectionState::Connected(mutex) = &self._state {
+            // replacing the connection in place lets every clone use the new socket
+            info!("Replacing the underlying connection...");
+            let mut current = mutex.lock().await;
+            *current = connection;
+
+            return Ok(());
+        }
+
+        self._state = ConnectionState::Connected(Arc::new(Mutex::new(connection)));
+
+        Ok(())
+    }
+
+    async fn open_connection_on_runtime(&self) -> Result<CommunicationConnection, String> {
+        match self._configuration.get_runtime() {
+            Some(runtime) => {
+                // the socket is registered with the runtime opening it and the receiver is spawned there too
+                let client = self.clone();
+
+                runtime.spawn(async move { client.open_connection().await })
+                    .await
+                    .map_err(|e| format!("The connection cannot be opened on the configured runtime: {}", e))?
+            },
+            None => self.open_connection().await,
+        }
+    }
+
+    async fn open_connection(&self) -> Result<CommunicationConnection, String> {
         let stream: Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, http::Response<()>), tokio_websockets::Error>;
-        info!("Connecting to endpoint {}", self._endpoint);
+        // the access token provider is asked for a fresh token on every (re)connect
+        let url = self._configuration.resolve_endpoint().await?;
+        let endpoint = Uri::from_str(&url).map_err(|e| format!("The endpoint Uri {:?} is invalid: {}", ConnectionConfiguration::redact_url(&url), e))?;
+        info!("Connecting to endpoint {}", ConnectionConfiguration::redact_url(&url));
          
-        if Some("wss") == self._endpoint.scheme_str() {
+        let mut builder = ClientBuilder::from_uri(endpoint.clone());
+
+        if let Some(authorization) = self._configuration.get_authorization() {
+            let value = HeaderValue::from_str(&authorization).map_err(|e| format!("Invalid authorization header: {}", e.to_string()))?;
+
+            builder = builder.add_header(AUTHORIZATION, value).map_err(|e| e.to_string())?;
+        }
+
+        if let Some(subprotocol) = self._configuration.get_subprotocol() {
+            let value = HeaderValue::from_str(&subprotocol).map_err(|e| format!("Invalid subprotocol header: {}", e.to_string()))?;
+
+            builder = builder.add_header(SEC_WEBSOCKET_PROTOCOL, value).map_err(|e| e.to_string())?;
+        }
+
+        if let Some(size) = self._configuration.get_max_message_size() {
+            builder = builder.limits(Limits::default().max_payload_len(Some(size)));
+        }
+
+        if Some("wss") == endpoint.scheme_str() {
             info!("Connection to secure endpoint...");
-            let connector = TlsConnector::builder()
-                .danger_accept_invalid_certs(true)
-                .min_protocol_version(Some(tokio_native_tls::native_tls::Protocol::Tlsv12))
-                .build()
-                .map_err(|e| format!("Cannot create TLS connector: {}", e.to_string()))?;
-        
-            let connector = tokio_websockets::Connector::NativeTls(connector.into());
-            stream = ClientBuilder::from_uri(self._endpoint.clone()).connector(&connector).connect().await;             
+            let connector = CommunicationClient::create_connector(&self._configuration)?;
+
+            if let Some(hostname) = self._configuration.get_sni_hostname() {
+                // the socket targets the configured address, the handshake presents and verifies the SNI hostname
+                let host = endpoint.host().ok_or(format!("The endpoint has no host"))?;
+                let port = endpoint.port_u16().unwrap_or(443);
+                info!("Connecting to {}:{} with SNI hostname {}", host, port, hostname);
+
+                let socket = TcpStream::connect((host.trim_start_matches('[').trim_end_matches(']'), port)).await.map_err(|e| format!("Cannot connect to {}:{}: {}", host, port, e))?;
+                let tls = connector.wrap(&hostname, socket).await.map_err(|e| format!("TLS handshake with {} failed: {}", hostname, e))?;
+
+                stream = builder.connect_on(tls).await;
+            } else {
+                stream = builder.connector(&connector).connect().await;
+            }
         } else {
             info!("Connection to plain endpoint...");
-            stream = ClientBuilder::from_uri(self._endpoint.clone()).connect().await;
+            stream = builder.connect().await;
         }        
 
         match stream {
             Ok((ws, _)) => {
+                *self._info.lock().unwrap() = CommunicationClient::describe_stream(ws.get_ref());
                 let (mut write, mut read) = ws.split();
 
                 info!("Initiating handshake...");
-                let handshake = HandshakeRequest::new("json".to_string());
-                let message = MessageParser::to_json(&handshake).unwrap();
+                let version = self._configuration.get_protocol_version();
+                let handshake = HandshakeRequest::new("json".to_string(), version);
+                let framing = self._configuration.get_framing();
+                let message = MessageParser::to_json_framed(&handshake, &framing).unwrap();
+                let outbound = self._configuration.get_outbound_interceptor();
+                let inbound = self._configuration.get_inbound_interceptor();
+                let message = intercept_outbound(&outbound, message);
                 let hsres = write.send(Message::text(message)).await;
         
                 if hsres.is_ok() {            
                     let mut connection = CommunicationConnection {
                         _receiver: None,
                         _sink: write,
+                        _counters: self._counters.clone(),
+                        _framing: framing.clone(),
+                        _outbound: outbound,
+                        _inbound: inbound.clone(),
                     };
             
                     if let Some(hand) = read.next().await {
                         if hand.is_ok() {
-                            connection.start_receiving(read, self._actions.clone());                
-                            self._state = ConnectionState::Connected(Arc::new(Mutex::new(connection)));
+                            CommunicationClient::check_handshake(hand.unwrap(), framing, inbound, version)?;
+                            connection.start_receiving(read, self._actions.clone(), self._close.clone(), self._configuration.get_server_timeout(), self._configuration.get_concurrent_callbacks());                
         
-                            Ok(())
+                            Ok(connection)
                         } else {
                             return Err(hand.err().unwrap().to_string());
                         }
@@ -216,13 +554,117 @@ impl CommunicationClient {
         }
     }
     
-    fn get_messages(message: Message) -> Vec<String> {
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    fn create_connector(configuration: &ConnectionData) -> Result<Connector, String> {
+        use tokio_native_tls::native_tls::{Certificate, Protocol, TlsConnector};
+
+        let mut builder = TlsConnector::builder();
+        builder
+            .danger_accept_invalid_certs(configuration.get_accept_invalid_certs())
+            .min_protocol_version(Some(Protocol::Tlsv12));
+
+        if let Some(pem) = configuration.get_root_certificates() {
+            let bundle = String::from_utf8(pem).map_err(|e| format!("Root certificates are not valid PEM: {}", e.to_string()))?;
+            let end_marker = "-----END CERTIFICATE-----";
+
+            for block in bundle.split_inclusive(end_marker).filter(|b| b.contains(end_marker)) {
+                let certificate = Certificate::from_pem(block.trim().as_bytes())
+                    .map_err(|e| format!("Cannot read root certificate: {}", e.to_string()))?;
+
+                builder.add_root_certificate(certificate);
+            }
+        }
+
+        let connector = builder
+            .build()
+            .map_err(|e| format!("Cannot create TLS connector: {}", e.to_string()))?;
+
+        Ok(Connector::NativeTls(connector.into()))
+    }
+
+    #[cfg(feature = "rustls")]
+    fn create_connector(configuration: &ConnectionData) -> Result<Connector, String> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        if let Some(pem) = configuration.get_root_certificates() {
+            for certificate in rustls_pemfile::certs(&mut pem.as_slice()) {
+                let certificate = certificate.map_err(|e| format!("Cannot read root certificate: {}", e.to_string()))?;
+
+                roots.add(certificate).map_err(|e| format!("Cannot add root certificate: {}", e.to_string()))?;
+            }
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Cannot create TLS connector: {}", e.to_string()))?;
+
+        let config = if configuration.get_accept_invalid_certs() {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoCertificateVerification(provider)))
+                .with_no_client_auth()
+        } else {
+            builder
+                .with_root_certificates(roots)
+                .with_no_client_auth()
+        };
+
+        Ok(Connector::Rustls(tokio_rustls::TlsConnector::from(Arc::new(config))))
+    }
+
+    #[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+    fn create_connector(_configuration: &ConnectionData) -> Result<Connector, String> {
+        Err("Secure endpoints require either the `native-tls` or the `rustls` feature".to_string())
+    }
+
+    fn notify_close(close: &Arc<SyncMutex<CloseHandler>>, reason: Option<String>) {
+        let notification = close.lock().unwrap().close(reason);
+
+        if let Some((mut callback, reason)) = notification {
+            callback(reason);
+            close.lock().unwrap().restore(callback);
+        }
+    }
+
+    #[allow(unreachable_patterns)]
+    fn describe_stream(stream: &MaybeTlsStream<TcpStream>) -> ConnectionInfo {
+        let (socket, secure, tls_version) = match stream {
+            MaybeTlsStream::Plain(socket) => (Some(socket), false, None),
+            #[cfg(feature = "native-tls")]
+            MaybeTlsStream::NativeTls(tls) => (Some(tls.get_ref().get_ref().get_ref()), true, None),
+            #[cfg(feature = "rustls")]
+            MaybeTlsStream::Rustls(tls) => {
+                let (socket, session) = tls.get_ref();
+
+                (Some(socket), true, session.protocol_version().map(|v| format!("{:?}", v)))
+            },
+            // the variants depend on the enabled TLS features
+            _ => (None, true, None),
+        };
+
+        ConnectionInfo {
+            remote_addr: socket.and_then(|s| s.peer_addr().ok()),
+            local_addr: socket.and_then(|s| s.local_addr().ok()),
+            secure: secure,
+            tls_version: tls_version,
+        }
+    }
+
+    fn check_handshake(message: Message, framing: Framing, inbound: Option<FrameInterceptor>, version: u8) -> Result<(), String> {
+        let messages = CommunicationClient::get_messages(&mut RecordBuffer::new(framing).with_interceptor(inbound), message);
+        let first = messages.first().ok_or("Handshake response is empty".to_string())?;
+        let response = MessageParser::parse_message::<HandshakeResponse>(first)
+            .map_err(|e| format!("Handshake response cannot be parsed: {}", e))?;
+
+        response.verify(version)
+    }
+
+    fn get_messages(buffer: &mut RecordBuffer, message: Message) -> Vec<String> {
         if message.is_text() {
             if let Some(txt) = message.as_text() {
-                return txt.split(RECORD_SEPARATOR)
-                   .map(|s| MessageParser::strip_record_separator(s).to_string())
-                   .filter(|s| s.len() > 0)
-                   .collect();
+                return buffer.push(txt);
             }
         }
 
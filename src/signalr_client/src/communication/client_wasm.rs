@@ -1,14 +1,15 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
 
 use log::{error, info, warn};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_sockets::{ConnectionStatus, PollingClient};
+use wasm_timer::Instant;
 
-use crate::{completer::CompletedFuture, 
+use crate::{client::ConnectionInfo, completer::CompletedFuture, 
     execution::
-        {ManualFutureState, Storage, UpdatableActionStorage}, protocol::{messages::{MessageParser, RECORD_SEPARATOR}, negotiate::{HandshakeRequest, HandshakeResponse, Ping}}};
+        {ManualFutureState, Storage, UpdatableActionStorage}, protocol::{close::Close, messages::{Framing, MessageParser}, negotiate::{HandshakeRequest, HandshakeResponse, MessageType, Ping}}};
 
-use super::common::Communication;
+use super::common::{intercept_outbound, CloseCallback, CloseHandler, Communication, ConnectionCounters, ConnectionData, ReadySignal, RecordBuffer};
 
 #[wasm_bindgen]
 extern "C" {
@@ -26,19 +27,33 @@ pub enum ConnectionState {
 pub struct CommunicationClient {
     _client: Option<Rc<RefCell<PollingClient>>>,
     _state: Rc<RefCell<ConnectionState>>,
-    _token: Option<f64>,
+    _token: Rc<RefCell<Option<f64>>>,
+    _handles: Rc<()>,
+    _configuration: ConnectionData,
+    _actions: UpdatableActionStorage,
+    _close: Rc<RefCell<CloseHandler>>,
+    _counters: Arc<ConnectionCounters>,
+    _ready: Arc<ReadySignal>,
 }
 
 impl Clone for CommunicationClient {
     fn clone(&self) -> Self {
         if self._client.is_some() {
-            let count = Rc::strong_count(self._client.as_ref().unwrap());
-
-            info!("Cloning communication client {} times", count + 1);
+            info!("Cloning communication client {} times", Rc::strong_count(&self._handles) + 1);
         } else {
             info!("Cloning empty communication client");
         }
-        Self { _client: self._client.clone(), _state: self._state.clone(), _token: self._token.clone() }
+        Self { 
+            _client: self._client.clone(), 
+            _state: self._state.clone(), 
+            _token: self._token.clone(),
+            _handles: self._handles.clone(),
+            _configuration: self._configuration.clone(),
+            _actions: self._actions.clone(),
+            _close: self._close.clone(),
+            _counters: self._counters.clone(),
+            _ready: self._ready.clone(),
+        }
     }
 }
 
@@ -49,8 +64,9 @@ impl Drop for CommunicationClient {
 }
 
 impl Communication for CommunicationClient {
-    async fn connect(configuration: &super::ConnectionData) -> Result<Self, String> {
-        let mut ret = CommunicationClient::create(configuration);
+    async fn connect(configuration: &super::ConnectionData, storage: UpdatableActionStorage) -> Result<Self, String> {
+        let endpoint = configuration.resolve_endpoint().await?;
+        let mut ret = CommunicationClient::create(configuration, &endpoint, storage);
 
         let res = ret.connect_internal().await;
 
@@ -61,12 +77,49 @@ impl Communication for CommunicationClient {
         }
     }
 
-    async fn send<T: serde::Serialize>(&mut self, data: T) -> Result<(), String> {
+    async fn reconnect(&mut self) -> Result<(), String> {
+        // the access token provider is asked for a fresh token on every reconnect
+        let endpoint = self._configuration.resolve_endpoint().await?;
+        let client = PollingClient::new(&endpoint)
+            .map_err(|e| format!("Connection client cannot be created: {:?}", e))?;
+
+        if let Some(current) = self._client.as_ref() {
+            // the polling loop holds the same client, so the socket is replaced in place
+            let mut current = current.borrow_mut();
+            let _ = current.close();
+            *current = client;
+        } else {
+            self._client = Some(Rc::new(RefCell::new(client)));
+        }
+
+        {
+            let mut state = self._state.borrow_mut();
+            *state = ConnectionState::Connect(ManualFutureState::new());
+        }
+
+        self._close.borrow_mut().reset();
+        self._ready.reset();
+
+        self.connect_internal().await
+    }
+
+    async fn send<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
         let res = self.send_internal(data);
 
         CompletedFuture::new(res).await
     }
 
+    async fn send_many<T: serde::Serialize>(&self, data: Vec<T>) -> Result<(), String> {
+        let res = self.send_many_internal(data);
+
+        CompletedFuture::new(res).await
+    }
+
+    async fn flush(&self) -> Result<(), String> {
+        // send_string hands the frame to the browser immediately, nothing is buffered here
+        CompletedFuture::new(Ok(())).await
+    }
+
     fn get_storage(&self) -> Result<UpdatableActionStorage, String> {
         let procstate: ConnectionState;
 
@@ -83,27 +136,104 @@ impl Communication for CommunicationClient {
         }
     }
 
+    fn is_connected(&self) -> bool {
+        let processing = matches!(*self._state.borrow(), ConnectionState::Process(_));
+
+        if processing && self._token.borrow().is_some() {
+            if let Some(client) = self._client.as_ref() {
+                return client.borrow().status() == ConnectionStatus::Connected;
+            }
+        }
+
+        false
+    }
+
+    fn get_close_reason(&self) -> Option<String> {
+        self._close.borrow().get_reason()
+    }
+
+    fn get_close_code(&self) -> Option<u16> {
+        // the polling client of wasm_sockets does not expose close frames
+        self._close.borrow().get_code()
+    }
+
+    fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>) {
+        self._close.borrow().get_reconnect_hint()
+    }
+
+    fn get_connection_info(&self) -> ConnectionInfo {
+        // the browser does not expose the socket
+        ConnectionInfo {
+            secure: self._configuration.get_endpoint().starts_with("wss"),
+            ..ConnectionInfo::default()
+        }
+    }
+
+    fn on_close(&self, callback: CloseCallback) {
+        self._close.borrow_mut().set_callback(callback);
+    }
+
+    fn get_counters(&self) -> Arc<ConnectionCounters> {
+        self._counters.clone()
+    }
+
+    fn get_ready(&self) -> Arc<ReadySignal> {
+        self._ready.clone()
+    }
+
+    fn get_configuration(&self) -> &ConnectionData {
+        &self._configuration
+    }
+
     fn disconnect(&mut self) {
         self.disconnect_internal();
     }    
 }
 
 impl CommunicationClient {
-    fn create(configuration: &super::ConnectionData) -> Self {
+    fn create(configuration: &super::ConnectionData, endpoint: &str, storage: UpdatableActionStorage) -> Self {
         info!("Creating communication client to {}", &configuration.get_endpoint());
-        let res = PollingClient::new(&configuration.get_endpoint());
+
+        if let Some(subprotocol) = configuration.get_subprotocol() {
+            // PollingClient::new does not accept protocols
+            warn!("Subprotocol {} is not supported by the WASM client, ignoring", subprotocol);
+        }
+
+        if let Some(hostname) = configuration.get_sni_hostname() {
+            // the browser always presents the host of the url
+            warn!("SNI hostname {} is not supported by the WASM client, ignoring", hostname);
+        }
+
+        if configuration.get_concurrent_callbacks() {
+            // the polling loop runs on the single thread of the browser
+            warn!("Concurrent callbacks are not supported by the WASM client, ignoring");
+        }
+
+        let res = PollingClient::new(endpoint);
 
         if res.is_ok() {
             CommunicationClient {
                 _state: Rc::new(RefCell::new(ConnectionState::Connect(ManualFutureState::new()))),
                 _client: Some(Rc::new(RefCell::new(res.unwrap()))),
-                _token: None,
+                _token: Rc::new(RefCell::new(None)),
+                _handles: Rc::new(()),
+                _configuration: configuration.clone(),
+                _actions: storage,
+                _close: Rc::new(RefCell::new(CloseHandler::new())),
+                _counters: Arc::new(ConnectionCounters::new()),
+                _ready: Arc::new(ReadySignal::new()),
             }    
         } else {
             CommunicationClient {
                 _state: Rc::new(RefCell::new(ConnectionState::Connect(ManualFutureState::new()))),
                 _client: None,
-                _token: None,
+                _token: Rc::new(RefCell::new(None)),
+                _handles: Rc::new(()),
+                _configuration: configuration.clone(),
+                _actions: storage,
+                _close: Rc::new(RefCell::new(CloseHandler::new())),
+                _counters: Arc::new(ConnectionCounters::new()),
+                _ready: Arc::new(ReadySignal::new()),
             }    
         }        
     }
@@ -118,25 +248,35 @@ impl CommunicationClient {
 
         if let ConnectionState::Connect(mut connected) = connstate {
             if self._client.is_some() {
-                let refclient = self._client.as_ref().unwrap().clone();
-                let refstate = self._state.clone();
-        
-                let closure = wasm_bindgen::prelude::Closure::wrap(Box::new(move || {
-                    CommunicationClient::polling_loop(&refclient, &refstate);
-                }) as Box<dyn Fn()>);
-        
-                info!("Starting poll loop");
-                let token = setInterval(&closure, 100);
-                closure.forget();
+                if self._token.borrow().is_none() {
+                    let refclient = self._client.as_ref().unwrap().clone();
+                    let refstate = self._state.clone();
+                    let refclose = self._close.clone();
+                    let refcounters = self._counters.clone();
+                    let refbuffer = Rc::new(RefCell::new(RecordBuffer::new(self._configuration.get_framing()).with_interceptor(self._configuration.get_inbound_interceptor())));
+                    let refseen = Rc::new(RefCell::new((Instant::now(), 0)));
+                    let timeout = self._configuration.get_server_timeout();
+                    let version = self._configuration.get_protocol_version();
+            
+                    let closure = wasm_bindgen::prelude::Closure::wrap(Box::new(move || {
+                        CommunicationClient::polling_loop(&refclient, &refstate, &refclose, &refcounters, &refbuffer, version);
+                        CommunicationClient::check_server_timeout(&refclient, &refstate, &refclose, &refseen, &refcounters, timeout);
+                    }) as Box<dyn Fn()>);
+            
+                    info!("Starting poll loop");
+                    let token = setInterval(&closure, 100);
+                    closure.forget();
+                    *self._token.borrow_mut() = Some(token);
+                }
         
                 info!("Waiting for uplink...");
                 connected.awaiter().await;
-                self._token = Some(token);
     
                 info!("Initiating handshake...");
-                let r = self.send(HandshakeRequest::new("json".to_string())).await;
+                let r = self.send(HandshakeRequest::new("json".to_string(), self._configuration.get_protocol_version())).await;
     
                 if r.is_err() {
+                    self._ready.set(false);
                     return Err(format!("Handshake cannot be sent. {}", r.unwrap_err()));
                 }
     
@@ -157,10 +297,12 @@ impl CommunicationClient {
         
         if let ConnectionState::Handshake(mut handshake) = handstate {
             let shook = handshake.awaiter().await;
+            // the waiters of client.ready() learn the outcome of the handshake awaited here
+            self._ready.set(shook);
 
             if shook {
                 let mut state = self._state.borrow_mut(); 
-                *state = ConnectionState::Process(UpdatableActionStorage::new());
+                *state = ConnectionState::Process(self._actions.clone());
             } else {
                 return Err("Unsuccessfull handshake".to_string());
             }
@@ -169,32 +311,68 @@ impl CommunicationClient {
         Ok(())
     }
 
-    fn get_messages(message: wasm_sockets::Message) -> Vec<String> {
+    fn get_messages(buffer: &mut RecordBuffer, message: wasm_sockets::Message) -> Vec<String> {
         match message {
             wasm_sockets::Message::Text(txt) => {
-                txt.split(RECORD_SEPARATOR).map(|s| MessageParser::strip_record_separator(s).to_string()).collect()
+                buffer.push(&txt)
             },
             wasm_sockets::Message::Binary(_) => {
-                panic!("Binary message is not supported");
+                // receive_messages routes the binary frames before they get here
+                Vec::new()
             },
         }
     }
 
     fn send_internal<T: serde::Serialize>(&self, data: T) -> Result<(), String> {
-        let json = MessageParser::to_json(&data).unwrap();
+        let json = MessageParser::to_json_framed(&data, &self._configuration.get_framing()).map_err(|e| e.to_string())?;
+        let json = intercept_outbound(&self._configuration.get_outbound_interceptor(), json);
         // debug!("CLIENT invocation json: {}", json);
 
         // debug!("CLIENT is borrowing polling wasm client");
 
         if self._client.is_some() {
             let bclient = self._client.as_ref().unwrap().borrow();
-            return bclient.send_string(&json).map_err(|e| e.as_string().unwrap());    
+            bclient.send_string(&json).map_err(|e| e.as_string().unwrap())?;
+            self._counters.add_sent(1);
+
+            return Ok(());
         } else {
             return Err(format!("The client is not connected. Cannot send data"));
         }
     }
 
-    fn polling_loop(client: &Rc<RefCell<wasm_sockets::PollingClient>>, state: &Rc<RefCell<ConnectionState>>) {
+    fn send_many_internal<T: serde::Serialize>(&self, data: Vec<T>) -> Result<(), String> {
+        let framing = self._configuration.get_framing();
+
+        if framing == Framing::MessagePerFrame {
+            for item in data {
+                self.send_internal(item)?;
+            }
+
+            return Ok(());
+        }
+
+        let mut json = String::new();
+        let count = data.len();
+
+        for item in data {
+            json.push_str(&MessageParser::to_json_framed(&item, &framing).map_err(|e| e.to_string())?);
+        }
+
+        let json = intercept_outbound(&self._configuration.get_outbound_interceptor(), json);
+
+        if self._client.is_some() {
+            let bclient = self._client.as_ref().unwrap().borrow();
+            bclient.send_string(&json).map_err(|e| e.as_string().unwrap())?;
+            self._counters.add_sent(count);
+
+            return Ok(());
+        } else {
+            return Err(format!("The client is not connected. Cannot send data"));
+        }
+    }
+
+    fn polling_loop(client: &Rc<RefCell<wasm_sockets::PollingClient>>, state: &Rc<RefCell<ConnectionState>>, close: &Rc<RefCell<CloseHandler>>, counters: &Arc<ConnectionCounters>, buffer: &Rc<RefCell<RecordBuffer>>, version: u8) {
         let status = client.borrow().status();
         
         if status == ConnectionStatus::Connected {
@@ -205,26 +383,41 @@ impl CommunicationClient {
                     connected.complete(true);
                 },
                 ConnectionState::Handshake(handshake) => {
-                    let messages = CommunicationClient::receive_messages(client);
+                    let messages = CommunicationClient::receive_messages(client, buffer, None);
 
                     if messages.len() == 1 {
                         let hs = MessageParser::parse_message::<HandshakeResponse>(messages.first().unwrap());
 
                         if hs.is_ok() {
-                            handshake.complete(true);
+                            let verified = hs.unwrap().verify(version);
+
+                            if let Err(reason) = &verified {
+                                error!("{}", reason);
+                            }
+
+                            handshake.complete(verified.is_ok());
                         } else {
                             handshake.complete(false);
                         }
                     }
                 },
                 ConnectionState::Process(storage) => {
-                    let messages = CommunicationClient::receive_messages(client);
+                    let messages = CommunicationClient::receive_messages(client, buffer, Some(&mut *storage));
+                    counters.add_received(messages.len(), messages.iter().map(|m| m.len()).sum());
 
                     for message in messages {
                         let ping = MessageParser::parse_message::<Ping>(&message);
 
                         if ping.is_ok() {
-                            let r = storage.process_message(message, ping.unwrap().message_type());
+                            let message_type = ping.unwrap().message_type();
+
+                            if message_type == MessageType::Close {
+                                if let Ok(message) = MessageParser::parse_message::<Close>(&message) {
+                                    close.borrow_mut().set_close_message(&message);
+                                }
+                            }
+
+                            let r = storage.process_message(message, message_type);
 
                             if r.is_err() {
                                 error!("Message could not be processed: {}", r.unwrap_err());
@@ -239,17 +432,78 @@ impl CommunicationClient {
             info!("Hub is connecting");
         } else if status == ConnectionStatus::Disconnected {
             warn!("Hub is NOT connected at endpoint {}", client.borrow().url);
+
+            if CommunicationClient::cancel_pending(state, close.borrow().describe()) {
+                CommunicationClient::notify_close(close, None);
+            }
         } else if status == ConnectionStatus::Error {
-            error!("Hub error at endpoint {}", client.borrow().url);
+            let reason = format!("Hub error at endpoint {}", client.borrow().url);
+            error!("{}", reason);
+
+            if CommunicationClient::cancel_pending(state, reason.clone()) {
+                CommunicationClient::notify_close(close, Some(reason));
+            }
+        }
+    }
+
+    /// Cancels the pending invocations of a processing connection, returns `false` if the connection is not processing.
+    /// The state is released before returning, so the close callback may use the client.
+    fn cancel_pending(state: &Rc<RefCell<ConnectionState>>, reason: String) -> bool {
+        if let ConnectionState::Process(storage) = &mut *state.borrow_mut() {
+            storage.cancel_pending(reason);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn check_server_timeout(client: &Rc<RefCell<wasm_sockets::PollingClient>>, state: &Rc<RefCell<ConnectionState>>, close: &Rc<RefCell<CloseHandler>>, last_seen: &Rc<RefCell<(Instant, usize)>>, counters: &Arc<ConnectionCounters>, timeout: Duration) {
+        let received = counters.get_bytes_received();
+        let mut seen = last_seen.borrow_mut();
+
+        // every message, including the pings of the server, restarts the timeout
+        if !matches!(*state.borrow(), ConnectionState::Process(_)) || seen.1 != received {
+            *seen = (Instant::now(), received);
+        } else if seen.0.elapsed() > timeout {
+            let reason = format!("Server timeout elapsed without receiving a message in {:?}", timeout);
+            error!("{}", reason);
+
+            *seen = (Instant::now(), received);
+            let _ = client.borrow_mut().close();
+            CommunicationClient::notify_close(close, Some(reason));
+        }
+    }
+
+    fn notify_close(close: &Rc<RefCell<CloseHandler>>, reason: Option<String>) {
+        let notification = close.borrow_mut().close(reason);
+
+        if let Some((mut callback, reason)) = notification {
+            callback(reason);
+            close.borrow_mut().restore(callback);
         }
     }
 
-    fn receive_messages(client: &Rc<RefCell<wasm_sockets::PollingClient>>) -> Vec<String> {
+    /// Returns the protocol messages, the binary frames are passed to the handler of the storage if there is one
+    fn receive_messages(client: &Rc<RefCell<wasm_sockets::PollingClient>>, buffer: &Rc<RefCell<RecordBuffer>>, mut storage: Option<&mut UpdatableActionStorage>) -> Vec<String> {
         let response = client.borrow_mut().receive();
         let mut ret = Vec::new();
 
         for msg in response {
-            for message in CommunicationClient::get_messages(msg).into_iter() {
+            if let wasm_sockets::Message::Binary(frame) = &msg {
+                let handled = match storage.as_mut() {
+                    Some(storage) => storage.notify_binary(frame),
+                    None => false,
+                };
+
+                if !handled {
+                    info!("Binary frame of {} bytes is ignored", frame.len());
+                }
+
+                continue;
+            }
+
+            for message in CommunicationClient::get_messages(&mut buffer.borrow_mut(), msg).into_iter() {
                 if message.len() > 0 {
                     ret.push(message);
                 }
@@ -260,22 +514,26 @@ impl CommunicationClient {
     }
 
     fn disconnect_internal(&mut self) {
-        if self._token.is_some() {
-            if self._client.is_some() {
-                let refc = self._client.as_ref().unwrap();
-                let count = Rc::strong_count(refc);
+        // the poll loop keeps its own reference to the polling client, only the clones of the communication client are counted
+        let count = Rc::strong_count(&self._handles);
 
-                if count == 2 {
-                    info!("Breaking message loop, destroying clients...");
-                    let token = self._token.take().unwrap();
-    
-                    clearInterval(token);
-                } else {
-                    info!("Connection cannot be destroyed, has still {} references", count);
-                }
-            } else {
-                info!("Connection is already disconnected");
+        if count > 1 {
+            info!("Connection cannot be destroyed, has still {} references", count - 1);
+            return;
+        }
+
+        let token = self._token.borrow_mut().take();
+
+        if let Some(token) = token {
+            info!("Breaking message loop, destroying clients...");
+            clearInterval(token);
+
+            if let Some(client) = self._client.as_ref() {
+                let _ = client.borrow_mut().close();
             }
+
+            self._actions.cancel_pending("The connection is closed".to_string());
+            CommunicationClient::notify_close(&self._close, None);
         } else {
             info!("Message loop is presumably stopped already");
         }
@@ -1,59 +1,754 @@
-use crate::client::{Authentication, ConnectionConfiguration};
+use crate::client::{AccessTokenProvider, Authentication, ConnectionConfiguration, ConnectionInfo, FrameInterceptor, ACCESS_TOKEN_PARAM};
+use crate::completer::{ManualFuture, ManualFutureCompleter};
 use crate::execution::UpdatableActionStorage; 
-use crate::protocol::negotiate::NegotiateResponseV0;
+use crate::protocol::{close::Close, messages::{Framing, MessageParser}, negotiate::NegotiateResponseV0};
 use base64::{engine::general_purpose, Engine};
+use std::{fmt, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}, time::Duration};
 use serde::{de::DeserializeOwned, Serialize};
+use log::warn;
+
+#[cfg(feature = "metrics")]
+use crate::client::InvocationTiming;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+pub use wasm_timer::Instant;
 
 const WEB_SOCKET_TRANSPORT: &str = "WebSockets";
 const TEXT_TRANSPORT_FORMAT: &str = "Text";
+const CONNECTION_ID_PARAM: &str = "id";
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct ConnectionData {
     endpoint: String,
     connection_id: String,
+    root_certificates: Option<Vec<u8>>,
+    accept_invalid_certs: bool,
+    authorization: Option<String>,
+    max_message_size: Option<usize>,
+    subprotocol: Option<String>,
+    sni_hostname: Option<String>,
+    framing: Framing,
+    protocol_version: u8,
+    server_timeout: Duration,
+    concurrent_callbacks: bool,
+    timeout_header: Option<String>,
+    transport: String,
+    transfer_format: String,
+    access_token_provider: Option<AccessTokenProvider>,
+    outbound_interceptor: Option<FrameInterceptor>,
+    inbound_interceptor: Option<FrameInterceptor>,
+    negotiation: Option<Negotiation>,
+    #[cfg(not(target_arch = "wasm32"))]
+    runtime: Option<tokio::runtime::Handle>,
+}
+
+impl fmt::Debug for ConnectionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionData")
+            .field("endpoint", &ConnectionConfiguration::redact_url(&self.endpoint))
+            .field("connection_id", &self.connection_id)
+            .field("root_certificates", &self.root_certificates.is_some())
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("authorization", &self.authorization.as_ref().map(|_| "***"))
+            .field("max_message_size", &self.max_message_size)
+            .field("subprotocol", &self.subprotocol)
+            .field("sni_hostname", &self.sni_hostname)
+            .field("framing", &self.framing)
+            .field("protocol_version", &self.protocol_version)
+            .field("server_timeout", &self.server_timeout)
+            .field("concurrent_callbacks", &self.concurrent_callbacks)
+            .field("timeout_header", &self.timeout_header)
+            .field("transport", &self.transport)
+            .field("transfer_format", &self.transfer_format)
+            .field("access_token_provider", &self.access_token_provider.is_some())
+            .field("outbound_interceptor", &self.outbound_interceptor.is_some())
+            .field("inbound_interceptor", &self.inbound_interceptor.is_some())
+            .field("negotiation", &self.negotiation.is_some())
+            .finish()
+    }
 }
 
 impl ConnectionData {
+    pub(crate) fn new(endpoint: String, connection_id: String, options: &ConnectionConfiguration) -> Self {
+        ConnectionData {
+            endpoint: endpoint,
+            connection_id: connection_id,
+            root_certificates: options.get_root_certificates(),
+            accept_invalid_certs: options.get_accept_invalid_certs(),
+            authorization: HttpClient::get_authorization(&options.get_authentication()),
+            max_message_size: options.get_max_message_size(),
+            subprotocol: options.get_subprotocol(),
+            sni_hostname: options.get_sni_hostname(),
+            framing: options.get_framing(),
+            protocol_version: options.get_protocol_version(),
+            server_timeout: options.get_server_timeout(),
+            concurrent_callbacks: options.get_concurrent_callbacks(),
+            timeout_header: options.get_timeout_header(),
+            transport: WEB_SOCKET_TRANSPORT.to_string(),
+            transfer_format: TEXT_TRANSPORT_FORMAT.to_string(),
+            access_token_provider: options.get_access_token_provider(),
+            outbound_interceptor: options.get_outbound_interceptor(),
+            inbound_interceptor: options.get_inbound_interceptor(),
+            negotiation: Negotiation::from_options(options),
+            #[cfg(not(target_arch = "wasm32"))]
+            runtime: options.get_runtime(),
+        }
+    }
+
     pub fn get_endpoint(&self) -> String {
         self.endpoint.clone()
     }
 
+    /// Returns the endpoint to connect to, carrying a fresh token when an access token provider is configured
+    /// and the id of a freshly negotiated connection when negotiation is enabled.
+    pub(crate) async fn resolve_endpoint(&self) -> Result<String, String> {
+        let token = match &self.access_token_provider {
+            Some(provider) => Some(provider().await.map_err(|e| format!("The access token cannot be fetched: {}", e))?),
+            None => None,
+        };
+
+        let endpoint = match &token {
+            Some(token) => ConnectionData::replace_access_token(&self.endpoint, token),
+            None => self.endpoint.clone(),
+        };
+
+        match &self.negotiation {
+            Some(negotiation) => {
+                let connection_id = negotiation.negotiate(token.as_deref()).await?;
+
+                Ok(ConnectionData::replace_query_param(&endpoint, CONNECTION_ID_PARAM, &connection_id))
+            },
+            None => Ok(endpoint),
+        }
+    }
+
+    pub(crate) fn replace_access_token(endpoint: &str, token: &str) -> String {
+        ConnectionData::replace_query_param(endpoint, ACCESS_TOKEN_PARAM, token)
+    }
+
+    pub(crate) fn replace_query_param(endpoint: &str, key: &str, value: &str) -> String {
+        let (base, query) = endpoint.split_once('?').unwrap_or((endpoint, ""));
+
+        let mut params: Vec<&str> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(key))
+            .collect();
+
+        let param = format!("{}={}", key, value);
+        params.push(&param);
+
+        format!("{}?{}", base, params.join("&"))
+    }
+
     #[allow(dead_code)]
     pub fn get_connection_id(&self) -> String {
         self.connection_id.clone()
     }
+
+    #[allow(dead_code)]
+    pub fn get_root_certificates(&self) -> Option<Vec<u8>> {
+        self.root_certificates.clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_accept_invalid_certs(&self) -> bool {
+        self.accept_invalid_certs
+    }
+
+    #[allow(dead_code)]
+    pub fn get_authorization(&self) -> Option<String> {
+        self.authorization.clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_max_message_size(&self) -> Option<usize> {
+        self.max_message_size
+    }
+
+    #[allow(dead_code)]
+    pub fn get_subprotocol(&self) -> Option<String> {
+        self.subprotocol.clone()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_sni_hostname(&self) -> Option<String> {
+        self.sni_hostname.clone()
+    }
+
+    pub(crate) fn get_framing(&self) -> Framing {
+        self.framing.clone()
+    }
+
+    pub(crate) fn get_outbound_interceptor(&self) -> Option<FrameInterceptor> {
+        self.outbound_interceptor.clone()
+    }
+
+    pub(crate) fn get_inbound_interceptor(&self) -> Option<FrameInterceptor> {
+        self.inbound_interceptor.clone()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn get_runtime(&self) -> Option<tokio::runtime::Handle> {
+        self.runtime.clone()
+    }
+
+    pub(crate) fn get_protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
+    pub fn get_server_timeout(&self) -> Duration {
+        self.server_timeout
+    }
+
+    pub(crate) fn get_concurrent_callbacks(&self) -> bool {
+        self.concurrent_callbacks
+    }
+
+    pub(crate) fn get_timeout_header(&self) -> Option<String> {
+        self.timeout_header.clone()
+    }
+
+    pub fn get_transport(&self) -> &str {
+        &self.transport
+    }
+
+    pub fn get_transfer_format(&self) -> &str {
+        &self.transfer_format
+    }
 }
 
 pub trait Communication : Clone {
-    async fn connect(configuration: &ConnectionData) -> Result<Self, String>;
-    async fn send<T: Serialize>(&mut self, data: T) -> Result<(), String>;
+    async fn connect(configuration: &ConnectionData, storage: UpdatableActionStorage) -> Result<Self, String>;
+    async fn reconnect(&mut self) -> Result<(), String>;
+    async fn send<T: Serialize>(&self, data: T) -> Result<(), String>;
+    async fn send_many<T: Serialize>(&self, data: Vec<T>) -> Result<(), String>;
+    /// Writes out the messages buffered by the transport, if there are any
+    async fn flush(&self) -> Result<(), String>;
     fn get_storage(&self) -> Result<UpdatableActionStorage, String>;
+    fn is_connected(&self) -> bool;
+    fn get_close_reason(&self) -> Option<String>;
+    fn get_close_code(&self) -> Option<u16>;
+    fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>);
+    fn get_connection_info(&self) -> ConnectionInfo;
+    fn on_close(&self, callback: CloseCallback);
+    fn get_counters(&self) -> Arc<ConnectionCounters>;
+    fn get_ready(&self) -> Arc<ReadySignal>;
+    fn get_configuration(&self) -> &ConnectionData;
     fn disconnect(&mut self);
 }
 
+/// Receives the timing of every finished invocation.
+#[cfg(all(feature = "metrics", not(target_arch = "wasm32")))]
+pub(crate) type TimingObserver = Box<dyn FnMut(&InvocationTiming) + Send>;
+
+#[cfg(all(feature = "metrics", target_arch = "wasm32"))]
+pub(crate) type TimingObserver = Box<dyn FnMut(&InvocationTiming)>;
+
+/// Message counters of a connection, shared by all clones of the client.
+pub struct ConnectionCounters {
+    messages_sent: AtomicUsize,
+    messages_received: AtomicUsize,
+    bytes_received: AtomicUsize,
+    last_received: Mutex<Option<Instant>>,
+    waiters: Mutex<Vec<ManualFutureCompleter<()>>>,
+    #[cfg(feature = "metrics")]
+    timing: Mutex<Option<TimingObserver>>,
+}
+
+impl ConnectionCounters {
+    pub(crate) fn new() -> Self {
+        ConnectionCounters {
+            messages_sent: AtomicUsize::new(0),
+            messages_received: AtomicUsize::new(0),
+            bytes_received: AtomicUsize::new(0),
+            last_received: Mutex::new(None),
+            waiters: Mutex::new(Vec::new()),
+            #[cfg(feature = "metrics")]
+            timing: Mutex::new(None),
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn set_timing_observer(&self, observer: TimingObserver) {
+        *self.timing.lock().unwrap() = Some(observer);
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn record_timing(&self, timing: InvocationTiming) {
+        if let Some(observer) = self.timing.lock().unwrap().as_mut() {
+            observer(&timing);
+        }
+    }
+
+    pub(crate) fn add_sent(&self, messages: usize) {
+        self.messages_sent.fetch_add(messages, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_received(&self, messages: usize, bytes: usize) {
+        self.messages_received.fetch_add(messages, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+        *self.last_received.lock().unwrap() = Some(Instant::now());
+
+        let waiters: Vec<_> = self.waiters.lock().unwrap().drain(..).collect();
+
+        for waiter in waiters {
+            waiter.complete(());
+        }
+    }
+
+    /// Returns a future resolving when the next frame arrives from the server.
+    pub(crate) fn next_received(&self) -> ManualFuture<()> {
+        let (future, completer) = ManualFuture::new();
+        self.waiters.lock().unwrap().push(completer);
+
+        future
+    }
+
+    pub(crate) fn get_last_received(&self) -> Option<Instant> {
+        *self.last_received.lock().unwrap()
+    }
+
+    pub(crate) fn get_messages_sent(&self) -> usize {
+        self.messages_sent.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_messages_received(&self) -> usize {
+        self.messages_received.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get_bytes_received(&self) -> usize {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+}
+
+/// The outcome of the handshake of a connection, shared by all clones of the client.
+pub struct ReadySignal {
+    ready: Mutex<Option<bool>>,
+    waiters: Mutex<Vec<ManualFutureCompleter<bool>>>,
+}
+
+impl ReadySignal {
+    pub(crate) fn new() -> Self {
+        ReadySignal {
+            ready: Mutex::new(None),
+            waiters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Marks the connection as connecting, waiters arriving from now on wait for the next handshake.
+    pub(crate) fn reset(&self) {
+        *self.ready.lock().unwrap() = None;
+    }
+
+    pub(crate) fn set(&self, ready: bool) {
+        *self.ready.lock().unwrap() = Some(ready);
+
+        let waiters: Vec<_> = self.waiters.lock().unwrap().drain(..).collect();
+
+        for waiter in waiters {
+            waiter.complete(ready);
+        }
+    }
+
+    /// Returns a future resolving with the outcome of the current handshake, immediately if it is known already.
+    pub(crate) fn wait(&self) -> ManualFuture<bool> {
+        let (future, completer) = ManualFuture::new();
+        let ready = *self.ready.lock().unwrap();
+
+        match ready {
+            Some(ready) => completer.complete(ready),
+            None => self.waiters.lock().unwrap().push(completer),
+        }
+
+        future
+    }
+}
+
+/// Collects the text received on a connection and hands out the complete records only.
+///
+/// The server may split a record over several frames, the unterminated tail is kept until its separator arrives.
+/// Without a separator every frame is a record of its own.
+pub(crate) struct RecordBuffer {
+    pending: String,
+    framing: Framing,
+    interceptor: Option<FrameInterceptor>,
+}
+
+impl RecordBuffer {
+    pub(crate) fn new(framing: Framing) -> Self {
+        RecordBuffer {
+            pending: String::new(),
+            framing: framing,
+            interceptor: None,
+        }
+    }
+
+    /// Transforms every frame with the inbound interceptor before it is split into records.
+    pub(crate) fn with_interceptor(mut self, interceptor: Option<FrameInterceptor>) -> Self {
+        self.interceptor = interceptor;
+
+        self
+    }
+
+    pub(crate) fn push(&mut self, text: &str) -> Vec<String> {
+        let intercepted;
+        let text = match &self.interceptor {
+            Some(interceptor) => {
+                intercepted = interceptor(text.to_string());
+                intercepted.as_str()
+            },
+            None => text,
+        };
+
+        let separator = match &self.framing {
+            Framing::Separator(separator) => separator,
+            Framing::MessagePerFrame => {
+                return if text.len() > 0 { vec![text.to_string()] } else { Vec::new() };
+            },
+        };
+
+        self.pending.push_str(text);
+
+        let mut records = Vec::new();
+
+        while let Some(index) = self.pending.find(separator.as_str()) {
+            let record: String = self.pending.drain(..index + separator.len()).collect();
+            let record = record.trim_end_matches(separator.as_str());
+
+            if record.len() > 0 {
+                records.push(record.to_string());
+            }
+        }
+
+        records
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn has_pending(&self) -> bool {
+        self.pending.len() > 0
+    }
+}
+
+/// Transforms a serialized frame with the outbound interceptor, if there is one.
+pub(crate) fn intercept_outbound(interceptor: &Option<FrameInterceptor>, frame: String) -> String {
+    match interceptor {
+        Some(interceptor) => interceptor(frame),
+        None => frame,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type CloseCallback = Box<dyn FnMut(Option<String>) + Send + 'static>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type CloseCallback = Box<dyn FnMut(Option<String>) + 'static>;
+
+/// Keeps the close reason of a connection together with the callback notified when the connection ends.
+pub(crate) struct CloseHandler {
+    reason: Option<String>,
+    code: Option<u16>,
+    allow_reconnect: Option<bool>,
+    retry_after: Option<Duration>,
+    callback: Option<CloseCallback>,
+    notified: bool,
+}
+
+impl CloseHandler {
+    pub(crate) fn new() -> Self {
+        CloseHandler {
+            reason: None,
+            code: None,
+            allow_reconnect: None,
+            retry_after: None,
+            callback: None,
+            notified: false,
+        }
+    }
+
+    pub(crate) fn set_callback(&mut self, callback: CloseCallback) {
+        self.callback = Some(callback);
+    }
+
+    pub(crate) fn set_reason(&mut self, reason: String) {
+        self.reason = Some(reason);
+    }
+
+    pub(crate) fn get_reason(&self) -> Option<String> {
+        self.reason.clone()
+    }
+
+    /// Stores the code of the close frame sent by the server, together with a readable reason unless a more specific one is known.
+    pub(crate) fn set_close_frame(&mut self, code: u16, reason: &str) {
+        self.code = Some(code);
+
+        if self.reason.is_none() {
+            let mut description = format!("Connection closed with code {} ({})", code, CloseHandler::describe_code(code));
+
+            if !reason.is_empty() {
+                description.push_str(&format!(": {}", reason));
+            }
+
+            self.reason = Some(description);
+        }
+    }
+
+    /// Returns the close reason, or a generic one if the connection closed without an error.
+    pub(crate) fn describe(&self) -> String {
+        self.reason.clone().unwrap_or("The connection is closed".to_string())
+    }
+
+    pub(crate) fn get_code(&self) -> Option<u16> {
+        self.code
+    }
+
+    /// Stores the error and the reconnect hints of the `Close` message sent by the hub.
+    pub(crate) fn set_close_message(&mut self, close: &Close) {
+        if let Some(error) = close.get_error() {
+            self.reason = Some(error);
+        }
+
+        self.allow_reconnect = close.get_allow_reconnect();
+        self.retry_after = close.get_retry_after();
+    }
+
+    pub(crate) fn get_reconnect_hint(&self) -> (Option<bool>, Option<Duration>) {
+        (self.allow_reconnect, self.retry_after)
+    }
+
+    fn describe_code(code: u16) -> &'static str {
+        match code {
+            1000 => "normal closure",
+            1001 => "going away",
+            1002 => "protocol error",
+            1003 => "unsupported data",
+            1007 => "invalid payload",
+            1008 => "policy violation",
+            1009 => "message too big",
+            1010 => "missing extension",
+            1011 => "internal error",
+            1012 => "service restart",
+            1013 => "try again later",
+            _ => "unknown",
+        }
+    }
+
+    /// Prepares the handler for a new connection
+    pub(crate) fn reset(&mut self) {
+        self.reason = None;
+        self.code = None;
+        self.allow_reconnect = None;
+        self.retry_after = None;
+        self.notified = false;
+    }
+
+    /// Marks the connection closed, hands out the callback only once per connection.
+    /// The callback is invoked by the caller outside of any lock and given back with `restore`.
+    pub(crate) fn close(&mut self, reason: Option<String>) -> Option<(CloseCallback, Option<String>)> {
+        if self.notified {
+            return None;
+        }
+
+        self.notified = true;
+
+        if reason.is_some() {
+            self.reason = reason;
+        }
+
+        let reason = self.reason.clone();
+
+        self.callback.take().map(|callback| (callback, reason))
+    }
+
+    pub(crate) fn restore(&mut self, callback: CloseCallback) {
+        if self.callback.is_none() {
+            self.callback = Some(callback);
+        }
+    }
+}
+
+/// The negotiate request sent before each connect when negotiation is enabled.
+#[derive(Clone)]
+pub(crate) struct Negotiation {
+    url: String,
+    authentication: Authentication,
+    retries: u32,
+    backoff: Duration,
+    client: HttpClient,
+}
+
+impl Negotiation {
+    pub(crate) fn from_options(options: &ConnectionConfiguration) -> Option<Negotiation> {
+        if !options.get_negotiate() {
+            return None;
+        }
+
+        let (retries, backoff) = options.get_negotiate_retries();
+
+        Some(Negotiation {
+            url: options.get_negotiate_url(),
+            authentication: options.get_authentication(),
+            retries,
+            backoff,
+            client: HttpClient::new(options),
+        })
+    }
+
+    /// Negotiates a new connection and returns its id, the id is valid for a single websocket only.
+    pub(crate) async fn negotiate(&self, token: Option<&str>) -> Result<String, String> {
+        let url = match token {
+            Some(token) => ConnectionData::replace_access_token(&self.url, token),
+            None => self.url.clone(),
+        };
+
+        let response = self.client.post_with_retries::<NegotiateResponseV0>(url, self.authentication.clone(), self.retries, self.backoff).await
+            .map_err(|e| format!("Negotiation failed: {}", e))?;
+
+        HttpClient::verify_transports(&response)?;
+
+        Ok(response.connection_id)
+    }
+}
+
+#[derive(Clone)]
 pub struct HttpClient {
-    
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    _client: reqwest::Client,
 }
 
 impl HttpClient {
+    #[allow(unused_variables)]
+    pub(crate) fn new(options: &ConnectionConfiguration) -> Self {
+        HttpClient {
+            #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+            _client: options.get_http_client(),
+        }
+    }
+
     pub(crate) async fn negotiate(options: ConnectionConfiguration) -> Result<ConnectionData, String> {
-        // Bỏ qua negotiate, tạo kết nối WebSocket trực tiếp
-        Ok(ConnectionData {
-            endpoint: options.get_socket_url(),
-            connection_id: String::new(), // Connection ID không cần thiết khi không negotiate
-        })
+        // Không negotiate ở đây, tạo cấu hình WebSocket trực tiếp
+        // Khi bật with_negotiate, mỗi lần kết nối và kết nối lại sẽ negotiate trong resolve_endpoint để lấy connection id mới
+        // Không hỗ trợ reconnect giữ connection id, việc này cần stateful reconnect (useStatefulReconnect, message sequence/ack) mà client chưa cài đặt
+        Ok(ConnectionData::new(options.get_socket_url(), String::new(), &options))
     }
 
-    fn create_configuration(endpoint: String, _negotiate: NegotiateResponseV0) -> Option<ConnectionData> {
-        // Luôn trả về Some vì chúng ta đã biết server hỗ trợ WebSocket
-        Some(ConnectionData {
-            endpoint: endpoint,
-            connection_id: String::new(),
-        })
+    pub(crate) fn verify_transports(negotiate: &NegotiateResponseV0) -> Result<(), String> {
+        // chỉ hỗ trợ WebSockets với định dạng Text
+        let supported = negotiate.available_transports.iter().any(|spec| {
+            spec.transport == WEB_SOCKET_TRANSPORT && spec.transfer_formats.iter().any(|format| format == TEXT_TRANSPORT_FORMAT)
+        });
+
+        if supported {
+            Ok(())
+        } else {
+            Err(format!("The server offers neither the {} transport nor the {} transfer format", WEB_SOCKET_TRANSPORT, TEXT_TRANSPORT_FORMAT))
+        }
+    }
+
+    /// Posts the request, retrying connection errors and `5xx` responses with a doubling backoff.
+    pub async fn post_with_retries<T: 'static + DeserializeOwned + Send + Unpin>(&self, endpoint: String, authentication: Authentication, retries: u32, backoff: Duration) -> Result<T, String> {
+        let mut attempt = 0;
+        let mut wait = backoff;
+
+        loop {
+            match self.fetch::<T>(endpoint.clone(), authentication.clone()).await {
+                Ok(result) => return Ok(result),
+                Err((status, error)) => {
+                    if !HttpClient::is_transient(status) || attempt >= retries {
+                        return Err(error);
+                    }
+
+                    attempt += 1;
+                    warn!("Request to {} failed, retrying {}/{} in {:?}: {}", endpoint, attempt, retries, wait, error);
+
+                    HttpClient::sleep(wait).await;
+                    wait = wait.saturating_mul(2);
+                },
+            }
+        }
+    }
+
+    /// Connection errors and server errors may succeed when retried, client errors like `401` or `403` never do.
+    pub(crate) fn is_transient(status: Option<u16>) -> bool {
+        match status {
+            Some(status) => status >= 500,
+            None => true,
+        }
+    }
+
+    pub async fn post<T: 'static + DeserializeOwned + Send + Unpin>(&self, endpoint: String, authentication: Authentication) -> Result<T, String> {
+        self.fetch(endpoint, authentication).await.map_err(|(_, error)| error)
+    }
+
+    /// Posts the request with the configured client, failures carry the response status, or `None` when no response arrived.
+    #[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+    async fn fetch<T: 'static + DeserializeOwned + Send + Unpin>(&self, endpoint: String, authentication: Authentication) -> Result<T, (Option<u16>, String)> {
+        let mut request = self._client.post(&endpoint);
+
+        if let Some(authorization) = HttpClient::get_authorization(&authentication) {
+            request = request.header("Authorization", authorization);
+        }
+
+        let response = request.send().await.map_err(|e| (e.status().map(|s| s.as_u16()), e.to_string()))?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err((Some(status.as_u16()), format!("The request to {} failed with status {}", endpoint, status)));
+        }
+
+        let text = response.text().await.map_err(|e| (Some(status.as_u16()), e.to_string()))?;
+
+        MessageParser::parse_message::<T>(&text).map_err(|e| (Some(status.as_u16()), e))
     }
 
-    pub async fn post<T: 'static + DeserializeOwned + Send>(endpoint: String, _authentication: Authentication) -> Result<T, String> {
-        // Phương thức này sẽ không được sử dụng nữa khi bỏ qua negotiate
-        Err("Direct WebSocket connection, POST not needed".to_string())
+    /// Posts the request, failures carry the response status, or `None` when no response arrived.
+    #[cfg(not(all(feature = "reqwest", not(target_arch = "wasm32"))))]
+    async fn fetch<T: 'static + DeserializeOwned + Send + Unpin>(&self, endpoint: String, authentication: Authentication) -> Result<T, (Option<u16>, String)> {
+        let mut request = ehttp::Request::post(endpoint, Vec::new());
+
+        if let Some(authorization) = HttpClient::get_authorization(&authentication) {
+            request.headers.insert("Authorization", authorization);
+        }
+
+        let (future, completer) = ManualFuture::<Result<T, (Option<u16>, String)>>::new();
+
+        ehttp::fetch(request, move |response| {
+            let result = match response {
+                Ok(response) if response.ok => match response.text() {
+                    Some(text) => MessageParser::parse_message::<T>(text).map_err(|e| (Some(response.status), e)),
+                    None => Err((Some(response.status), format!("The response of {} is not a text", response.url))),
+                },
+                Ok(response) => Err((Some(response.status), format!("The request to {} failed with status {} {}", response.url, response.status, response.status_text))),
+                Err(error) => Err((None, error)),
+            };
+
+            completer.complete(result);
+        });
+
+        future.await
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn sleep(duration: Duration) {
+        let _ = wasm_timer::Delay::new(duration).await;
+    }
+
+    pub(crate) fn get_authorization(authentication: &Authentication) -> Option<String> {
+        match authentication {
+            Authentication::None => None,
+            Authentication::Basic { user, password } => {
+                let credentials = format!("{}:{}", user, password.clone().unwrap_or_default());
+
+                Some(format!("Basic {}", general_purpose::STANDARD.encode(credentials)))
+            },
+            Authentication::Bearer { token } => Some(format!("Bearer {}", token)),
+        }
     }
 }
\ No newline at end of file
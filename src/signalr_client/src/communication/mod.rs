@@ -6,11 +6,17 @@ mod client_wasm;
 #[cfg(not(target_arch = "wasm32"))]
 mod client_tokio;
 
-pub(crate) use common::HttpClient;
-pub use common::{ConnectionData, Communication};
+#[cfg(feature = "test-util")]
+mod client_mock;
 
-#[cfg(target_arch = "wasm32")]
+pub(crate) use common::{intercept_outbound, CloseCallback, CloseHandler, ConnectionCounters, HttpClient, ReadySignal, RecordBuffer};
+pub use common::{ConnectionData, Communication, Instant};
+
+#[cfg(all(target_arch = "wasm32", not(feature = "test-util")))]
 pub use client_wasm::CommunicationClient;
 
-#[cfg(not(target_arch = "wasm32"))]
-pub use client_tokio::CommunicationClient;
\ No newline at end of file
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "test-util")))]
+pub use client_tokio::CommunicationClient;
+
+#[cfg(feature = "test-util")]
+pub use client_mock::{CommunicationClient, MockCommunication};
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::{cell::RefCell, future::Future, pin::Pin, task::{Context, Poll}};
 use std::marker::Unpin;
+use futures::future::FusedFuture;
 
 /// A future that is immediately ready with a value.
 ///
@@ -8,6 +9,9 @@ use std::marker::Unpin;
 /// implementation cannot await any call but still needs to return a future, serving as
 /// an async-to-sync boundary utility.
 ///
+/// The value is returned by the first poll only. Polling the future again, e.g. in a `select!`,
+/// returns `Poll::Pending` forever, which is reported by the `FusedFuture` implementation.
+///
 /// # Examples
 ///
 /// ```
@@ -45,9 +49,19 @@ impl<T: Unpin> Future for CompletedFuture<T> {
     type Output = T;
 
     fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<Self::Output> {
-        let data = self.data.borrow_mut().take().unwrap(); 
-        
-        return Poll::Ready(data);
+        let data = self.data.borrow_mut().take();
+
+        match data {
+            Some(data) => Poll::Ready(data),
+            // the value is already taken, there is nothing to wake up for
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T: Unpin> FusedFuture for CompletedFuture<T> {
+    fn is_terminated(&self) -> bool {
+        self.data.borrow().is_none()
     }
 }
 
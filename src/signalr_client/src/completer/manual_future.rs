@@ -1,8 +1,10 @@
-use std::{future::Future, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll, Waker}};
+use std::{future::Future, pin::Pin, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, task::{Context, Poll, Waker}, time::Duration};
 use std::marker::Unpin;
 
 use log::{error, warn};
 
+use crate::execution::MaybeSend;
+
 enum State<T> {
     Incomplete,
     Waiting(Waker),
@@ -43,6 +45,7 @@ impl<T> State<T> {
 /// ```
 pub struct ManualFuture<T: Unpin> {
     state: Arc<Mutex<State<T>>>,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl<T: Unpin> ManualFuture<T> {
@@ -60,7 +63,78 @@ impl<T: Unpin> ManualFuture<T> {
     pub fn new() -> (Self, ManualFutureCompleter<T>) {
         let state: State<T> = State::new(None);
         let a = Arc::new(Mutex::new(state));
-        (Self { state: a.clone() }, ManualFutureCompleter { state: a })
+        let c = Arc::new(AtomicBool::new(false));
+        (Self { state: a.clone(), cancelled: c.clone() }, ManualFutureCompleter { state: a, cancelled: c })
+    }
+
+    /// Creates a new `ManualFuture` resolving to the given sentinel if it is not completed in time.
+    ///
+    /// The timeout is driven by a spawned task sleeping for the duration on Tokio, and by a timer backed by `setTimeout` on WASM.
+    /// A timed out future is cancelled, so completing it afterwards is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The `Duration` to wait for the completion.
+    /// * `sentinel` - The value the future resolves to when the timeout elapses.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the `ManualFuture` and its `ManualFutureCompleter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (future, completer) = ManualFuture::new_with_timeout(Duration::from_secs(5), Err("timed out".to_string()));
+    /// let watcher = future.clone();
+    ///
+    /// match future.await {
+    ///     Ok(value) => info!("Completed with {}", value),
+    ///     Err(_) if watcher.is_cancelled() => warn!("No value in 5 seconds"),
+    ///     Err(e) => error!("Failed: {}", e),
+    /// }
+    /// ```
+    pub fn new_with_timeout(timeout: Duration, sentinel: T) -> (Self, ManualFutureCompleter<T>)
+        where T: MaybeSend + 'static
+    {
+        let (future, completer) = ManualFuture::new();
+
+        ManualFuture::spawn_timeout(timeout, completer.clone(), sentinel);
+
+        (future, completer)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_timeout(timeout: Duration, timer: ManualFutureCompleter<T>, sentinel: T)
+        where T: Send + 'static
+    {
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            timer.time_out(sentinel);
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_timeout(timeout: Duration, timer: ManualFutureCompleter<T>, sentinel: T)
+        where T: 'static
+    {
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = wasm_timer::Delay::new(timeout).await;
+            timer.time_out(sentinel);
+        });
+    }
+
+    /// Returns if the `ManualFuture` is cancelled, e.g. it resolved to the sentinel of `new_with_timeout` instead of a completed value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let (future, completer) = ManualFuture::new();
+    /// let watcher = future.clone();
+    /// completer.cancel();
+    /// let mustbetrue = watcher.is_cancelled();
+    /// ```
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
     }
 
     /// Returns if the `ManualFuture` is completed.
@@ -87,7 +161,7 @@ impl<T: Unpin> ManualFuture<T> {
 
 impl<T: Unpin> Clone for ManualFuture<T> {
     fn clone(&self) -> Self {
-        Self { state: self.state.clone() }
+        Self { state: self.state.clone(), cancelled: self.cancelled.clone() }
     }
 }
 
@@ -101,12 +175,13 @@ impl<T: Unpin> Clone for ManualFuture<T> {
 /// ```
 pub struct ManualFutureCompleter<T: Unpin> {
     state: Arc<Mutex<State<T>>>,
+    cancelled: Arc<AtomicBool>,
 }
 
 impl<T: Unpin> ManualFutureCompleter<T> {             
     /// Completes the future with the given value.
     ///
-    /// This method triggers the completion of the associated `ManualFuture`.
+    /// This method triggers the completion of the associated `ManualFuture`. Completing a cancelled or timed out future is ignored.
     ///
     /// # Arguments
     ///
@@ -121,10 +196,31 @@ impl<T: Unpin> ManualFutureCompleter<T> {
     pub fn complete(self, value: T) {
         let mut state = self.state.lock().unwrap();
 
+        if self.cancelled.load(Ordering::Relaxed) {
+            warn!("Future is cancelled already, the value is dropped");
+            return;
+        }
+
         match std::mem::replace(&mut *state, State::Complete(Some(value))) {
             State::Incomplete => {}
             State::Waiting(w) => w.wake(),
-            _ => panic!("Future is completed or cancelled already. This happened because complete method is called more than once or after cancel is called. If not sure, study is_completed before calling complete."),
+            _ => panic!("Future is completed already. This happened because complete method is called more than once. If not sure, study is_completed before calling complete."),
+        }
+    }
+
+    /// Resolves the future with the sentinel and marks it cancelled, unless it is completed already.
+    fn time_out(self, sentinel: T) {
+        let mut state = self.state.lock().unwrap();
+
+        if let State::Complete(_) = *state {
+            return;
+        }
+
+        warn!("Future has timed out");
+        self.cancelled.store(true, Ordering::Relaxed);
+
+        if let State::Waiting(w) = std::mem::replace(&mut *state, State::Complete(Some(sentinel))) {
+            w.wake();
         }
     }
 
@@ -141,6 +237,7 @@ impl<T: Unpin> ManualFutureCompleter<T> {
     pub fn cancel(self) {
         warn!("Cancelling future...");
         let mut state = self.state.lock().unwrap();
+        self.cancelled.store(true, Ordering::Relaxed);
 
         match std::mem::replace(&mut *state, State::Complete(None)) {
             _ => {},
@@ -171,7 +268,7 @@ impl<T: Unpin> ManualFutureCompleter<T> {
 
 impl<T: Unpin> Clone for ManualFutureCompleter<T> {
     fn clone(&self) -> Self {
-        Self { state: self.state.clone() }
+        Self { state: self.state.clone(), cancelled: self.cancelled.clone() }
     }
 }
 
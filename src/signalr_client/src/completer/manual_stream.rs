@@ -1,6 +1,6 @@
 use std::{collections::VecDeque, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll, Waker}};
 
-use futures::Stream;
+use futures::{stream::FusedStream, Stream};
 
 struct ManualStreamState<T> {
     queue: Arc<Mutex<VecDeque<Option<T>>>>,
@@ -42,6 +42,7 @@ impl<T> ManualStreamState<T> {
 
 pub struct ManualStream<T> {
     state: ManualStreamState<T>,
+    closed: bool,
 }
 
 impl<T> ManualStream<T> {
@@ -49,7 +50,8 @@ impl<T> ManualStream<T> {
         let state = ManualStreamState::new();
 
         (ManualStream {
-            state: state.clone()
+            state: state.clone(),
+            closed: false,
         }, ManualStreamCompleter {
             state: state
         })
@@ -74,8 +76,15 @@ impl<T> Stream for ManualStream<T> {
     type Item = T;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // once closed, the stream keeps returning None without touching the queue
+        if this.closed {
+            return Poll::Ready(None);
+        }
+
         // debug!("Polling stream...");
-        let mut queue = self.state.queue.lock().unwrap();
+        let mut queue = this.state.queue.lock().unwrap();
         if let Some(item) = queue.pop_front() {
             // debug!("Item popped...");
             match item {
@@ -85,14 +94,21 @@ impl<T> Stream for ManualStream<T> {
                 },
                 None => {
                     // debug!("Poll Ready without value");
+                    this.closed = true;
                     Poll::Ready(None)
                 },
             }
         } else {
             // debug!("Waker is peding..");
-            let mut waker = self.state.waker.lock().unwrap();
+            let mut waker = this.state.waker.lock().unwrap();
             *waker = Some(cx.waker().clone());
             Poll::Pending
         }
     }
 }
+
+impl<T> FusedStream for ManualStream<T> {
+    fn is_terminated(&self) -> bool {
+        self.closed
+    }
+}
@@ -1,9 +1,39 @@
 use crate::protocol::negotiate::MessageType;
 
-pub(crate) trait UpdatableAction {
+use super::{callback::CallbackFn, MaybeSend};
+
+pub(crate) trait UpdatableAction: MaybeSend {
     fn update_with(&mut self, message: &str, message_type: MessageType);
     #[allow(dead_code)]
     fn is_completed(&self) -> bool;
     #[allow(dead_code)]
     fn dispose(self);
+
+    /// Callbacks stay registered, every other action is a pending invocation
+    fn is_callback(&self) -> bool {
+        false
+    }
+
+    /// Resolves the awaiting future with the given error, the action will not be updated anymore
+    fn cancel(&mut self, _reason: &str) {
+    }
+
+    /// Adds another callback for the same target, gives the callback back if the action is not a callback
+    fn add_callback(&mut self, _id: usize, callback: CallbackFn) -> Result<(), CallbackFn> {
+        Err(callback)
+    }
+
+    /// Removes the callback with the given id, returns `true` if no callback is left
+    fn remove_callback(&mut self, _id: usize) -> bool {
+        false
+    }
+
+    /// Suspends or resumes the callback with the given id, returns `false` if it is not found or is in the given state already
+    fn set_callback_active(&mut self, _id: usize, _active: bool) -> bool {
+        false
+    }
+
+    fn is_callback_active(&self, _id: usize) -> bool {
+        false
+    }
 }
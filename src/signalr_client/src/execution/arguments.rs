@@ -1,17 +1,22 @@
 use log::error;
 use serde::Serialize;
 
-use crate::protocol::invoke::Invocation;
+use crate::protocol::{invoke::Invocation, serializer::SerializerOptions};
 
 /// Lets the arguments to be configured for a method on the Hub
+///
+/// A configuration can be cloned and kept as a template of common arguments, see `SignalRClient::argument_template`.
+#[derive(Debug, Clone)]
 pub struct ArgumentConfiguration {
     invocation: Option<Invocation>,
+    serializer: SerializerOptions,
 }
 
 impl ArgumentConfiguration {
-    pub(crate) fn new(invocation: Invocation) -> Self {
+    pub(crate) fn new(invocation: Invocation, serializer: SerializerOptions) -> Self {
         Self {  
-            invocation: Some(invocation)
+            invocation: Some(invocation),
+            serializer: serializer,
         }
     }
 
@@ -52,19 +57,183 @@ impl ArgumentConfiguration {
     /// ```    
     pub fn argument<T: Serialize>(&mut self, value: T) -> &mut ArgumentConfiguration {
         if self.invocation.is_some() {
-            let succ = self.invocation.as_mut().unwrap().with_argument(value);
+            let succ = self.serializer.to_value(&value);
 
-            if succ.is_err() {
-                error!("Argument could not be put into invocation data.");
+            if succ.is_ok() {
+                self.invocation.as_mut().unwrap().with_raw_arguments(vec![succ.unwrap()]);
+            } else {
+                error!("Argument could not be put into invocation data: {}", succ.unwrap_err());
             }
         }
 
         self
     }
 
-    pub(crate) fn build_invocation(mut self) -> Invocation {
+    /// Adds an argument which converts into a JSON value, e.g. a string slice, a number, a `bool` or a `serde_json::Value`.
+    ///
+    /// The value is added as is, without the serializer options of the client, so it cannot fail. Structs are added with `argument`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of the argument to add, which must convert into `serde_json::Value`.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response = client.send_with_args("Forward".to_string(), |c| {
+    ///     c.value("device-1").value(42).value(true);
+    /// }).await;
+    /// ```
+    pub fn value(&mut self, value: impl Into<serde_json::Value>) -> &mut ArgumentConfiguration {
+        if self.invocation.is_some() {
+            self.invocation.as_mut().unwrap().with_value_argument(value);
+        }
+
+        self
+    }
+
+    /// Adds a string argument to the method call configuration, without converting it to a `String` first.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A string slice that holds the argument to add.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let triggered = client.send_and_confirm_with_args("TriggerEntityCallback".to_string(), |c| {
+    ///     c.str_arg("callback1");
+    /// }).await;
+    /// ```
+    pub fn str_arg(&mut self, value: &str) -> &mut ArgumentConfiguration {
+        self.value(value)
+    }
+
+    /// Adds an integer argument to the method call configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - An `i64` holding the argument to add.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response: Result<bool, String> = client.invoke_with_args("SetVolume".to_string(), |c| {
+    ///     c.str_arg("device-1").int_arg(75);
+    /// }).await;
+    /// ```
+    pub fn int_arg(&mut self, value: i64) -> &mut ArgumentConfiguration {
+        self.value(value)
+    }
+
+    /// Adds many already serialized arguments to the method call configuration at once.
+    ///
+    /// The values are appended in order after the arguments added before, without serializing them again.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - The JSON values of the arguments to add.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let forwarded: Vec<serde_json::Value> = vec![json!("device-1"), json!(42)];
+    /// let response = client.send_with_args("Forward".to_string(), |c| {
+    ///     c.arguments(forwarded);
+    /// }).await;
+    /// ```
+    pub fn arguments<I: IntoIterator<Item = serde_json::Value>>(&mut self, values: I) -> &mut ArgumentConfiguration {
+        if self.invocation.is_some() {
+            self.invocation.as_mut().unwrap().with_raw_arguments(values.into_iter().collect());
+        }
+
+        self
+    }
+
+    /// Adds many arguments of the same type to the method call configuration at once.
+    ///
+    /// Every value is added as a separate argument in order, the same way as calling `argument` for each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - The values of the arguments to add, which must implement `Serialize`.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let response: Result<i32, String> = client.invoke_with_args("Sum".to_string(), |c| {
+    ///     c.arguments_typed(vec![1, 2, 3]);
+    /// }).await;
+    /// ```
+    pub fn arguments_typed<T: Serialize, I: IntoIterator<Item = T>>(&mut self, values: I) -> &mut ArgumentConfiguration {
+        for value in values {
+            self.argument(value);
+        }
+
+        self
+    }
+
+    /// Adds the arguments of a template to the method call configuration.
+    ///
+    /// The arguments of the template are appended in order after the arguments added before, the template is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - The configuration holding the common arguments, e.g. created by `SignalRClient::argument_template`.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut ArgumentConfiguration` - Returns a mutable reference to the updated argument configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+    /// let mut tenant = client.argument_template();
+    /// tenant.argument("tenant-1");
+    ///
+    /// let response: Result<bool, String> = client.invoke_with_args("ConnectDevice".to_string(), |c| {
+    ///     c.arguments_from(&tenant).argument("device-42");
+    /// }).await;
+    /// ```
+    pub fn arguments_from(&mut self, template: &ArgumentConfiguration) -> &mut ArgumentConfiguration {
+        let arguments = template.invocation.as_ref().and_then(|invocation| invocation.arguments.clone());
+
+        if let Some(arguments) = arguments {
+            self.arguments(arguments);
+        }
+
+        self
+    }
+
+    pub(crate) fn build_invocation(&self) -> Invocation {
         if self.invocation.is_some() {
-            return self.invocation.take().unwrap();
+            return self.invocation.clone().unwrap();
         } else {
             panic!("Invocation cannot be built before it is provided");
         }     
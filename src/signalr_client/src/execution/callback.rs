@@ -1,34 +1,57 @@
 use crate::{client::SignalRClient, protocol::{invoke::Invocation, negotiate::MessageType}, InvocationContext};
 use crate::protocol::messages::MessageParser;
+use log::error;
 use super::actions::UpdatableAction;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type CallbackFn = Box<dyn FnMut(InvocationContext) + Send + 'static>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type CallbackFn = Box<dyn FnMut(InvocationContext) + 'static>;
+
+struct CallbackEntry {
+    id: usize,
+    callback: CallbackFn,
+    active: bool,
+}
+
+/// The callbacks registered for a target, every active one is invoked in the order of registration.
 pub(crate) struct CallbackAction {
-    #[allow(dead_code)]
     target: String,
-    callback: Box<dyn Fn(InvocationContext) + 'static>,
+    callbacks: Vec<CallbackEntry>,
     client: SignalRClient,
 }
 
 impl CallbackAction {
-    pub(crate) fn create(target: String, callback: impl Fn(InvocationContext) + 'static, client: SignalRClient) -> CallbackAction {
+    pub(crate) fn create(target: String, id: usize, callback: CallbackFn, client: SignalRClient) -> CallbackAction {
         CallbackAction {
             target: target,
-            callback: Box::new(callback),
+            callbacks: vec![CallbackEntry { id: id, callback: callback, active: true }],
             client: client
         }
     }
+
+    fn find(&mut self, id: usize) -> Option<&mut CallbackEntry> {
+        self.callbacks.iter_mut().find(|entry| entry.id == id)
+    }
 }
 
 impl UpdatableAction for CallbackAction {
     fn update_with(&mut self, message: &str, message_type: MessageType) {
         match message_type {
-            MessageType::Invocation => {
-                let invocation: Invocation = MessageParser::parse_message(message).unwrap();
-                let context = InvocationContext::create(self.client.clone(), invocation);
+            MessageType::Invocation | MessageType::StreamInvocation => {
+                match MessageParser::parse_message::<Invocation>(message) {
+                    Ok(invocation) => {
+                        for entry in self.callbacks.iter_mut().filter(|entry| entry.active) {
+                            let context = InvocationContext::create(self.client.clone(), invocation.clone());
 
-                (self.callback)(context);
+                            (entry.callback)(context);
+                        }
+                    },
+                    Err(e) => error!("Callback {} cannot be invoked with message {}: {}", self.target, message, e),
+                }
             },
-            _ => panic!("Callbacks accept only invocation data"),
+            _ => error!("Callback {} accepts only invocation and stream invocation data, the message {} is ignored", self.target, message),
         }
     }
 
@@ -36,8 +59,39 @@ impl UpdatableAction for CallbackAction {
         false
     }
 
+    fn is_callback(&self) -> bool {
+        true
+    }
+
+    fn add_callback(&mut self, id: usize, callback: CallbackFn) -> Result<(), CallbackFn> {
+        self.callbacks.push(CallbackEntry { id: id, callback: callback, active: true });
+
+        Ok(())
+    }
+
+    fn remove_callback(&mut self, id: usize) -> bool {
+        self.callbacks.retain(|entry| entry.id != id);
+
+        self.callbacks.is_empty()
+    }
+
+    fn set_callback_active(&mut self, id: usize, active: bool) -> bool {
+        match self.find(id) {
+            Some(entry) if entry.active != active => {
+                entry.active = active;
+
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn is_callback_active(&self, id: usize) -> bool {
+        self.callbacks.iter().any(|entry| entry.id == id && entry.active)
+    }
+
     fn dispose(self) {
-        drop(self.callback);
+        drop(self.callbacks);
         drop(self.client);
         drop(self.target);
     }
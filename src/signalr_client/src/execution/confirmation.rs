@@ -0,0 +1,75 @@
+use crate::{completer::{ManualFuture, ManualFutureCompleter}, protocol::{invoke::Completion, negotiate::MessageType}};
+use log::error;
+
+use crate::protocol::messages::MessageParser;
+
+use super::actions::UpdatableAction;
+
+pub(crate) struct ConfirmationAction {
+    invocation_id: String,
+    completer: Option<ManualFutureCompleter<Result<(), String>>>
+}
+
+impl ConfirmationAction {
+    pub fn new(invocation_id: String) -> (Self, ManualFuture<Result<(), String>>) {
+        let (f, c) = ManualFuture::new();
+        let confirmation = ConfirmationAction {
+            invocation_id: invocation_id,
+            completer: Some(c)
+        };
+
+        (confirmation, f)
+    }
+
+    fn complete(&mut self, result: Result<(), String>) {
+        if let Some(completer) = self.completer.take() {
+            completer.complete(result);
+        }
+    }
+
+    fn dispose_internal(&mut self) {
+        let c = self.completer.take();
+
+        if c.is_some() {
+            c.unwrap().cancel();
+        }
+    }
+}
+
+impl Drop for ConfirmationAction {
+    fn drop(&mut self) {
+        self.dispose_internal();
+    }
+}
+
+impl UpdatableAction for ConfirmationAction {
+    fn update_with(&mut self, message: &str, message_type: MessageType) {
+        match message_type {
+            MessageType::Completion => {
+                // the result is ignored, void methods do not send any
+                if let Ok(completition) = MessageParser::parse_message::<Completion<serde_json::Value>>(message) {
+                    if completition.is_error() {
+                        self.complete(Err(completition.unwrap_error_with_headers()));
+                    } else {
+                        self.complete(Ok(()));
+                    }
+                } else {
+                    error!("Cannot parse completition: {}", message);
+                }
+            },
+            _ => error!("Cannot confirm invocation {} with message {:?}, the message is ignored", self.invocation_id, message),
+        }
+    }
+
+    fn is_completed(&self) -> bool {
+        self.completer.is_none()
+    }
+
+    fn cancel(&mut self, reason: &str) {
+        self.complete(Err(reason.to_string()));
+    }
+
+    fn dispose(mut self) {
+        self.dispose_internal();
+    }
+}
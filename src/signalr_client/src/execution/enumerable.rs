@@ -1,24 +1,26 @@
 use log::error;
 use serde::de::DeserializeOwned;
 
-use crate::{completer::{ManualStream, ManualStreamCompleter}, protocol::{messages::MessageParser, invoke::Completion, negotiate::MessageType, streaming::StreamItem}};
+use crate::{completer::{ManualStream, ManualStreamCompleter}, protocol::{messages::{MessageParser, ParserConfig}, invoke::Completion, negotiate::MessageType, streaming::StreamItem}};
 
-use super::actions::UpdatableAction;
+use super::{actions::UpdatableAction, MaybeSend};
 
 pub(crate) struct EnumerableAction<R: DeserializeOwned + Unpin> {
     invocation_id: String,
-    completer: ManualStreamCompleter<R>,
+    completer: ManualStreamCompleter<Result<R, String>>,
     completed: bool,
+    parser: ParserConfig,
 }
 
 impl<R: DeserializeOwned + Unpin> EnumerableAction<R> {
-    pub fn new(invocation_id: String) -> (Self, ManualStream<R>) {
+    pub fn new(invocation_id: String, parser: ParserConfig) -> (Self, ManualStream<Result<R, String>>) {
         let (s, c) = ManualStream::create();
 
         (EnumerableAction {
             invocation_id: invocation_id,
             completer: c,
-            completed: false
+            completed: false,
+            parser: parser,
         }, s)
     }
 
@@ -34,29 +36,31 @@ impl<R: DeserializeOwned + Unpin> Drop for EnumerableAction<R> {
     }
 }
 
-impl<R: DeserializeOwned + Unpin> UpdatableAction for EnumerableAction<R> {
+impl<R: DeserializeOwned + Unpin + MaybeSend> UpdatableAction for EnumerableAction<R> {
     fn update_with(&mut self, message: &str, message_type: MessageType) {
         match message_type {
-            MessageType::Invocation => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
             MessageType::StreamItem => {
-                if let Ok(item) = MessageParser::parse_message::<StreamItem<R>>(message) {
-                    self.completer.push(item.item);
+                if let Ok(item) = MessageParser::parse_message_with::<StreamItem<R>>(message, &self.parser) {
+                    self.completer.push(Ok(item.item));
                 } else {
                     error!("Cannot update stream {} with unparseable item {}", self.invocation_id, message);
                 }
             },
             MessageType::Completion => {
-                if let Ok(_) = MessageParser::parse_message::<Completion<R>>(message) {
+                if let Ok(completition) = MessageParser::parse_message::<Completion<serde_json::Value>>(message) {
+                    if completition.is_error() {
+                        let error = completition.unwrap_error_with_headers();
+
+                        error!("Stream {} is faulted: {}", self.invocation_id, error);
+                        self.completer.push(Err(error));
+                    }
+
                     self.completer.close();
                 } else {
                     error!("Cannot parse completition: {}", message);
                 }
             },
-            MessageType::StreamInvocation => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
-            MessageType::CancelInvocation => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
-            MessageType::Ping => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
-            MessageType::Close => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
-            MessageType::Other => panic!("Cannot update stream {} with message {:?}", self.invocation_id, message),
+            _ => error!("Cannot update stream {} with message {:?}, the message is ignored", self.invocation_id, message),
         }
     }
 
@@ -64,6 +68,13 @@ impl<R: DeserializeOwned + Unpin> UpdatableAction for EnumerableAction<R> {
         self.completed
     }
 
+    fn cancel(&mut self, reason: &str) {
+        if !self.completed {
+            self.completer.push(Err(reason.to_string()));
+            self.dispose_internal();
+        }
+    }
+
     fn dispose(mut self) {
         self.dispose_internal();
     }
@@ -1,22 +1,26 @@
+use std::any::type_name;
+
 use crate::{completer::{ManualFuture, ManualFutureCompleter}, protocol::{invoke::Completion, negotiate::MessageType}};
 use log::{error, info};
 use serde::de::DeserializeOwned;
 
-use crate::protocol::messages::MessageParser;
+use crate::protocol::messages::{MessageParser, ParserConfig};
 
-use super::actions::UpdatableAction;
+use super::{actions::UpdatableAction, MaybeSend};
 
 pub(crate) struct InvocationAction<R: DeserializeOwned + Unpin> {
     invocation_id: String,
-    completer: Option<ManualFutureCompleter<R>>
+    completer: Option<ManualFutureCompleter<Result<R, String>>>,
+    parser: ParserConfig,
 }
 
 impl<R: DeserializeOwned + Unpin> InvocationAction<R> {
-    pub fn new(invocation_id: String) -> (Self, ManualFuture<R>) {
+    pub fn new(invocation_id: String, parser: ParserConfig) -> (Self, ManualFuture<Result<R, String>>) {
         let (f, c) = ManualFuture::new();
         let invocation = InvocationAction {
             invocation_id: invocation_id,
-            completer: Some(c)
+            completer: Some(c),
+            parser: parser,
         };
 
         (invocation, f)
@@ -27,12 +31,11 @@ impl<R: DeserializeOwned + Unpin> InvocationAction<R> {
         self.completer.is_some()
     }
 
-    pub fn complete(&mut self, result: R) {
-        info!("Trying to get future completer form Invocation Action");
-        let completer = self.completer.take().unwrap();
-        info!("Future completer is taken");
-        completer.complete(result);
-        info!("Future completer is completed");
+    pub fn complete(&mut self, result: Result<R, String>) {
+        if let Some(completer) = self.completer.take() {
+            completer.complete(result);
+            info!("Future completer is completed");
+        }
     }
 
     fn dispose_internal(&mut self) {
@@ -50,30 +53,40 @@ impl<R: DeserializeOwned + Unpin> Drop for InvocationAction<R> {
     }
 }
 
-impl<R: DeserializeOwned + Unpin> UpdatableAction for InvocationAction<R> {
+impl<R: DeserializeOwned + Unpin + MaybeSend> UpdatableAction for InvocationAction<R> {
     fn update_with(&mut self, message: &str, message_type: MessageType) {
         // debug!("Updating invocation {}", self.invocation_id);
 
         match message_type {
-            MessageType::Invocation => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
-            MessageType::StreamItem => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
             MessageType::Completion => {
-                if let Ok(completition) = MessageParser::parse_message::<Completion<R>>(message) {
-                    if completition.is_result() {
-                        info!("Completition is parsed");
-                        self.complete(completition.unwrap_result());
-                    } else {
-                        error!("Cannot complete invocation {}, error: {}", self.invocation_id, completition.unwrap_error());
-                    }
-                } else {
-                    error!("Cannot parse completition: {}", message);
+                match MessageParser::parse_message_with::<Completion<R>>(message, &self.parser) {
+                    Ok(completition) => {
+                        if completition.is_result() {
+                            info!("Completition is parsed");
+                            self.complete(Ok(completition.unwrap_result()));
+                        } else if completition.is_error() {
+                            let error = completition.unwrap_error_with_headers();
+
+                            error!("Cannot complete invocation {}, error: {}", self.invocation_id, error);
+                            self.complete(Err(error));
+                        } else {
+                            // a void hub method, only result types accepting null can be completed
+                            let result = MessageParser::parse_value_with::<R>(&serde_json::Value::Null, &self.parser)
+                                .map_err(|_| format!("Invocation {} expected a result of type {}, but the hub returned no result", self.invocation_id, type_name::<R>()));
+
+                            self.complete(result);
+                        }
+                    },
+                    Err(e) => {
+                        let error = format!("The result of invocation {} cannot be deserialized to {}: {}", self.invocation_id, type_name::<R>(), e);
+
+                        error!("{}", error);
+                        self.complete(Err(error));
+                    },
                 }
             },
-            MessageType::StreamInvocation => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
-            MessageType::CancelInvocation => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
-            MessageType::Ping => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
-            MessageType::Close => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
-            MessageType::Other => panic!("Cannot complete invocation {}, with message {:?}", self.invocation_id, message),
+            // a misbehaving hub must not stop the receive loop, the invocation keeps waiting for its completion
+            _ => error!("Cannot complete invocation {} with message {:?}, the message is ignored", self.invocation_id, message),
         }
         
     }
@@ -82,6 +95,10 @@ impl<R: DeserializeOwned + Unpin> UpdatableAction for InvocationAction<R> {
         self.completer.is_none()
     }
 
+    fn cancel(&mut self, reason: &str) {
+        self.complete(Err(reason.to_string()));
+    }
+
     fn dispose(mut self) {
         self.dispose_internal();
     }
@@ -0,0 +1,35 @@
+/// Requires `Send` on Tokio, where the callbacks and the results of the hub cross the worker threads of the runtime.
+///
+/// The WASM client runs on a single thread, so every type satisfies it there.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// Requires `Send` on Tokio, where the callbacks and the results of the hub cross the worker threads of the runtime.
+///
+/// The WASM client runs on a single thread, so every type satisfies it there.
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Requires `Sync` on Tokio, where values borrowed across an await may be shared between the worker threads of the runtime.
+///
+/// The WASM client runs on a single thread, so every type satisfies it there.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSync: Sync {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// Requires `Sync` on Tokio, where values borrowed across an await may be shared between the worker threads of the runtime.
+///
+/// The WASM client runs on a single thread, so every type satisfies it there.
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSync {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSync for T {}
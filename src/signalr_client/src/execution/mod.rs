@@ -1,15 +1,18 @@
 mod actions;
+mod maybe_send;
 mod invocation;
+mod confirmation;
 mod enumerable;
 mod arguments;
 mod callback;
 mod storage;
 
 pub use arguments::ArgumentConfiguration;
+pub use maybe_send::{MaybeSend, MaybeSync};
 pub use storage::CallbackHandler;
 
 pub(crate) use actions::UpdatableAction;
-pub(crate) use storage::{Storage, StorageUnregistrationHandler};
+pub(crate) use storage::{BinaryFrameHandler, Storage, StorageUnregistrationHandler, UnknownMessageHandler};
 
 #[cfg(target_arch = "wasm32")]
 pub(crate) use storage::ManualFutureState;
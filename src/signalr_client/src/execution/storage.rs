@@ -1,7 +1,7 @@
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::de::DeserializeOwned;
-use crate::{completer::{CompletedFuture, ManualFuture, ManualFutureCompleter, ManualStream}, {client::SignalRClient, protocol::{invoke::{Invocation, PossibleInvocation}, messages::MessageParser, negotiate::{self, MessageType}}, InvocationContext}};
-use super::{callback::CallbackAction, enumerable::EnumerableAction, invocation::InvocationAction, UpdatableAction};
+use crate::{completer::{CompletedFuture, ManualFuture, ManualFutureCompleter, ManualStream}, {client::SignalRClient, protocol::{invoke::{Invocation, PossibleInvocation}, messages::{MessageParser, ParserConfig}, negotiate::{self, MessageType}, serializer::SerializerOptions}, InvocationContext}};
+use super::{callback::{CallbackAction, CallbackFn}, confirmation::ConfirmationAction, enumerable::EnumerableAction, invocation::InvocationAction, MaybeSend, UpdatableAction};
 
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -40,28 +40,96 @@ impl ManualFutureState {
     }
 }
 
+/// Receives the raw text of the messages whose type is not known by the client.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type UnknownMessageHandler = Box<dyn FnMut(&str) + Send + 'static>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type UnknownMessageHandler = Box<dyn FnMut(&str) + 'static>;
+
+/// Receives the payload of the binary frames, which are not messages of the JSON protocol.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type BinaryFrameHandler = Box<dyn FnMut(&[u8]) + Send + 'static>;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) type BinaryFrameHandler = Box<dyn FnMut(&[u8]) + 'static>;
+
 pub trait Storage : Clone {
     fn insert(&mut self, key: String, action: impl UpdatableAction + 'static);
-    #[allow(dead_code)]
     fn contains(&self, key: String) -> bool;
     fn update(&mut self, key: String, f: impl FnMut(&mut Box<dyn UpdatableAction>));
     fn remove(&mut self, key: String);
+    #[allow(dead_code)]
+    fn suspend(&mut self, key: String) -> bool;
+    #[allow(dead_code)]
+    fn resume(&mut self, key: String) -> bool;
     fn dispose(&mut self);
+    /// Removes every pending invocation and stream, resolving them with the given error. Callbacks stay registered.
+    fn cancel_pending(&mut self, reason: String);
+    fn pending_count(&self) -> usize;
+    /// The keys of the pending invocations and streams, callbacks are not listed
+    fn pending_keys(&self) -> Vec<String>;
+    /// Counts a stream item which could not be routed to any invocation
+    fn add_dropped(&mut self);
+    fn dropped_count(&self) -> usize;
     fn increment(&mut self) -> usize;
+    fn get_key_prefix(&self) -> Option<String>;
+    fn get_parser_config(&self) -> ParserConfig;
+    fn get_serializer_options(&self) -> SerializerOptions;
+    fn set_unknown_handler(&mut self, handler: UnknownMessageHandler);
+    /// Passes the message to the unknown message handler, returns `false` if there is no handler
+    fn notify_unknown(&mut self, message: &str) -> bool;
+    fn set_binary_handler(&mut self, handler: BinaryFrameHandler);
+    /// Passes the binary frame to the binary frame handler, returns `false` if there is no handler
+    fn notify_binary(&mut self, frame: &[u8]) -> bool;
 
+    /// Creates an invocation id not used by any action, e.g. a callback registered for a target like `Method_1`
     fn create_key(&mut self, target: String) -> String {
-        let index = self.increment();
+        loop {
+            #[cfg(feature = "uuid")]
+            let id = uuid::Uuid::new_v4().simple().to_string();
+            #[cfg(not(feature = "uuid"))]
+            let id = self.increment();
+
+            let key = match self.get_key_prefix() {
+                Some(prefix) => format!("{}_{}_{}", prefix, target, id),
+                None => format!("{}_{}", target, id),
+            };
+
+            if !self.contains(key.clone()) {
+                return key;
+            }
 
-        format!("{}_{}", target, index)
+            warn!("Invocation id {} is already registered, creating another one", key);
+        }
     }
 
-    fn add_callback(&mut self, target: String, callback: impl Fn(InvocationContext) + 'static, client: SignalRClient) {
+    /// Adds a callback for the target next to the ones registered already, returns the id of the callback within the target
+    ///
+    /// The storage is shared by its clones, so a callback can be added through a shared reference, e.g. of a client kept in an `Arc`.
+    fn add_callback(&self, target: String, callback: impl FnMut(InvocationContext) + MaybeSend + 'static, client: SignalRClient) -> usize {
         debug!("Adding a callback for key {}", target);
-        self.insert(target.clone(), CallbackAction::create(target.clone(), callback, client));
+        let mut storage = self.clone();
+        let id = storage.increment();
+        let mut callback: Option<CallbackFn> = Some(Box::new(callback));
+
+        if storage.contains(target.clone()) {
+            storage.update(target.clone(), |action| {
+                if let Some(c) = callback.take() {
+                    callback = action.add_callback(id, c).err();
+                }
+            });
+        }
+
+        if let Some(callback) = callback {
+            storage.insert(target.clone(), CallbackAction::create(target.clone(), id, callback, client));
+        }
+
+        id
     }
 
-    fn add_invocation<R: 'static + DeserializeOwned + Unpin>(&mut self, invocation_id: String) -> ManualFuture<R> {
-        let (invocation, f) = InvocationAction::<R>::new(invocation_id.clone());
+    fn add_invocation<R: 'static + DeserializeOwned + Unpin + MaybeSend>(&mut self, invocation_id: String) -> ManualFuture<Result<R, String>> {
+        let (invocation, f) = InvocationAction::<R>::new(invocation_id.clone(), self.get_parser_config());
 
         debug!("Inserting invocation for key {}", invocation_id);
         self.insert(invocation_id, invocation);
@@ -69,8 +137,17 @@ pub trait Storage : Clone {
         f
     }
 
-    fn add_stream<R: 'static + DeserializeOwned + Unpin>(&mut self, invocation_id: String) -> ManualStream<R> {
-        let (stream, f) = EnumerableAction::<R>::new(invocation_id.clone());
+    fn add_confirmation(&mut self, invocation_id: String) -> ManualFuture<Result<(), String>> {
+        let (confirmation, f) = ConfirmationAction::new(invocation_id.clone());
+
+        debug!("Inserting confirmation for key {}", invocation_id);
+        self.insert(invocation_id, confirmation);
+
+        f
+    }
+
+    fn add_stream<R: 'static + DeserializeOwned + Unpin + MaybeSend>(&mut self, invocation_id: String) -> ManualStream<Result<R, String>> {
+        let (stream, f) = EnumerableAction::<R>::new(invocation_id.clone(), self.get_parser_config());
 
         self.insert(invocation_id, stream);
 
@@ -83,37 +160,59 @@ pub trait Storage : Clone {
         match message_type {
             negotiate::MessageType::Invocation => {
                 debug!("Server invocation {:?} -> {}", message_type, message);
-                let invocation = MessageParser::parse_message::<Invocation>(&message).unwrap();
+                let invocation = MessageParser::parse_message::<Invocation>(&message)
+                    .map_err(|e| format!("Invalid invocation {}: {}", message, e))?;
 
                 self.update(invocation.get_target(), |i| {
                     i.update_with(&message, message_type);
                 });    
             },
             negotiate::MessageType::StreamItem => {
-                let invocation = MessageParser::parse_message::<PossibleInvocation>(&message).unwrap();
+                let invocation = MessageParser::parse_message::<PossibleInvocation>(&message)
+                    .map_err(|e| format!("Invalid stream item {}: {}", message, e))?;
 
                 if invocation.invocation_id.is_some() {
+                    // callbacks share the key space with invocation ids, they must never receive results
                     self.update(invocation.invocation_id.unwrap(), |i| {
-                        i.update_with(&message, message_type);
+                        if !i.is_callback() {
+                            i.update_with(&message, message_type);
+                        }
                     });    
+                } else {
+                    warn!("Stream item without invocation id is dropped: {}", message);
+                    self.add_dropped();
                 }
             },
             negotiate::MessageType::Completion => {
-                let invocation = MessageParser::parse_message::<PossibleInvocation>(&message).unwrap();                
+                let invocation = MessageParser::parse_message::<PossibleInvocation>(&message)
+                    .map_err(|e| format!("Invalid completion {}: {}", message, e))?;
 
                 info!("Completition received {}", message);
 
                 if invocation.invocation_id.is_some() {
                     let key = invocation.invocation_id.unwrap();
+                    let mut routed = false;
+
                     self.update(key.clone(), |i| {
-                        i.update_with(&message, message_type);
+                        if !i.is_callback() {
+                            i.update_with(&message, message_type);
+                            routed = true;
+                        }
                     });
 
-                    self.remove(key.clone());
+                    if routed {
+                        self.remove(key.clone());
+                    }
                 }
             },
             negotiate::MessageType::StreamInvocation => {
-                debug!("Stream invocation is arrived");                                        
+                debug!("Server stream invocation {:?} -> {}", message_type, message);
+                let invocation = MessageParser::parse_message::<Invocation>(&message)
+                    .map_err(|e| format!("Invalid invocation {}: {}", message, e))?;
+
+                self.update(invocation.get_target(), |i| {
+                    i.update_with(&message, message_type);
+                });
             },
             negotiate::MessageType::CancelInvocation => {
                 debug!("Cancel invocation is arrived");                                        
@@ -128,7 +227,9 @@ pub trait Storage : Clone {
                 debug!("Close is arrived");
             },
             negotiate::MessageType::Other => {
-                debug!("Other is arrived");
+                if !self.notify_unknown(&message) {
+                    debug!("Other is arrived");
+                }
             },
         }
 
@@ -137,7 +238,14 @@ pub trait Storage : Clone {
 }
 
 pub trait CallbackHandler {
+    /// Removes the callback permanently.
     fn unregister(self);
+    /// Removes the callback temporarily, it can be registered again with `re_register`.
+    fn suspend(&mut self);
+    /// Registers the suspended callback again without providing the closure again.
+    fn re_register(&mut self);
+    /// Returns if the callback is currently registered and receives the invocations of the hub.
+    fn is_registered(&self) -> bool;
 }
 
 pub(crate) struct StorageUnregistrationHandler<T> 
@@ -145,19 +253,61 @@ pub(crate) struct StorageUnregistrationHandler<T>
 {
     _storage: T,
     _key: String,
+    _id: usize,
 }
 
 impl<T: Storage> StorageUnregistrationHandler<T> {
-    pub(crate) fn new(storage: T, key: String) -> Self {
+    pub(crate) fn new(storage: T, key: String, id: usize) -> Self {
         StorageUnregistrationHandler {
             _key: key,
+            _id: id,
             _storage: storage
         }
     }
+
+    fn with_callback<R: Default>(&self, mut f: impl FnMut(&mut Box<dyn UpdatableAction>) -> R) -> R {
+        let mut storage = self._storage.clone();
+        let mut result = R::default();
+
+        if storage.contains(self._key.clone()) {
+            storage.update(self._key.clone(), |action| {
+                result = f(action);
+            });
+        }
+
+        result
+    }
 }
 
 impl<T: Storage> CallbackHandler for StorageUnregistrationHandler<T> {
     fn unregister(mut self) {
-        self._storage.remove(self._key);
+        let id = self._id;
+
+        // the other callbacks of the target stay registered
+        if self.with_callback(|action| action.remove_callback(id)) {
+            self._storage.remove(self._key.clone());
+        }
+    }
+
+    fn suspend(&mut self) {
+        let id = self._id;
+
+        if !self.with_callback(|action| action.set_callback_active(id, false)) {
+            warn!("Callback {} is not registered, cannot suspend", self._key);
+        }
+    }
+
+    fn re_register(&mut self) {
+        let id = self._id;
+
+        if !self.with_callback(|action| action.set_callback_active(id, true)) {
+            warn!("Callback {} is not suspended, cannot register again", self._key);
+        }
+    }
+
+    fn is_registered(&self) -> bool {
+        let id = self._id;
+
+        self.with_callback(|action| action.is_callback_active(id))
     }
 }
\ No newline at end of file
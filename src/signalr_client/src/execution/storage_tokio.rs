@@ -1,19 +1,41 @@
-use std::{collections::HashMap, sync::{Arc, Mutex}};
+use std::{collections::HashMap, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}};
 use log::{error, info};
-use super::{Storage, UpdatableAction};
+use crate::protocol::{messages::ParserConfig, serializer::SerializerOptions};
+use super::{BinaryFrameHandler, Storage, UnknownMessageHandler, UpdatableAction};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Clone)]
 pub struct UpdatableActionStorage {
     _data: Arc<Mutex<HashMap<String, Mutex<Box<dyn UpdatableAction>>>>>,
+    _suspended: Arc<Mutex<HashMap<String, Mutex<Box<dyn UpdatableAction>>>>>,
+    _prefix: Option<String>,
+    _parser: ParserConfig,
+    _serializer: SerializerOptions,
+    _pending: Arc<AtomicUsize>,
+    _dropped: Arc<AtomicUsize>,
     _index: Arc<Mutex<usize>>,
+    _unknown: Arc<Mutex<Option<UnknownMessageHandler>>>,
+    _binary: Arc<Mutex<Option<BinaryFrameHandler>>>,
 }
 
 impl UpdatableActionStorage {
+    #[allow(dead_code)]
     pub fn new() -> Self {
+        UpdatableActionStorage::with_options(None, ParserConfig::default(), SerializerOptions::default())
+    }
+
+    pub fn with_options(prefix: Option<String>, parser: ParserConfig, serializer: SerializerOptions) -> Self {
         UpdatableActionStorage {
+            _prefix: prefix,
+            _parser: parser,
+            _serializer: serializer,
+            _pending: Arc::new(AtomicUsize::new(0)),
+            _dropped: Arc::new(AtomicUsize::new(0)),
             _data: Arc::new(Mutex::new(HashMap::new())),
+            _suspended: Arc::new(Mutex::new(HashMap::new())),
             _index: Arc::new(Mutex::new(0)),
+            _unknown: Arc::new(Mutex::new(None)),
+            _binary: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -24,15 +46,20 @@ impl Drop for UpdatableActionStorage {
     }
 }
 
-unsafe impl Send for UpdatableActionStorage {}
-
 impl Storage for UpdatableActionStorage {
     fn insert(&mut self, key: String, action: impl UpdatableAction + 'static) {
         if let Ok(mut data) = self._data.lock() {
             if data.contains_key(&key) == false {
+                if !action.is_callback() {
+                    self._pending.fetch_add(1, Ordering::Relaxed);
+                }
+
                 data.insert(key, Mutex::new(Box::new(action)));
             } else {
                 error!("Key {} is already registered as an action", key);
+                // the awaiting caller gets an error instead of a future which never completes
+                let mut action = action;
+                action.cancel(&format!("The key {} is already registered", key));
             }
         } else {
             error!("Cannot lock storage");
@@ -77,24 +104,203 @@ impl Storage for UpdatableActionStorage {
                 let r = ret.into_inner();
     
                 if r.is_ok() {
-                    drop(r.unwrap());
+                    let action = r.unwrap();
+
+                    if !action.is_callback() {
+                        self._pending.fetch_sub(1, Ordering::Relaxed);
+                    }
+
+                    drop(action);
                 }
             }    
         } else {
             error!("Cannot lock storage");
         }
+
+        if let Ok(mut suspended) = self._suspended.lock() {
+            suspended.remove(&key);
+        } else {
+            error!("Cannot lock suspended storage");
+        }
+    }
+
+    fn suspend(&mut self, key: String) -> bool {
+        if let (Ok(mut data), Ok(mut suspended)) = (self._data.lock(), self._suspended.lock()) {
+            if let Some(action) = data.remove(&key) {
+                suspended.insert(key, action);
+
+                return true;
+            }
+        } else {
+            error!("Cannot lock storage");
+        }
+
+        false
+    }
+
+    fn resume(&mut self, key: String) -> bool {
+        if let (Ok(mut data), Ok(mut suspended)) = (self._data.lock(), self._suspended.lock()) {
+            if data.contains_key(&key) {
+                error!("Key {} is already registered as an action", key);
+            } else if let Some(action) = suspended.remove(&key) {
+                data.insert(key, action);
+
+                return true;
+            }
+        } else {
+            error!("Cannot lock storage");
+        }
+
+        false
     }
 
     fn dispose(&mut self) {
+        // every clone of a client owns a clone of the storage, including the clones held by the registered callbacks,
+        // so the actions are only cleared when the last client is gone
         let count = Arc::strong_count(&self._data);
 
         if count == 1 {
             info!("Clearing storage...");
             if let Ok(mut data) = self._data.lock() {
                 data.clear();
+                self._pending.store(0, Ordering::Relaxed);
             } else {
                 error!("Cannot lock storage");
             }
+
+            if let Ok(mut suspended) = self._suspended.lock() {
+                suspended.clear();
+            } else {
+                error!("Cannot lock suspended storage");
+            }
+
+            if let Ok(mut unknown) = self._unknown.lock() {
+                unknown.take();
+            }
+        }
+    }
+
+    fn cancel_pending(&mut self, reason: String) {
+        let mut cancelled = Vec::new();
+
+        if let Ok(mut data) = self._data.lock() {
+            let keys: Vec<String> = data
+                .iter()
+                .filter(|(_, action)| action.lock().map(|a| !a.is_callback()).unwrap_or(false))
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in keys {
+                if let Some(action) = data.remove(&key) {
+                    self._pending.fetch_sub(1, Ordering::Relaxed);
+                    cancelled.push(action);
+                }
+            }
+        } else {
+            error!("Cannot lock storage");
+        }
+
+        if !cancelled.is_empty() {
+            info!("Cancelling {} pending invocations: {}", cancelled.len(), reason);
+        }
+
+        // completers are fired outside of the storage lock
+        for action in cancelled {
+            if let Ok(mut action) = action.into_inner() {
+                action.cancel(&reason);
+            }
+        }
+    }
+
+    fn pending_count(&self) -> usize {
+        self._pending.load(Ordering::Relaxed)
+    }
+
+    fn pending_keys(&self) -> Vec<String> {
+        if let Ok(data) = self._data.lock() {
+            data
+                .iter()
+                .filter(|(_, action)| action.lock().map(|a| !a.is_callback()).unwrap_or(false))
+                .map(|(key, _)| key.clone())
+                .collect()
+        } else {
+            error!("Cannot lock storage");
+
+            Vec::new()
+        }
+    }
+
+    fn add_dropped(&mut self) {
+        self._dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn dropped_count(&self) -> usize {
+        self._dropped.load(Ordering::Relaxed)
+    }
+
+    fn get_key_prefix(&self) -> Option<String> {
+        self._prefix.clone()
+    }
+
+    fn get_parser_config(&self) -> ParserConfig {
+        self._parser
+    }
+
+    fn get_serializer_options(&self) -> SerializerOptions {
+        self._serializer
+    }
+
+    fn set_unknown_handler(&mut self, handler: UnknownMessageHandler) {
+        if let Ok(mut unknown) = self._unknown.lock() {
+            *unknown = Some(handler);
+        } else {
+            error!("Cannot lock unknown message handler");
+        }
+    }
+
+    fn notify_unknown(&mut self, message: &str) -> bool {
+        // the handler is called outside of the lock, so it may replace itself
+        let handler = self._unknown.lock().ok().and_then(|mut unknown| unknown.take());
+
+        if let Some(mut handler) = handler {
+            handler(message);
+
+            if let Ok(mut unknown) = self._unknown.lock() {
+                if unknown.is_none() {
+                    *unknown = Some(handler);
+                }
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_binary_handler(&mut self, handler: BinaryFrameHandler) {
+        if let Ok(mut binary) = self._binary.lock() {
+            *binary = Some(handler);
+        } else {
+            error!("Cannot lock binary frame handler");
+        }
+    }
+
+    fn notify_binary(&mut self, frame: &[u8]) -> bool {
+        // the handler is called outside of the lock, so it may replace itself
+        let handler = self._binary.lock().ok().and_then(|mut binary| binary.take());
+
+        if let Some(mut handler) = handler {
+            handler(frame);
+
+            if let Ok(mut binary) = self._binary.lock() {
+                if binary.is_none() {
+                    *binary = Some(handler);
+                }
+            }
+
+            true
+        } else {
+            false
         }
     }
 
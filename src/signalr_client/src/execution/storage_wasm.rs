@@ -1,20 +1,42 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use log::{debug, error, info, warn};
 
-use super::{storage::Storage, UpdatableAction};
+use crate::protocol::{messages::ParserConfig, serializer::SerializerOptions};
+use super::{storage::Storage, BinaryFrameHandler, UnknownMessageHandler, UpdatableAction};
 
 #[cfg(target_arch = "wasm32")]
 #[derive(Clone)]
 pub struct UpdatableActionStorage {
     _data: Rc<RefCell<HashMap<String, Box<dyn UpdatableAction>>>>,
+    _suspended: Rc<RefCell<HashMap<String, Box<dyn UpdatableAction>>>>,
+    _prefix: Option<String>,
+    _parser: ParserConfig,
+    _serializer: SerializerOptions,
+    _pending: Rc<RefCell<usize>>,
+    _dropped: Rc<RefCell<usize>>,
     _index: Rc<RefCell<usize>>,
+    _unknown: Rc<RefCell<Option<UnknownMessageHandler>>>,
+    _binary: Rc<RefCell<Option<BinaryFrameHandler>>>,
 }
 
 impl UpdatableActionStorage {
+    #[allow(dead_code)]
     pub fn new() -> Self {
+        UpdatableActionStorage::with_options(None, ParserConfig::default(), SerializerOptions::default())
+    }
+
+    pub fn with_options(prefix: Option<String>, parser: ParserConfig, serializer: SerializerOptions) -> Self {
         UpdatableActionStorage {
+            _prefix: prefix,
+            _parser: parser,
+            _serializer: serializer,
+            _pending: Rc::new(RefCell::new(0)),
+            _dropped: Rc::new(RefCell::new(0)),
             _data: Rc::new(RefCell::new(HashMap::new())),
+            _suspended: Rc::new(RefCell::new(HashMap::new())),
             _index: Rc::new(RefCell::new(0)),
+            _unknown: Rc::new(RefCell::new(None)),
+            _binary: Rc::new(RefCell::new(None)),
         }
     }
 }
@@ -31,10 +53,17 @@ impl Storage for UpdatableActionStorage {
         let mut data = self._data.borrow_mut();
         
         if data.contains_key(&key) == false {
+            if !action.is_callback() {
+                *self._pending.borrow_mut() += 1;
+            }
+
             data.insert(key.clone(), Box::new(action));
             debug!("Inserting key {} into actions, count: {}", key, data.len());
         } else {
             warn!("The key already exists in storage: {}. Dropping...", &key);
+            // the awaiting caller gets an error instead of a future which never completes
+            let mut action = action;
+            action.cancel(&format!("The key {} is already registered", key));
         }
     }
 
@@ -64,6 +93,11 @@ impl Storage for UpdatableActionStorage {
 
                 info!("Dropping item at key {}", key);
                 let data = removed.take().unwrap();
+
+                if !data.is_callback() {
+                    *self._pending.borrow_mut() -= 1;
+                }
+
                 drop(data);
             } else {
                 warn!("Data at key {} is an empty action.", key);
@@ -71,9 +105,43 @@ impl Storage for UpdatableActionStorage {
         } else {
             warn!("Cannot remove key {} from actions, count: {}. The key does not exist.", key, data.len());
         }
+
+        self._suspended.borrow_mut().remove(&key);
     }
     
+    fn suspend(&mut self, key: String) -> bool {
+        let mut data = self._data.borrow_mut();
+
+        if let Some(action) = data.remove(&key) {
+            debug!("Suspending key {}, count: {}", key, data.len());
+            self._suspended.borrow_mut().insert(key, action);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn resume(&mut self, key: String) -> bool {
+        let mut data = self._data.borrow_mut();
+
+        if data.contains_key(&key) {
+            warn!("The key already exists in storage: {}. Cannot resume...", &key);
+
+            false
+        } else if let Some(action) = self._suspended.borrow_mut().remove(&key) {
+            data.insert(key.clone(), action);
+            debug!("Resuming key {}, count: {}", key, data.len());
+
+            true
+        } else {
+            false
+        }
+    }
+
     fn dispose(&mut self) {
+        // every clone of a client owns a clone of the storage, including the clones held by the registered callbacks,
+        // so the actions are only cleared when the last client is gone
         let count = Rc::strong_count(&self._data);
 
         if count == 1 {
@@ -81,6 +149,111 @@ impl Storage for UpdatableActionStorage {
             let mut data = self._data.borrow_mut();
 
             data.clear();
+            self._suspended.borrow_mut().clear();
+            *self._pending.borrow_mut() = 0;
+            self._unknown.borrow_mut().take();
+        }
+    }
+
+    fn cancel_pending(&mut self, reason: String) {
+        let cancelled: Vec<Box<dyn UpdatableAction>> = {
+            let mut data = self._data.borrow_mut();
+            let keys: Vec<String> = data
+                .iter()
+                .filter(|(_, action)| !action.is_callback())
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            keys.iter().filter_map(|key| data.remove(key)).collect()
+        };
+
+        if !cancelled.is_empty() {
+            info!("Cancelling {} pending invocations: {}", cancelled.len(), reason);
+            *self._pending.borrow_mut() -= cancelled.len();
+        }
+
+        // completers are fired outside of the storage borrow
+        for mut action in cancelled {
+            action.cancel(&reason);
+        }
+    }
+
+    fn pending_count(&self) -> usize {
+        *self._pending.borrow()
+    }
+
+    fn pending_keys(&self) -> Vec<String> {
+        self._data
+            .borrow()
+            .iter()
+            .filter(|(_, action)| !action.is_callback())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn add_dropped(&mut self) {
+        *self._dropped.borrow_mut() += 1;
+    }
+
+    fn dropped_count(&self) -> usize {
+        *self._dropped.borrow()
+    }
+
+    fn get_key_prefix(&self) -> Option<String> {
+        self._prefix.clone()
+    }
+
+    fn get_parser_config(&self) -> ParserConfig {
+        self._parser
+    }
+
+    fn get_serializer_options(&self) -> SerializerOptions {
+        self._serializer
+    }
+
+    fn set_unknown_handler(&mut self, handler: UnknownMessageHandler) {
+        *self._unknown.borrow_mut() = Some(handler);
+    }
+
+    fn notify_unknown(&mut self, message: &str) -> bool {
+        // the handler is called outside of the borrow, so it may replace itself
+        let handler = self._unknown.borrow_mut().take();
+
+        if let Some(mut handler) = handler {
+            handler(message);
+
+            let mut unknown = self._unknown.borrow_mut();
+
+            if unknown.is_none() {
+                *unknown = Some(handler);
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_binary_handler(&mut self, handler: BinaryFrameHandler) {
+        *self._binary.borrow_mut() = Some(handler);
+    }
+
+    fn notify_binary(&mut self, frame: &[u8]) -> bool {
+        // the handler is called outside of the borrow, so it may replace itself
+        let handler = self._binary.borrow_mut().take();
+
+        if let Some(mut handler) = handler {
+            handler(frame);
+
+            let mut binary = self._binary.borrow_mut();
+
+            if binary.is_none() {
+                *binary = Some(handler);
+            }
+
+            true
+        } else {
+            false
         }
     }
 
@@ -5,6 +5,19 @@ mod protocol;
 mod client;
 mod communication;
 
-pub use client::{InvocationContext, SignalRClient};
-pub use execution::{ArgumentConfiguration, CallbackHandler};
-pub use completer::{CompletedFuture, ManualFuture, ManualStream};
\ No newline at end of file
+#[doc(hidden)]
+pub mod macros;
+
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+
+pub use client::{ConnectionConfiguration, ConnectionInfo, CountedStream, HubConnectionManager, InvocationContext, InvocationStream, ItemStream, Metrics, SignalRClient, TimeoutStream};
+pub use execution::{ArgumentConfiguration, CallbackHandler, MaybeSend, MaybeSync};
+pub use completer::{CompletedFuture, ManualFuture, ManualStream};
+pub use protocol::{invoke::{CancelInvocation, Completion, Invocation}, serializer::{EnumRepresentation, SerializerOptions}, streaming::StreamItem};
+
+#[cfg(feature = "metrics")]
+pub use client::InvocationTiming;
+
+#[cfg(feature = "test-util")]
+pub use communication::MockCommunication;
\ No newline at end of file
@@ -0,0 +1,128 @@
+//! Declarative macros generating strongly-typed hub proxies.
+
+#[doc(hidden)]
+pub use futures::Stream;
+
+/// Generates a typed proxy over [`SignalRClient`](crate::SignalRClient) for the methods of a hub.
+///
+/// Every method is declared with the name of the hub method and the kind of the call, the proxy sends the parameters as arguments in order:
+///
+/// * `#[invoke("Target")]` - Calls `invoke_with_args`, the method returns `Result<T, String>` of its declared return type.
+/// * `#[send("Target")]` - Calls `send_with_args`, the method returns `Result<(), String>` and has no return type.
+/// * `#[enumerate("Target")]` - Calls `enumerate_with_args`, the method returns a `Stream` of its declared return type.
+///
+/// The proxy is created with `new` from a connected client and can be cloned like the client itself. Parameter types must implement `Serialize`,
+/// return types `DeserializeOwned`, `Unpin` and `MaybeSend`.
+///
+/// # Examples
+///
+/// ```
+/// signalr_hub! {
+///     /// The test hub of the server
+///     pub struct TestHub {
+///         #[invoke("SingleEntity")]
+///         pub async fn single_entity(&self) -> TestEntity;
+///
+///         #[invoke("PushTwoEntities")]
+///         pub async fn push_two(&self, a: TestEntity, b: TestEntity) -> TestEntity;
+///
+///         #[send("TriggerEntityCallback")]
+///         pub async fn trigger_callback(&self, target: String);
+///
+///         #[enumerate("HundredEntities")]
+///         pub async fn hundred_entities(&self) -> TestEntity;
+///     }
+/// }
+///
+/// let client = SignalRClient::connect("localhost", "test").await.unwrap();
+/// let hub = TestHub::new(client);
+///
+/// let merged = hub.push_two(first, second).await.unwrap();
+/// let mut entities = hub.hundred_entities().await;
+///
+/// while let Some(entity) = entities.next().await {
+///     info!("Entity {}, {}", entity.text, entity.number);
+/// }
+/// ```
+#[macro_export]
+macro_rules! signalr_hub {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($body:tt)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        $vis struct $name {
+            client: $crate::SignalRClient,
+        }
+
+        impl $name {
+            /// Creates the proxy on top of a connected client.
+            #[allow(dead_code)]
+            $vis fn new(client: $crate::SignalRClient) -> Self {
+                $name { client: client }
+            }
+
+            /// Returns the client of the proxy, e.g. to register callbacks.
+            #[allow(dead_code)]
+            $vis fn client(&self) -> &$crate::SignalRClient {
+                &self.client
+            }
+
+            $crate::signalr_hub!(@methods $($body)*);
+        }
+    };
+
+    (@methods) => {};
+
+    (@methods
+        $(#[doc = $doc:expr])*
+        #[invoke($target:literal)]
+        $vis:vis async fn $method:ident(&self $(, $arg:ident : $ty:ty)*) -> $ret:ty;
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $doc])*
+        $vis async fn $method(&self $(, $arg: $ty)*) -> Result<$ret, String> {
+            self.client.invoke_with_args::<$ret, _>($target.to_string(), move |_c| {
+                $( _c.argument(&$arg); )*
+            }).await
+        }
+
+        $crate::signalr_hub!(@methods $($rest)*);
+    };
+
+    (@methods
+        $(#[doc = $doc:expr])*
+        #[send($target:literal)]
+        $vis:vis async fn $method:ident(&self $(, $arg:ident : $ty:ty)*);
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $doc])*
+        $vis async fn $method(&self $(, $arg: $ty)*) -> Result<(), String> {
+            self.client.send_with_args($target.to_string(), move |_c| {
+                $( _c.argument(&$arg); )*
+            }).await
+        }
+
+        $crate::signalr_hub!(@methods $($rest)*);
+    };
+
+    (@methods
+        $(#[doc = $doc:expr])*
+        #[enumerate($target:literal)]
+        $vis:vis async fn $method:ident(&self $(, $arg:ident : $ty:ty)*) -> $ret:ty;
+        $($rest:tt)*
+    ) => {
+        $(#[doc = $doc])*
+        $vis async fn $method(&self $(, $arg: $ty)*) -> impl $crate::macros::Stream<Item = $ret> + '_ {
+            // the arguments are moved into the configuration, so the stream borrows the client only
+            self.client.enumerate_with_args::<$ret, _>($target.to_string(), move |_c| {
+                $( _c.argument(&$arg); )*
+            }).await
+        }
+
+        $crate::signalr_hub!(@methods $($rest)*);
+    };
+}
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 use serde::{Deserialize, Serialize};
 use super::negotiate::MessageType;
 
@@ -9,11 +9,32 @@ use super::negotiate::MessageType;
 pub struct Close {
     r#type: MessageType,
     #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     allow_reconnect: Option<bool>,
 }
 
+impl Close {
+    pub fn get_error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    pub fn get_allow_reconnect(&self) -> Option<bool> {
+        self.allow_reconnect
+    }
+
+    /// Reads the `Retry-After` header in seconds, some deployments send it to spread the reconnecting clients
+    pub fn get_retry_after(&self) -> Option<Duration> {
+        self.headers.as_ref()?
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Retry-After"))
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+    }
+}
+
 // NEVER SENT
 
 // impl Close {
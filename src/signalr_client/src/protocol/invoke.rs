@@ -61,11 +61,30 @@ impl Invocation {
         }
     }
 
+    pub fn with_value_argument(&mut self, value: impl Into<serde_json::Value>) -> &mut Self {
+        self.with_raw_arguments(vec![value.into()])
+    }
+
+    pub fn with_raw_arguments(&mut self, data: Vec<serde_json::Value>) -> &mut Self {
+        if let Some(ref mut vec) = self.arguments {
+            vec.extend(data);
+        } else {
+            self.arguments = Some(data);
+        }
+
+        self
+    }
+
     pub fn with_invocation_id(&mut self, invocation_id: impl ToString) -> &mut Self {
         self.invocation_id = Some(invocation_id.to_string());
         self
     }
 
+    pub fn with_header(&mut self, key: impl ToString, value: impl ToString) -> &mut Self {
+        self.headers.get_or_insert_with(HashMap::new).insert(key.to_string(), value.to_string());
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_streams(&mut self, stream_ids: Vec<String>) -> &mut Self {
         if !stream_ids.is_empty() {
@@ -115,7 +134,17 @@ impl<R> Completion<R> {
         }
     }
 
-    #[allow(dead_code)]
+    /// Completion without a result, e.g. the end of a stream
+    pub fn create_empty(invocation_id: String) -> Self {
+        Completion {
+            r#type: MessageType::Completion,
+            invocation_id: invocation_id,
+            result: None,
+            error: None,
+            headers: None,
+        }
+    }
+
     pub fn is_error(&self) -> bool {
         self.error.is_some()
     }
@@ -128,6 +157,31 @@ impl<R> Completion<R> {
         self.error.unwrap()
     }
 
+    pub fn get_headers(&self) -> Option<&HashMap<String, String>> {
+        self.headers.as_ref()
+    }
+
+    /// Returns the error text together with the headers of the completion, e.g. an error code set by the hub
+    pub fn error_details(&self) -> Option<(String, HashMap<String, String>)> {
+        self.error.as_ref().map(|error| (error.clone(), self.headers.clone().unwrap_or_default()))
+    }
+
+    /// Returns the error text followed by the headers in `[key=value, ...]` form, ordered by key, e.g. `Not found [code=E404]`
+    pub fn unwrap_error_with_headers(self) -> String {
+        let (error, headers) = self.error_details().unwrap();
+
+        if headers.is_empty() {
+            return error;
+        }
+
+        let mut headers: Vec<(String, String)> = headers.into_iter().collect();
+        headers.sort();
+
+        let headers: Vec<String> = headers.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+
+        format!("{} [{}]", error, headers.join(", "))
+    }
+
     pub fn unwrap_result(self) -> R {
         self.result.unwrap()
     }
@@ -143,6 +197,16 @@ pub struct CancelInvocation {
     pub invocation_id: String,
 }
 
+impl CancelInvocation {
+    pub fn new(invocation_id: impl Into<String>) -> Self {
+        CancelInvocation {
+            r#type: MessageType::CancelInvocation,
+            headers: None,
+            invocation_id: invocation_id.into(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 /// Sent by the client to cancel a streaming invocation on the server.
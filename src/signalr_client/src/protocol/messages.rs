@@ -3,6 +3,43 @@ use serde_json::Value;
 
 pub const RECORD_SEPARATOR: &str = "\u{001E}";
 
+/// How the messages are delimited within the websocket frames of a connection.
+///
+/// The SignalR protocol terminates every message with `RECORD_SEPARATOR`, non-standard gateways may use another separator
+/// or send every message in a frame of its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Separator(String),
+    MessagePerFrame,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::Separator(RECORD_SEPARATOR.to_string())
+    }
+}
+
+/// Number handling of the messages received by a client.
+///
+/// With the `arbitrary_precision` feature, `serde_json` keeps the exact digits of every number, e.g. ids beyond `u64` or long decimals.
+/// Clients which do not enable arbitrary precision get numbers normalized to `u64`, `i64` or `f64`, as without the feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParserConfig {
+    arbitrary_precision: bool,
+}
+
+impl ParserConfig {
+    pub fn new(arbitrary_precision: bool) -> Self {
+        ParserConfig {
+            arbitrary_precision: arbitrary_precision,
+        }
+    }
+
+    pub fn is_arbitrary_precision(&self) -> bool {
+        self.arbitrary_precision
+    }
+}
+
 pub struct MessageParser {
 
 }
@@ -13,6 +50,16 @@ impl MessageParser {
         Ok(serialized + RECORD_SEPARATOR)
     }
 
+    /// Serializes a message terminated according to the framing of the connection
+    pub(crate) fn to_json_framed<T: ?Sized + Serialize>(value: &T, framing: &Framing) -> Result<String, serde_json::Error> {
+        let serialized = serde_json::to_string(value)?;
+
+        match framing {
+            Framing::Separator(separator) => Ok(serialized + separator),
+            Framing::MessagePerFrame => Ok(serialized),
+        }
+    }
+
     pub fn to_json_value<T: ?Sized + Serialize>(value: &T) -> Result<Value, serde_json::Error> {
         let serialized = serde_json::to_value(value)?;
         Ok(serialized)
@@ -31,4 +78,62 @@ impl MessageParser {
             Err(response.err().unwrap().to_string())
         }
     }
+
+    pub fn parse_value<T: DeserializeOwned>(value: &Value) -> Result<T, String> {
+        T::deserialize(value).map_err(|e| e.to_string())
+    }
+
+    /// Parses a message respecting the number handling of the client
+    pub fn parse_message_with<T: DeserializeOwned>(message: &str, config: &ParserConfig) -> Result<T, String> {
+        #[cfg(feature = "arbitrary_precision")]
+        if !config.is_arbitrary_precision() {
+            let mut value = serde_json::from_str::<Value>(message).map_err(|e| e.to_string())?;
+            MessageParser::normalize_numbers(&mut value);
+
+            return T::deserialize(value).map_err(|e| e.to_string());
+        }
+
+        #[cfg(not(feature = "arbitrary_precision"))]
+        let _ = config;
+
+        MessageParser::parse_message(message)
+    }
+
+    /// Parses a value respecting the number handling of the client
+    pub fn parse_value_with<T: DeserializeOwned>(value: &Value, config: &ParserConfig) -> Result<T, String> {
+        #[cfg(feature = "arbitrary_precision")]
+        if !config.is_arbitrary_precision() {
+            let mut value = value.clone();
+            MessageParser::normalize_numbers(&mut value);
+
+            return T::deserialize(value).map_err(|e| e.to_string());
+        }
+
+        #[cfg(not(feature = "arbitrary_precision"))]
+        let _ = config;
+
+        MessageParser::parse_value(value)
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    fn normalize_numbers(value: &mut Value) {
+        match value {
+            Value::Number(number) => {
+                let normalized = if let Some(n) = number.as_u64() {
+                    Some(serde_json::Number::from(n))
+                } else if let Some(n) = number.as_i64() {
+                    Some(serde_json::Number::from(n))
+                } else {
+                    number.as_f64().and_then(serde_json::Number::from_f64)
+                };
+
+                if let Some(normalized) = normalized {
+                    *number = normalized;
+                }
+            },
+            Value::Array(items) => items.iter_mut().for_each(MessageParser::normalize_numbers),
+            Value::Object(map) => map.values_mut().for_each(MessageParser::normalize_numbers),
+            _ => {},
+        }
+    }
 }
\ No newline at end of file
@@ -2,4 +2,5 @@ pub(crate) mod negotiate;
 pub(crate) mod messages;
 pub(crate) mod invoke;
 pub(crate) mod close;
-pub(crate) mod streaming;
\ No newline at end of file
+pub(crate) mod streaming;
+pub(crate) mod serializer;
\ No newline at end of file
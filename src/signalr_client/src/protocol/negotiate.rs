@@ -1,7 +1,7 @@
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_repr::Serialize_repr;
 
-#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize_repr, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum MessageType {
     Invocation = 1,
@@ -14,6 +14,42 @@ pub enum MessageType {
     Other = 8,
 }
 
+impl MessageType {
+    /// Maps the numeric message type of the protocol, the types added by later protocol versions become `Other`
+    pub fn from_u8(value: u8) -> MessageType {
+        match value {
+            1 => MessageType::Invocation,
+            2 => MessageType::StreamItem,
+            3 => MessageType::Completion,
+            4 => MessageType::StreamInvocation,
+            5 => MessageType::CancelInvocation,
+            6 => MessageType::Ping,
+            7 => MessageType::Close,
+            _ => MessageType::Other,
+        }
+    }
+}
+
+impl TryFrom<&str> for MessageType {
+    type Error = String;
+
+    /// Parses the numeric message type, e.g. `"1"`, text which is not a number is rejected
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().parse::<u64>().map_err(|e| format!("Invalid message type {:?}: {}", value, e))?;
+
+        Ok(u8::try_from(value).map(MessageType::from_u8).unwrap_or(MessageType::Other))
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // message types added by later protocol versions are received as Other, even above the range of u8
+        let value = u64::deserialize(deserializer)?;
+
+        Ok(u8::try_from(value).map(MessageType::from_u8).unwrap_or(MessageType::Other))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NegotiateResponseV0 {
@@ -38,10 +74,10 @@ pub struct HandshakeRequest {
 }
 
 impl HandshakeRequest {
-    pub fn new(protocol: impl ToString) -> Self {
+    pub fn new(protocol: impl ToString, version: u8) -> Self {
         HandshakeRequest {
             protocol: protocol.to_string(),
-            version: 1,
+            version: version,
         }
     }
 }
@@ -52,6 +88,27 @@ impl HandshakeRequest {
 pub struct HandshakeResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    /// Not sent by ASP.NET Core, some custom servers answer with the version they speak
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u8>,
+}
+
+impl HandshakeResponse {
+    pub fn get_error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    /// Checks that the server accepted the handshake for the requested protocol version
+    pub fn verify(&self, version: u8) -> Result<(), String> {
+        if let Some(error) = &self.error {
+            return Err(format!("Handshake is rejected by the server: {}", error));
+        }
+
+        match self.version {
+            Some(answered) if answered != version => Err(format!("Handshake protocol version mismatch, requested {} but the server speaks {}", version, answered)),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,348 @@
+use serde::{ser, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use serde_json::value::Serializer as ValueSerializer;
+type Error = serde_json::Error;
+
+/// The way the enum variants of the arguments are written into the messages sent to the hub.
+///
+/// `serde` writes the variant names as they are in Rust, hubs using e.g. `JsonStringEnumConverter` with a naming policy
+/// or plain integer enums expect another contract.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumRepresentation {
+    /// The variant name as produced by `serde`, e.g. `"Connected"`.
+    #[default]
+    Name,
+    /// The zero-based index of the variant, e.g. `0`. Variants with data keep their name.
+    Integer,
+    /// The variant name in screaming snake case, e.g. `"NOT_CONNECTED"`.
+    ScreamingSnakeCase,
+    /// The variant name in camel case, e.g. `"notConnected"`.
+    CamelCase,
+}
+
+/// Serialization of the arguments sent by a client.
+///
+/// The options apply to every value passed to `ArgumentConfiguration::argument`, including the enums nested into structs,
+/// sequences and maps. Types with their own `serde` attributes are serialized first and the representation is applied to the resulting variant name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SerializerOptions {
+    enum_representation: EnumRepresentation,
+}
+
+impl SerializerOptions {
+    pub fn new(enum_representation: EnumRepresentation) -> Self {
+        SerializerOptions {
+            enum_representation: enum_representation,
+        }
+    }
+
+    pub fn enum_representation(&self) -> EnumRepresentation {
+        self.enum_representation
+    }
+
+    pub(crate) fn to_value<T: ?Sized + Serialize>(&self, value: &T) -> Result<Value, Error> {
+        if self.enum_representation == EnumRepresentation::Name {
+            serde_json::to_value(value)
+        } else {
+            value.serialize(ArgumentSerializer { options: *self })
+        }
+    }
+
+    fn variant_name(&self, variant: &str) -> String {
+        match self.enum_representation {
+            EnumRepresentation::Name | EnumRepresentation::Integer => variant.to_string(),
+            EnumRepresentation::ScreamingSnakeCase => {
+                let mut name = String::with_capacity(variant.len() + 4);
+
+                for (i, c) in variant.char_indices() {
+                    if i > 0 && c.is_uppercase() {
+                        name.push('_');
+                    }
+
+                    name.extend(c.to_uppercase());
+                }
+
+                name
+            },
+            EnumRepresentation::CamelCase => {
+                let mut chars = variant.chars();
+
+                match chars.next() {
+                    Some(first) => first.to_lowercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            },
+        }
+    }
+
+    fn variant_value(&self, variant_index: u32, variant: &str) -> Value {
+        match self.enum_representation {
+            EnumRepresentation::Integer => Value::from(variant_index),
+            _ => Value::String(self.variant_name(variant)),
+        }
+    }
+
+    fn tagged(&self, variant: &str, value: Value) -> Value {
+        let mut map = Map::new();
+        map.insert(self.variant_name(variant), value);
+
+        Value::Object(map)
+    }
+}
+
+/// Builds a `serde_json::Value` like `serde_json::to_value`, writing the enum variants as the options require.
+struct ArgumentSerializer {
+    options: SerializerOptions,
+}
+
+/// Forwards the already converted elements of sequences, maps and structs to the `serde_json` serializer.
+struct Compound<S> {
+    inner: S,
+    options: SerializerOptions,
+}
+
+struct TupleVariant {
+    variant: &'static str,
+    values: Vec<Value>,
+    options: SerializerOptions,
+}
+
+struct StructVariant {
+    variant: &'static str,
+    fields: Map<String, Value>,
+    options: SerializerOptions,
+}
+
+impl Serializer for ArgumentSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Compound<<ValueSerializer as Serializer>::SerializeSeq>;
+    type SerializeTuple = Compound<<ValueSerializer as Serializer>::SerializeTuple>;
+    type SerializeTupleStruct = Compound<<ValueSerializer as Serializer>::SerializeTupleStruct>;
+    type SerializeTupleVariant = TupleVariant;
+    type SerializeMap = Compound<<ValueSerializer as Serializer>::SerializeMap>;
+    type SerializeStruct = Compound<<ValueSerializer as Serializer>::SerializeStruct>;
+    type SerializeStructVariant = StructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        ValueSerializer.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        ValueSerializer.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        ValueSerializer.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        ValueSerializer.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        ValueSerializer.serialize_i64(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        ValueSerializer.serialize_i128(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        ValueSerializer.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        ValueSerializer.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        ValueSerializer.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        ValueSerializer.serialize_u64(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        ValueSerializer.serialize_u128(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        ValueSerializer.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        ValueSerializer.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        ValueSerializer.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        ValueSerializer.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        ValueSerializer.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        ValueSerializer.serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        ValueSerializer.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value, Error> {
+        ValueSerializer.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, variant: &'static str) -> Result<Value, Error> {
+        Ok(self.options.variant_value(variant_index, variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Value, Error> {
+        let value = self.options.to_value(value)?;
+
+        Ok(self.options.tagged(variant, value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(Compound { inner: ValueSerializer.serialize_seq(len)?, options: self.options })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        Ok(Compound { inner: ValueSerializer.serialize_tuple(len)?, options: self.options })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Ok(Compound { inner: ValueSerializer.serialize_tuple_struct(name, len)?, options: self.options })
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(TupleVariant { variant: variant, values: Vec::with_capacity(len), options: self.options })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(Compound { inner: ValueSerializer.serialize_map(len)?, options: self.options })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+        Ok(Compound { inner: ValueSerializer.serialize_struct(name, len)?, options: self.options })
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(StructVariant { variant: variant, fields: Map::new(), options: self.options })
+    }
+}
+
+impl<S: ser::SerializeSeq<Ok = Value, Error = Error>> ser::SerializeSeq for Compound<S> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.serialize_element(&self.options.to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: ser::SerializeTuple<Ok = Value, Error = Error>> ser::SerializeTuple for Compound<S> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.serialize_element(&self.options.to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: ser::SerializeTupleStruct<Ok = Value, Error = Error>> ser::SerializeTupleStruct for Compound<S> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.serialize_field(&self.options.to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: ser::SerializeMap<Ok = Value, Error = Error>> ser::SerializeMap for Compound<S> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.inner.serialize_key(&self.options.to_value(key)?)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.serialize_value(&self.options.to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: ser::SerializeStruct<Ok = Value, Error = Error>> ser::SerializeStruct for Compound<S> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.inner.serialize_field(key, &self.options.to_value(value)?)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.inner.end()
+    }
+}
+
+impl ser::SerializeTupleVariant for TupleVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(self.options.to_value(value)?);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.options.tagged(self.variant, Value::Array(self.values)))
+    }
+}
+
+impl ser::SerializeStructVariant for StructVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.fields.insert(key.to_string(), self.options.to_value(value)?);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(self.options.tagged(self.variant, Value::Object(self.fields)))
+    }
+}
@@ -13,15 +13,13 @@ pub struct StreamItem<I> {
     pub(crate) item: I,
 }
 
-// WILL BE USED WHEN STREAM IS UPLOADING
-// NOT SUPPORTED YET
-// impl<I> StreamItem<I> {
-//     pub fn new(invocation_id: impl Into<String>, item: I) -> Self {
-//         StreamItem {
-//             r#type: MessageType::StreamItem,
-//             headers: None,
-//             invocation_id: invocation_id.into(),
-//             item,
-//         }
-//     }
-// }
\ No newline at end of file
+impl<I> StreamItem<I> {
+    pub fn new(invocation_id: impl Into<String>, item: I) -> Self {
+        StreamItem {
+            r#type: MessageType::StreamItem,
+            headers: None,
+            invocation_id: invocation_id.into(),
+            item,
+        }
+    }
+}
@@ -8,4 +8,16 @@ pub struct TestEntity {
 
 #[cfg(test)]
 #[cfg(not(target_arch="wasm32"))]
-pub mod tests_tokio;
\ No newline at end of file
+pub mod tests_tokio;
+#[cfg(test)]
+pub mod tests_parser;
+
+#[cfg(test)]
+pub mod tests_completer;
+
+#[cfg(test)]
+pub mod tests_configuration;
+
+#[cfg(test)]
+#[cfg(all(feature = "test-util", not(target_arch="wasm32")))]
+pub mod tests_mock;
@@ -0,0 +1,39 @@
+use std::{future::Future, pin::Pin, task::{Context, Poll}};
+
+use futures::{future::FusedFuture, task::noop_waker};
+
+use crate::CompletedFuture;
+
+#[test]
+fn test_completed_future_polled_twice() {
+    let waker = noop_waker();
+    let mut context = Context::from_waker(&waker);
+    let mut future = CompletedFuture::new(42);
+
+    assert!(!future.is_terminated());
+    assert_eq!(Pin::new(&mut future).poll(&mut context), Poll::Ready(42));
+
+    assert!(future.is_terminated());
+    assert_eq!(Pin::new(&mut future).poll(&mut context), Poll::Pending);
+}
+
+#[tokio::test]
+#[cfg(not(target_arch = "wasm32"))]
+async fn test_manual_future_timeout() {
+    let (future, completer) = crate::ManualFuture::new_with_timeout(std::time::Duration::from_millis(20), -1);
+    let watcher = future.clone();
+
+    assert_eq!(future.await, -1);
+    assert!(watcher.is_cancelled());
+
+    // a late completion is dropped
+    completer.complete(42);
+
+    let (future, completer) = crate::ManualFuture::new_with_timeout(std::time::Duration::from_millis(20), -1);
+    let watcher = future.clone();
+    completer.complete(42);
+
+    assert_eq!(future.await, 42);
+    tokio::time::sleep(std::time::Duration::from_millis(40)).await;
+    assert!(!watcher.is_cancelled());
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use crate::{client::ConnectionConfiguration, communication::{ConnectionData, HttpClient}};
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_port(5220);
+    config.authenticate_bearer("bearer-secret".to_string());
+    config.with_access_token("token-secret".to_string());
+    config.with_query_param("type".to_string(), "client".to_string());
+
+    let debug = format!("{:?}", config);
+
+    assert!(!debug.contains("bearer-secret"));
+    assert!(!debug.contains("token-secret"));
+    assert!(debug.contains("localhost"));
+    assert!(debug.contains("test"));
+    assert!(debug.contains("5220"));
+    assert!(debug.contains("client"));
+}
+
+#[test]
+fn test_debug_redacts_basic_password() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.authenticate_basic("user".to_string(), Some("password-secret".to_string()));
+
+    let debug = format!("{:?}", config);
+
+    assert!(debug.contains("user"));
+    assert!(!debug.contains("password-secret"));
+}
+
+#[test]
+fn test_redact_url() {
+    let url = "wss://localhost:5220/test?type=client&access_token=token-secret";
+
+    assert_eq!(ConnectionConfiguration::redact_url(url), "wss://localhost:5220/test?type=client&access_token=***");
+    assert_eq!(ConnectionConfiguration::redact_url("wss://localhost/test"), "wss://localhost/test");
+}
+
+#[test]
+fn test_port_zero_is_rejected() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_port(0);
+
+    assert!(config.validate().is_err());
+
+    config.with_port(65535);
+
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_url_port_out_of_range_is_rejected() {
+    for url in ["wss://localhost:70000/test", "wss://localhost:-1/test", "wss://localhost:0/test"] {
+        let mut config = ConnectionConfiguration::new(String::new(), String::new());
+        config.with_url(url);
+
+        assert!(config.validate().is_err(), "{} should be rejected", url);
+    }
+}
+
+#[test]
+fn test_negotiate_retries() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+
+    assert_eq!(config.get_negotiate_retries(), (0, Duration::ZERO));
+
+    config.with_negotiate_retries(3, Duration::from_millis(500));
+
+    assert_eq!(config.get_negotiate_retries(), (3, Duration::from_millis(500)));
+}
+
+#[test]
+fn test_negotiate_url() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+
+    assert!(!config.get_negotiate());
+    assert!(config.get_negotiate_url().ends_with("/test/negotiate"));
+
+    config.with_negotiate();
+    config.add_query_param("type".to_string(), "client".to_string());
+
+    assert!(config.get_negotiate());
+    assert!(config.get_negotiate_url().ends_with("/test/negotiate?type=client"));
+}
+
+#[test]
+fn test_negotiate_transient_failures() {
+    assert!(HttpClient::is_transient(None));
+    assert!(HttpClient::is_transient(Some(502)));
+    assert!(HttpClient::is_transient(Some(503)));
+    assert!(!HttpClient::is_transient(Some(401)));
+    assert!(!HttpClient::is_transient(Some(403)));
+    assert!(!HttpClient::is_transient(Some(404)));
+}
+
+#[test]
+fn test_replace_access_token() {
+    assert_eq!(ConnectionData::replace_access_token("wss://localhost/test?type=client&access_token=old", "new"), "wss://localhost/test?type=client&access_token=new");
+    assert_eq!(ConnectionData::replace_access_token("wss://localhost/test", "new"), "wss://localhost/test?access_token=new");
+    assert_eq!(ConnectionData::replace_query_param("wss://localhost/test?access_token=a&id=old", "id", "new"), "wss://localhost/test?access_token=a&id=new");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[tokio::test]
+async fn test_access_token_provider() {
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_access_token("expired".to_string());
+    config.with_access_token_provider(move || {
+        let call = counter.fetch_add(1, Ordering::Relaxed) + 1;
+
+        async move { Ok(format!("fresh{}", call)) }
+    });
+
+    let data = ConnectionData::new(config.get_socket_url(), String::new(), &config);
+
+    assert_eq!(data.resolve_endpoint().await.unwrap(), "wss://localhost/test?access_token=fresh1");
+    assert_eq!(data.resolve_endpoint().await.unwrap(), "wss://localhost/test?access_token=fresh2");
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn test_access_token_is_replaced() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_access_token("old".to_string());
+    config.add_query_param("tag".to_string(), "a".to_string());
+    config.add_query_param("tag".to_string(), "b".to_string());
+    config.with_access_token("new".to_string());
+
+    let url = config.get_socket_url();
+
+    assert_eq!(url.matches("access_token=").count(), 1);
+    assert!(url.contains("access_token=new"));
+    assert!(url.contains("tag=a&tag=b"));
+
+    config.set_query_param("tag".to_string(), "c".to_string());
+
+    assert_eq!(config.get_socket_url().matches("tag=").count(), 1);
+}
+
+#[test]
+fn test_public_getters() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_port(5220);
+    config.authenticate_basic("user".to_string(), Some("password-secret".to_string()));
+    config.with_query_param("type".to_string(), "client".to_string());
+    config.with_access_token("token-secret".to_string());
+
+    assert_eq!(config.domain(), "localhost");
+    assert_eq!(config.hub(), "test");
+    assert_eq!(config.port(), Some(5220));
+    assert!(config.is_secure());
+    assert_eq!(config.query_params(), vec![
+        ("type".to_string(), "client".to_string()),
+        ("access_token".to_string(), "***".to_string()),
+    ]);
+    assert!(config.authentication().contains("user"));
+    assert!(!config.authentication().contains("password-secret"));
+}
+
+#[test]
+fn test_empty_record_separator_is_rejected() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_record_separator(String::new());
+
+    assert!(config.validate().is_err());
+
+    config.with_message_per_frame();
+
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_concurrent_callbacks() {
+    let mut config = ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    assert!(!ConnectionData::new(config.get_socket_url(), String::new(), &config).get_concurrent_callbacks());
+
+    config.with_concurrent_callbacks(true);
+    assert!(ConnectionData::new(config.get_socket_url(), String::new(), &config).get_concurrent_callbacks());
+}
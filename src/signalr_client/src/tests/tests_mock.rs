@@ -0,0 +1,1007 @@
+use std::sync::{Arc, Mutex};
+
+use futures::{poll, Stream as _, StreamExt};
+
+use crate::{MockCommunication, SignalRClient};
+
+use super::TestEntity;
+
+fn last_invocation_id(mock: &MockCommunication) -> String {
+    let sent = mock.sent();
+    let message: serde_json::Value = serde_json::from_str(sent.last().unwrap()).unwrap();
+
+    message["invocationId"].as_str().unwrap().to_string()
+}
+
+/// Yields until a spawned task sends a cancellation, gives up after a bounded number of attempts
+async fn wait_for_cancel(mock: &MockCommunication) -> serde_json::Value {
+    for _ in 0..1000 {
+        if let Some(message) = mock.sent().iter().rev().find(|m| m.contains("\"type\":5")) {
+            return serde_json::from_str(message).unwrap();
+        }
+
+        tokio::task::yield_now().await;
+    }
+
+    panic!("No cancellation was sent");
+}
+
+#[tokio::test]
+async fn test_mock_invoke_completion() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":5,\"text\":\"test\"}}}}\u{001E}", id));
+
+    let entity = invocation.await.unwrap();
+    assert_eq!(entity.number, 5);
+    assert_eq!(entity.text, "test");
+}
+
+#[tokio::test]
+async fn test_mock_invoke_error() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"error\":\"failed\"}}\u{001E}", id));
+
+    assert_eq!(invocation.await.err(), Some("failed".to_string()));
+}
+
+#[tokio::test]
+async fn test_mock_invoke_result_type_mismatch() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":true}}\u{001E}", id));
+
+    let error = invocation.await.err().unwrap();
+    assert!(error.contains("cannot be deserialized"), "{}", error);
+    assert!(error.contains("TestEntity"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_mock_invocation_id_skips_registered_keys() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let _handler = client.register("SingleEntity_1".to_string(), |_| {});
+
+    let mut invocation = Box::pin(client.invoke::<i32>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    assert_ne!(id, "SingleEntity_1");
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":1}}\u{001E}", id));
+    assert_eq!(invocation.await, Ok(1));
+}
+
+#[tokio::test]
+async fn test_mock_many_concurrent_invocations() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let mut invocations = Vec::new();
+
+    for _ in 0..500 {
+        let c = client.clone();
+        let mut invocation = Box::pin(async move { c.invoke::<usize>("Echo".to_string()).await });
+        assert!(poll!(invocation.as_mut()).is_pending());
+        invocations.push(invocation);
+    }
+
+    let ids: Vec<String> = mock.sent()
+        .iter()
+        .map(|m| serde_json::from_str::<serde_json::Value>(m).unwrap()["invocationId"].as_str().unwrap().to_string())
+        .collect();
+    let unique: std::collections::HashSet<&String> = ids.iter().collect();
+    assert_eq!(unique.len(), 500);
+
+    for (i, id) in ids.iter().enumerate() {
+        mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{}}}\u{001E}", id, i));
+    }
+
+    let results = futures::future::join_all(invocations).await;
+    assert_eq!(results, (0..500).map(Ok).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_mock_stream_items() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let stream = client.enumerate::<TestEntity>("HundredEntities".to_string()).await;
+    let id = last_invocation_id(&mock);
+
+    // a record split across frames is routed once it is complete
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":1,", id));
+    mock.feed(&format!("\"text\":\"a\"}}}}\u{001E}{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":2,\"text\":\"b\"}}}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+
+    let items: Vec<TestEntity> = stream.collect().await;
+    assert_eq!(items.iter().map(|e| e.number).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_mock_stream_outlives_client_borrow() {
+    let mock = MockCommunication::new();
+
+    let stream = {
+        let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+        client.enumerate::<TestEntity>("HundredEntities".to_string()).await
+    };
+
+    let id = stream.invocation_id().to_string();
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":1,\"text\":\"a\"}}}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+
+    let items: Vec<TestEntity> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_callback() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let target = received.clone();
+    let _handler = client.register("callback1".to_string(), move |ctx| {
+        target.lock().unwrap().push(ctx.argument::<String>(0).unwrap());
+    });
+
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"hello\"]}\u{001E}");
+
+    assert_eq!(*received.lock().unwrap(), vec!["hello".to_string()]);
+}
+
+#[tokio::test]
+async fn test_mock_malformed_messages_are_skipped() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let target = received.clone();
+    let _handler = client.register("callback1".to_string(), move |ctx| {
+        target.lock().unwrap().push(ctx.argument::<String>(0).unwrap());
+    });
+
+    // an invocation without a target, a completion with a numeric id, a stream item with a numeric id and garbage
+    mock.feed("{\"type\":1,\"arguments\":[\"lost\"]}\u{001E}{\"type\":3,\"invocationId\":5}\u{001E}{\"type\":2,\"invocationId\":5,\"item\":1}\u{001E}not json\u{001E}");
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"hello\"]}\u{001E}");
+
+    assert_eq!(*received.lock().unwrap(), vec!["hello".to_string()]);
+}
+
+#[tokio::test]
+async fn test_mock_multiple_callbacks_per_target() {
+    use crate::CallbackHandler;
+
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let target = received.clone();
+    let mut first = client.register("callback1".to_string(), move |ctx| {
+        target.lock().unwrap().push(format!("first {}", ctx.argument::<String>(0).unwrap()));
+    });
+    let target = received.clone();
+    let second = client.register("callback1".to_string(), move |ctx| {
+        target.lock().unwrap().push(format!("second {}", ctx.argument::<String>(0).unwrap()));
+    });
+
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"a\"]}\u{001E}");
+    assert_eq!(*received.lock().unwrap(), vec!["first a".to_string(), "second a".to_string()]);
+
+    first.suspend();
+    assert!(!first.is_registered());
+    assert!(second.is_registered());
+
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"b\"]}\u{001E}");
+    assert_eq!(received.lock().unwrap().last(), Some(&"second b".to_string()));
+
+    second.unregister();
+    first.re_register();
+
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"c\"]}\u{001E}");
+    assert_eq!(received.lock().unwrap().len(), 4);
+    assert_eq!(received.lock().unwrap().last(), Some(&"first c".to_string()));
+
+    first.unregister();
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"d\"]}\u{001E}");
+    assert_eq!(received.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_mock_records_sent_messages() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    client.send("Notify".to_string()).await.unwrap();
+
+    let sent = mock.sent();
+    assert_eq!(sent.len(), 1);
+
+    let message: serde_json::Value = serde_json::from_str(&sent[0]).unwrap();
+    assert_eq!(message["target"], "Notify");
+    assert!(message.get("invocationId").is_none());
+}
+
+#[tokio::test]
+async fn test_mock_close() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    mock.close(Some("server closed".to_string()));
+
+    assert!(!client.is_connected());
+    assert_eq!(client.close_reason(), Some("server closed".to_string()));
+    assert!(client.send("Notify".to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_mock_concurrent_streams_are_isolated() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let first = client.enumerate::<TestEntity>("HundredEntities".to_string()).await;
+    let first_id = last_invocation_id(&mock);
+
+    let second = client.enumerate::<TestEntity>("HundredEntities".to_string()).await;
+    let second_id = last_invocation_id(&mock);
+
+    assert_ne!(first_id, second_id);
+
+    // items of both streams interleave on the wire, with one of them completing early
+    for i in 0..10 {
+        mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":{},\"text\":\"first\"}}}}\u{001E}", first_id, i));
+
+        if i < 5 {
+            mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":{},\"text\":\"second\"}}}}\u{001E}", second_id, 100 + i));
+        } else if i == 5 {
+            mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", second_id));
+        }
+    }
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", first_id));
+
+    let (first, second): (Vec<TestEntity>, Vec<TestEntity>) = futures::join!(first.collect(), second.collect());
+
+    assert!(first.iter().all(|e| e.text == "first"));
+    assert_eq!(first.iter().map(|e| e.number).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+    assert!(second.iter().all(|e| e.text == "second"));
+    assert_eq!(second.iter().map(|e| e.number).collect::<Vec<_>>(), (100..105).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_mock_concurrent_streams_single_frame() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let first = client.enumerate::<i32>("Numbers".to_string()).await;
+    let first_id = last_invocation_id(&mock);
+
+    let second = client.enumerate::<i32>("Numbers".to_string()).await;
+    let second_id = last_invocation_id(&mock);
+
+    let mut frame = String::new();
+
+    for i in 0..20 {
+        let id = if i % 2 == 0 { &first_id } else { &second_id };
+        frame.push_str(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{}}}\u{001E}", id, i));
+    }
+
+    frame.push_str(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", second_id));
+    frame.push_str(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", first_id));
+
+    mock.feed(&frame);
+
+    let first: Vec<i32> = first.collect().await;
+    let second: Vec<i32> = second.collect().await;
+
+    assert_eq!(first, (0..20).filter(|i| i % 2 == 0).collect::<Vec<_>>());
+    assert_eq!(second, (0..20).filter(|i| i % 2 == 1).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_mock_stream_item_does_not_reach_callback() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let received = Arc::new(Mutex::new(0));
+
+    let target = received.clone();
+    let _handler = client.register("1".to_string(), move |_| {
+        *target.lock().unwrap() += 1;
+    });
+
+    // a result with an id equal to a callback target is neither routed to nor removes the callback
+    mock.feed("{\"type\":2,\"invocationId\":\"1\",\"item\":1}\u{001E}");
+    mock.feed("{\"type\":3,\"invocationId\":\"1\"}\u{001E}");
+    mock.feed("{\"type\":1,\"target\":\"1\",\"arguments\":[]}\u{001E}");
+
+    assert_eq!(*received.lock().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_stream_item_to_non_stream_invocation() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let invoked = last_invocation_id(&mock);
+
+    let mut confirmation = Box::pin(client.send_and_confirm("Notify".to_string()));
+    assert!(poll!(confirmation.as_mut()).is_pending());
+    let confirmed = last_invocation_id(&mock);
+
+    // stream items of a misbehaving hub are ignored, the invocations still wait for their completions
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":1}}\u{001E}", invoked));
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":1}}\u{001E}", confirmed));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    assert!(poll!(confirmation.as_mut()).is_pending());
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"done\"}}}}\u{001E}", invoked));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", confirmed));
+
+    assert_eq!(invocation.await.unwrap().text, "done");
+    assert!(confirmation.await.is_ok());
+}
+
+#[tokio::test]
+async fn test_mock_close_code() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let notified = Arc::new(Mutex::new(None));
+
+    let target = notified.clone();
+    client.on_close(move |reason| {
+        *target.lock().unwrap() = reason;
+    });
+
+    mock.close_with_code(1008, "token expired");
+
+    assert_eq!(client.close_code(), Some(1008));
+
+    let reason = notified.lock().unwrap().clone().unwrap();
+    assert!(reason.contains("1008"));
+    assert!(reason.contains("policy violation"));
+    assert!(reason.contains("token expired"));
+    assert_eq!(client.close_reason(), Some(reason));
+}
+
+#[tokio::test]
+async fn test_mock_ping() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    assert!(client.last_server_message_at().is_none());
+
+    let mut ping = Box::pin(client.ping());
+    assert!(poll!(ping.as_mut()).is_pending());
+
+    let sent: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert_eq!(sent["type"], 6);
+
+    mock.feed("{\"type\":6}\u{001E}");
+
+    assert!(ping.await.is_ok());
+    assert!(client.last_server_message_at().is_some());
+}
+
+#[tokio::test]
+async fn test_mock_disconnect_keeps_invocations_of_clones() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let invoking = client.clone();
+
+    let mut invocation = Box::pin(invoking.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    assert_eq!(invoking.metrics().pending_invocations, 1);
+    let id = last_invocation_id(&mock);
+
+    // the connection is still used by the other clones, so their invocations keep running
+    client.disconnect();
+
+    assert!(poll!(invocation.as_mut()).is_pending());
+    assert_eq!(invoking.metrics().pending_invocations, 1);
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"after disconnect\"}}}}\u{001E}", id));
+    assert_eq!(invocation.await.unwrap().text, "after disconnect");
+    assert_eq!(invoking.metrics().pending_invocations, 0);
+}
+
+#[tokio::test]
+async fn test_mock_close_cancels_pending() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let stream = client.try_enumerate::<TestEntity>("HundredEntities".to_string()).await;
+
+    mock.close(Some("server closed".to_string()));
+
+    assert_eq!(invocation.await.err(), Some("server closed".to_string()));
+
+    let items: Vec<Result<TestEntity, String>> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().err(), Some(&"server closed".to_string()));
+}
+
+#[tokio::test]
+async fn test_mock_counted_stream() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut stream = client.enumerate_counted::<TestEntity>("HundredEntities".to_string()).await;
+    let id = last_invocation_id(&mock);
+    assert_eq!(stream.size_hint(), (0, None));
+
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":2}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":1,\"text\":\"a\"}}}}\u{001E}", id));
+
+    let first = stream.next().await.unwrap().unwrap();
+    assert_eq!(first.number, 1);
+    assert_eq!(stream.total(), Some(2));
+    assert_eq!(stream.size_hint(), (1, Some(1)));
+
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":2,\"text\":\"b\"}}}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+
+    let rest: Vec<Result<TestEntity, String>> = stream.by_ref().collect().await;
+    assert_eq!(rest.len(), 1);
+    assert_eq!(stream.received(), 2);
+    assert_eq!(stream.size_hint(), (0, Some(0)));
+}
+
+#[tokio::test]
+async fn test_mock_close_reconnect_hint() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    assert!(client.allows_reconnect());
+    assert_eq!(client.reconnect_delay(), None);
+
+    mock.feed("{\"type\":7,\"error\":\"restarting\",\"allowReconnect\":true,\"headers\":{\"retry-after\":\"30\"}}\u{001E}");
+
+    assert!(client.allows_reconnect());
+    assert_eq!(client.reconnect_delay(), Some(std::time::Duration::from_secs(30)));
+    assert_eq!(client.close_reason(), Some("restarting".to_string()));
+
+    mock.feed("{\"type\":7,\"allowReconnect\":false}\u{001E}");
+
+    assert!(!client.allows_reconnect());
+    assert_eq!(client.reconnect_delay(), None);
+}
+
+#[tokio::test]
+async fn test_mock_stream_item_timeout() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let stream = client.enumerate_with_item_timeout::<TestEntity>("HundredEntities".to_string(), std::time::Duration::from_millis(50)).await;
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":1,\"text\":\"a\"}}}}\u{001E}", id));
+
+    let mut stream = Box::pin(stream);
+    let items: Vec<TestEntity> = stream.as_mut().collect().await;
+    assert_eq!(items.len(), 1);
+    assert!(stream.is_timed_out());
+
+    // the cancellation is sent from a spawned task
+    let cancel = wait_for_cancel(&mock).await;
+    assert_eq!(cancel["type"], 5);
+    assert_eq!(cancel["invocationId"], id);
+}
+
+#[tokio::test]
+async fn test_mock_send_message() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    client.send_message(crate::StreamItem::new("remote-1", 7)).await.unwrap();
+    client.send_message(crate::Completion::<()>::create_empty("remote-1".to_string())).await.unwrap();
+
+    assert_eq!(mock.sent(), vec![
+        "{\"type\":2,\"invocationId\":\"remote-1\",\"item\":7}".to_string(),
+        "{\"type\":3,\"invocationId\":\"remote-1\"}".to_string(),
+    ]);
+}
+
+crate::signalr_hub! {
+    /// The test hub of the server
+    struct TestHub {
+        /// Merges two entities
+        #[invoke("PushTwoEntities")]
+        async fn push_two(&self, a: TestEntity, b: TestEntity) -> TestEntity;
+
+        #[send("TriggerEntityCallback")]
+        async fn trigger_callback(&self, target: String);
+
+        #[enumerate("HundredEntities")]
+        async fn hundred_entities(&self) -> TestEntity;
+    }
+}
+
+#[tokio::test]
+async fn test_mock_hub_proxy() {
+    let mock = MockCommunication::new();
+    let hub = TestHub::new(SignalRClient::from_communication(mock.clone()).unwrap());
+
+    hub.trigger_callback("callback1".to_string()).await.unwrap();
+
+    let sent: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert_eq!(sent["target"], "TriggerEntityCallback");
+    assert_eq!(sent["arguments"], serde_json::json!(["callback1"]));
+
+    let first = TestEntity { number: 200, text: "entity1".to_string() };
+    let second = TestEntity { number: 300, text: "entity2".to_string() };
+
+    let mut merged = Box::pin(hub.push_two(first, second));
+    assert!(poll!(merged.as_mut()).is_pending());
+
+    let sent: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert_eq!(sent["target"], "PushTwoEntities");
+    assert_eq!(sent["arguments"][0]["number"], 200);
+    assert_eq!(sent["arguments"][1]["number"], 300);
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":500,\"text\":\"merged\"}}}}\u{001E}", id));
+
+    assert_eq!(merged.await.unwrap().number, 500);
+
+    let entities = hub.hundred_entities().await;
+    let id = last_invocation_id(&mock);
+
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":1,\"text\":\"a\"}}}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+
+    let items: Vec<TestEntity> = entities.collect().await;
+    assert_eq!(items.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_stream_item_without_invocation_id() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let stream = client.enumerate::<TestEntity>("HundredEntities".to_string()).await;
+    let id = last_invocation_id(&mock);
+
+    mock.feed("{\"type\":2,\"item\":{\"number\":1,\"text\":\"lost\"}}\u{001E}");
+    assert_eq!(client.metrics().dropped_stream_items, 1);
+
+    mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":2,\"text\":\"a\"}}}}\u{001E}", id));
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+
+    let items: Vec<TestEntity> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].number, 2);
+    assert_eq!(client.metrics().dropped_stream_items, 1);
+}
+
+#[tokio::test]
+async fn test_mock_ready() {
+    let mock = MockCommunication::new();
+    let mut client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    assert!(client.ready().await);
+
+    mock.close(None);
+    assert!(client.reconnect().await.is_ok());
+    assert!(client.ready().await);
+}
+
+#[tokio::test]
+async fn test_mock_invoke_array() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke_array::<TestEntity>("EntityList".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let sent: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert_eq!(sent["type"], 1);
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":[{{\"number\":1,\"text\":\"a\"}},{{\"number\":2,\"text\":\"b\"}}]}}\u{001E}", id));
+
+    let entities = invocation.await.unwrap();
+    assert_eq!(entities.len(), 2);
+    assert_eq!(entities[1].text, "b");
+
+    let mut invocation = Box::pin(client.invoke_array::<TestEntity>("EntityList".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":null}}\u{001E}", id));
+
+    assert!(invocation.await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mock_frame_interceptors() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let mut config = crate::ConnectionConfiguration::new("mock".to_string(), "mock".to_string());
+    config.with_outbound_interceptor(|frame| STANDARD.encode(frame));
+    config.with_inbound_interceptor(|frame| String::from_utf8(STANDARD.decode(frame).unwrap()).unwrap());
+
+    let mock = MockCommunication::with_configuration(&config);
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let encoded = mock.sent().last().unwrap().clone();
+    let frame = String::from_utf8(STANDARD.decode(&encoded).unwrap()).unwrap();
+    assert!(frame.ends_with("\u{001E}"));
+
+    let sent: serde_json::Value = serde_json::from_str(frame.trim_end_matches('\u{001E}')).unwrap();
+    assert_eq!(sent["target"], "SingleEntity");
+
+    let id = sent["invocationId"].as_str().unwrap();
+    let completion = format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":7,\"text\":\"secret\"}}}}\u{001E}", id);
+    mock.feed(&STANDARD.encode(completion));
+
+    assert_eq!(invocation.await.unwrap().text, "secret");
+}
+
+#[tokio::test]
+async fn test_mock_invoke_error_headers() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let id = last_invocation_id(&mock);
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"error\":\"Device not found\",\"headers\":{{\"code\":\"E404\",\"device\":\"d1\"}}}}\u{001E}", id));
+
+    assert_eq!(invocation.await.err().unwrap(), "Device not found [code=E404, device=d1]");
+
+    let completion: crate::Completion<TestEntity> = serde_json::from_str("{\"type\":3,\"invocationId\":\"1\",\"error\":\"failed\",\"headers\":{\"code\":\"E1\"}}").unwrap();
+    let (error, headers) = completion.error_details().unwrap();
+    assert_eq!(error, "failed");
+    assert_eq!(headers.get("code").unwrap(), "E1");
+}
+
+#[tokio::test]
+async fn test_mock_clone_outlives_original() {
+    use crate::CallbackHandler;
+
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let target = received.clone();
+    let handler = client.register("callback1".to_string(), move |ctx| {
+        target.lock().unwrap().push(ctx.argument::<String>(0).unwrap());
+    });
+
+    let clone = client.clone();
+    let mut invocation = Box::pin(clone.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let id = last_invocation_id(&mock);
+
+    drop(client);
+
+    assert!(clone.is_connected());
+    assert!(handler.is_registered());
+
+    mock.feed("{\"type\":1,\"target\":\"callback1\",\"arguments\":[\"after drop\"]}\u{001E}");
+    assert_eq!(*received.lock().unwrap(), vec!["after drop".to_string()]);
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"clone\"}}}}\u{001E}", id));
+    assert_eq!(invocation.await.unwrap().text, "clone");
+
+    assert_eq!(clone.metrics().pending_invocations, 0);
+}
+
+#[tokio::test]
+async fn test_mock_clones_dropped_in_any_order() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    for order in [[0, 1, 2], [1, 0, 2], [2, 1, 0], [0, 2, 1], [1, 2, 0], [2, 0, 1]] {
+        let client = SignalRClient::from_communication(MockCommunication::new()).unwrap();
+        let closed = Arc::new(AtomicUsize::new(0));
+
+        let counter = closed.clone();
+        client.on_close(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let mut clients = vec![Some(client.clone()), Some(client.clone()), Some(client)];
+
+        for (i, index) in order.iter().enumerate() {
+            drop(clients[*index].take());
+
+            for remaining in clients.iter().flatten() {
+                assert!(remaining.is_connected(), "order {:?}, step {}", order, i);
+                assert!(remaining.send("Ping".to_string()).await.is_ok(), "order {:?}, step {}", order, i);
+            }
+
+            let expected = if i == order.len() - 1 { 1 } else { 0 };
+            assert_eq!(closed.load(Ordering::Relaxed), expected, "order {:?}, step {}", order, i);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_mock_register_on_shared_client() {
+    let mock = MockCommunication::new();
+    let client = Arc::new(SignalRClient::from_communication(mock.clone()).unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let shared = client.clone();
+    let target = received.clone();
+    shared.register("MESSAGE".to_string(), move |ctx| {
+        target.lock().unwrap().push(ctx.argument::<String>(0).unwrap());
+    });
+
+    mock.feed("{\"type\":1,\"target\":\"MESSAGE\",\"arguments\":[\"shared\"]}\u{001E}");
+    assert_eq!(*received.lock().unwrap(), vec!["shared".to_string()]);
+}
+
+#[tokio::test]
+async fn test_mock_flush() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    client.send("Notify".to_string()).await.unwrap();
+    assert!(client.flush().await.is_ok());
+
+    mock.close(None);
+    assert!(client.flush().await.is_err());
+}
+
+#[tokio::test]
+async fn test_mock_pending_invocations() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    client.register("callback1".to_string(), |_| {});
+    assert!(client.pending_invocations().is_empty());
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let id = last_invocation_id(&mock);
+
+    assert_eq!(client.pending_invocations(), vec![id.clone()]);
+    assert!(client.is_pending(&id));
+    assert!(!client.is_pending("callback1"));
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"done\"}}}}\u{001E}", id));
+    assert_eq!(invocation.await.unwrap().text, "done");
+
+    assert!(!client.is_pending(&id));
+    assert!(client.pending_invocations().is_empty());
+}
+
+#[tokio::test]
+async fn test_mock_invoke_with_timeout() {
+    let mut config = crate::ConnectionConfiguration::new("localhost".to_string(), "test".to_string());
+    config.with_timeout_header("timeout-ms".to_string());
+
+    let mock = MockCommunication::with_configuration(&config);
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke_with_timeout::<TestEntity>("SingleEntity".to_string(), std::time::Duration::from_millis(20)));
+    assert!(poll!(invocation.as_mut()).is_pending());
+
+    let message: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert_eq!(message["headers"]["timeout-ms"], "20");
+
+    let error = invocation.await.err().unwrap();
+    assert!(error.contains("timed out"), "{}", error);
+    assert!(client.pending_invocations().is_empty());
+
+    // without a configured header the timeout is applied by the client only
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut invocation = Box::pin(client.invoke_with_timeout::<TestEntity>("SingleEntity".to_string(), std::time::Duration::from_secs(5)));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let id = last_invocation_id(&mock);
+
+    let message: serde_json::Value = serde_json::from_str(mock.sent().last().unwrap()).unwrap();
+    assert!(message.get("headers").is_none());
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"in time\"}}}}\u{001E}", id));
+    assert_eq!(invocation.await.unwrap().text, "in time");
+}
+
+#[tokio::test]
+async fn test_mock_binary_frame() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    // without a handler the frame is ignored
+    mock.feed_binary(&[1, 2, 3]);
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let target = received.clone();
+    client.on_binary_frame(move |frame| {
+        target.lock().unwrap().push(frame.to_vec());
+    });
+
+    mock.feed_binary(&[4, 5]);
+    mock.feed("{\"type\":6}\u{001E}");
+
+    assert_eq!(*received.lock().unwrap(), vec![vec![4u8, 5]]);
+    assert_eq!(client.metrics().bytes_received, 3 + 2 + "{\"type\":6}\u{001E}".len());
+}
+
+#[tokio::test]
+async fn test_mock_argument_template() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut tenant = client.argument_template();
+    tenant.argument("tenant-1");
+
+    client.send_with_args("Notify".to_string(), |c| {
+        c.arguments_from(&tenant).argument("first");
+    }).await.unwrap();
+
+    client.send_with_args("Notify".to_string(), |c| {
+        c.arguments_from(&tenant).argument(2);
+    }).await.unwrap();
+
+    let sent: Vec<serde_json::Value> = mock.sent().iter().map(|m| serde_json::from_str(m).unwrap()).collect();
+    assert_eq!(sent[0]["arguments"], serde_json::json!(["tenant-1", "first"]));
+    assert_eq!(sent[1]["arguments"], serde_json::json!(["tenant-1", 2]));
+}
+
+#[tokio::test]
+async fn test_mock_wait_for() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut next = Box::pin(client.wait_for("ANSWER".to_string()));
+    assert!(poll!(next.as_mut()).is_pending());
+
+    mock.feed("{\"type\":1,\"target\":\"ANSWER\",\"arguments\":[\"device-1\",\"sdp1\"]}\u{001E}");
+    mock.feed("{\"type\":1,\"target\":\"ANSWER\",\"arguments\":[\"device-1\",\"sdp2\"]}\u{001E}");
+
+    let ctx = next.await;
+    assert_eq!(ctx.argument::<String>(1).unwrap(), "sdp1");
+
+    // a dropped waiter does not receive anything, the next one gets the next invocation
+    let mut dropped = Box::pin(client.wait_for("ANSWER".to_string()));
+    assert!(poll!(dropped.as_mut()).is_pending());
+    drop(dropped);
+
+    let mut next = Box::pin(client.wait_for("ANSWER".to_string()));
+    assert!(poll!(next.as_mut()).is_pending());
+
+    mock.feed("{\"type\":1,\"target\":\"ANSWER\",\"arguments\":[\"device-1\",\"sdp3\"]}\u{001E}");
+    assert_eq!(next.await.argument::<String>(1).unwrap(), "sdp3");
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_mock_invocation_timing() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let timings = Arc::new(Mutex::new(Vec::new()));
+    let captured = timings.clone();
+    client.on_invocation_timing(move |timing| captured.lock().unwrap().push(timing.clone()));
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let id = last_invocation_id(&mock);
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"result\":{{\"number\":1,\"text\":\"timed\"}}}}\u{001E}", id));
+    invocation.await.unwrap();
+
+    let mut invocation = Box::pin(client.invoke::<TestEntity>("SingleEntity".to_string()));
+    assert!(poll!(invocation.as_mut()).is_pending());
+    let failed = last_invocation_id(&mock);
+
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\",\"error\":\"boom\"}}\u{001E}", failed));
+    invocation.await.err().unwrap();
+
+    let timings = timings.lock().unwrap();
+    assert_eq!(timings.len(), 2);
+    assert_eq!(timings[0].target, "SingleEntity");
+    assert_eq!(timings[0].invocation_id, id);
+    assert!(timings[0].succeeded);
+    assert_eq!(timings[1].invocation_id, failed);
+    assert!(!timings[1].succeeded);
+}
+
+#[tokio::test]
+async fn test_mock_stream_early_break_cancels_invocation() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    let mut stream = client.enumerate::<TestEntity>("MillionEntities".to_string()).await;
+    let id = last_invocation_id(&mock);
+
+    for number in 0..1000 {
+        mock.feed(&format!("{{\"type\":2,\"invocationId\":\"{}\",\"item\":{{\"number\":{},\"text\":\"item\"}}}}\u{001E}", id, number));
+    }
+
+    let mut consumed = 0;
+    while let Some(_) = stream.next().await {
+        consumed += 1;
+
+        if consumed == 10 {
+            break;
+        }
+    }
+
+    drop(stream);
+    assert!(!client.is_pending(&id));
+
+    // the cancellation is sent from a spawned task
+    let cancel = wait_for_cancel(&mock).await;
+    assert_eq!(cancel["type"], 5);
+    assert_eq!(cancel["invocationId"], id);
+
+    // items still in flight are ignored
+    mock.feed(&format!("{{\"type\":3,\"invocationId\":\"{}\"}}\u{001E}", id));
+    assert_eq!(mock.sent().iter().filter(|m| m.contains("\"type\":5")).count(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_primitive_arguments() {
+    let mock = MockCommunication::new();
+    let client = SignalRClient::from_communication(mock.clone()).unwrap();
+
+    client.send_with_args("Forward".to_string(), |c| {
+        c.str_arg("device-1").int_arg(-42).value(true).value(serde_json::json!({"volume": 75}));
+    }).await.unwrap();
+
+    client.send_with_args("Forward".to_string(), |c| {
+        c.str_arg("device-1").argument(TestEntity { number: 1, text: "entity".to_string() });
+    }).await.unwrap();
+
+    let sent: Vec<serde_json::Value> = mock.sent().iter().map(|m| serde_json::from_str(m).unwrap()).collect();
+    assert_eq!(sent[0]["arguments"], serde_json::json!(["device-1", -42, true, {"volume": 75}]));
+    assert_eq!(sent[1]["arguments"][0], "device-1");
+    assert_eq!(sent[1]["arguments"][1]["text"], "entity");
+}
+
+#[test]
+fn test_client_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<SignalRClient>();
+    assert_send_sync::<MockCommunication>();
+}
+
+#[test]
+fn test_negotiate_requires_websockets_text() {
+    use crate::{communication::HttpClient, protocol::negotiate::{NegotiateResponseV0, TransportSpec}};
+
+    let response = |transports: Vec<(&str, Vec<&str>)>| NegotiateResponseV0 {
+        connection_id: "connection-1".to_string(),
+        negotiate_version: 0,
+        available_transports: transports.into_iter().map(|(transport, formats)| TransportSpec {
+            transport: transport.to_string(),
+            transfer_formats: formats.into_iter().map(|f| f.to_string()).collect(),
+        }).collect(),
+    };
+
+    assert!(HttpClient::verify_transports(&response(vec![("LongPolling", vec!["Text", "Binary"])])).is_err());
+    assert!(HttpClient::verify_transports(&response(vec![("WebSockets", vec!["Binary"])])).is_err());
+    assert!(HttpClient::verify_transports(&response(vec![("LongPolling", vec!["Text"]), ("WebSockets", vec!["Text", "Binary"])])).is_ok());
+}
@@ -0,0 +1,221 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use crate::communication::RecordBuffer;
+use crate::execution::{Storage, UpdatableActionStorage};
+use crate::protocol::{invoke::{Completion, Invocation}, messages::{Framing, MessageParser, ParserConfig}, negotiate::{HandshakeRequest, HandshakeResponse, MessageType, Ping}, serializer::{EnumRepresentation, SerializerOptions}};
+
+#[test]
+fn test_u64_max_round_trip() {
+    let mut invocation = Invocation::create_single("Lookup");
+    invocation.with_invocation_id("1");
+    invocation.with_argument(u64::MAX).unwrap();
+
+    let json = MessageParser::to_json(&invocation).unwrap();
+    let message = MessageParser::strip_record_separator(&json);
+
+    for config in [ParserConfig::default(), ParserConfig::new(true)] {
+        let parsed = MessageParser::parse_message_with::<Invocation>(message, &config).unwrap();
+        let argument = parsed.arguments.as_ref().unwrap().get(0).unwrap();
+
+        assert_eq!(MessageParser::parse_value_with::<u64>(argument, &config).unwrap(), u64::MAX);
+    }
+}
+
+#[test]
+fn test_u64_max_completion_round_trip() {
+    let completion = Completion::create_result("1".to_string(), u64::MAX);
+
+    let json = MessageParser::to_json(&completion).unwrap();
+    let message = MessageParser::strip_record_separator(&json);
+
+    for config in [ParserConfig::default(), ParserConfig::new(true)] {
+        let parsed = MessageParser::parse_message_with::<Completion<u64>>(message, &config).unwrap();
+
+        assert!(parsed.is_result());
+        assert_eq!(parsed.unwrap_result(), u64::MAX);
+    }
+}
+
+#[test]
+#[cfg(feature = "arbitrary_precision")]
+fn test_arbitrary_precision_keeps_digits() {
+    let message = r#"{"type":3,"invocationId":"1","result":123456789012345678901234567890}"#;
+
+    let precise = MessageParser::parse_message_with::<Completion<Value>>(message, &ParserConfig::new(true)).unwrap();
+    assert_eq!(precise.unwrap_result().to_string(), "123456789012345678901234567890");
+
+    let normalized = MessageParser::parse_message_with::<Completion<Value>>(message, &ParserConfig::default()).unwrap();
+    assert!(normalized.unwrap_result().as_f64().is_some());
+}
+
+#[test]
+fn test_small_numbers_unchanged() {
+    let message = r#"{"type":3,"invocationId":"1","result":{"number":42,"ratio":0.5}}"#;
+
+    for config in [ParserConfig::default(), ParserConfig::new(true)] {
+        let parsed = MessageParser::parse_message_with::<Completion<Value>>(message, &config).unwrap();
+        let result = parsed.unwrap_result();
+
+        assert_eq!(result["number"].as_u64(), Some(42));
+        assert_eq!(result["ratio"].as_f64(), Some(0.5));
+    }
+}
+
+#[test]
+fn test_record_split_across_frames() {
+    let mut invocation = Invocation::create_single("Lookup");
+    invocation.with_invocation_id("1");
+    invocation.with_argument("ünïcödé payload").unwrap();
+
+    let json = MessageParser::to_json(&invocation).unwrap();
+    let offset = json.char_indices().map(|(i, _)| i).find(|i| *i >= json.len() / 2).unwrap();
+    let mut buffer = RecordBuffer::new(Framing::default());
+
+    assert!(buffer.push(&json[..offset]).is_empty());
+    assert!(buffer.has_pending());
+
+    let records = buffer.push(&json[offset..]);
+    assert_eq!(records.len(), 1);
+    assert!(!buffer.has_pending());
+
+    let parsed = MessageParser::parse_message::<Invocation>(records.first().unwrap()).unwrap();
+    assert_eq!(parsed, invocation);
+}
+
+#[test]
+fn test_records_with_trailing_fragment() {
+    let first = MessageParser::to_json(&Completion::create_result("1".to_string(), 1)).unwrap();
+    let second = MessageParser::to_json(&Completion::create_result("2".to_string(), 2)).unwrap();
+    let frame = format!("{}{}", first, &second[..5]);
+    let mut buffer = RecordBuffer::new(Framing::default());
+
+    let records = buffer.push(&frame);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0], MessageParser::strip_record_separator(&first));
+
+    let records = buffer.push(&second[5..]);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0], MessageParser::strip_record_separator(&second));
+}
+
+#[test]
+fn test_custom_record_separator() {
+    let framing = Framing::Separator("\n".to_string());
+    let first = MessageParser::to_json_framed(&Completion::create_result("1".to_string(), 1), &framing).unwrap();
+    let second = MessageParser::to_json_framed(&Completion::create_result("2".to_string(), 2), &framing).unwrap();
+    let mut buffer = RecordBuffer::new(framing);
+
+    assert!(first.ends_with('\n'));
+
+    let records = buffer.push(&format!("{}{}", first, second));
+    assert_eq!(records, vec![first.trim_end().to_string(), second.trim_end().to_string()]);
+}
+
+#[test]
+fn test_message_per_frame() {
+    let json = MessageParser::to_json_framed(&Completion::create_result("1".to_string(), 1), &Framing::MessagePerFrame).unwrap();
+    let mut buffer = RecordBuffer::new(Framing::MessagePerFrame);
+
+    assert!(!json.ends_with(crate::protocol::messages::RECORD_SEPARATOR));
+    assert_eq!(buffer.push(&json), vec![json.clone()]);
+    assert!(buffer.push("").is_empty());
+    assert!(!buffer.has_pending());
+}
+
+#[test]
+fn test_unknown_message_type() {
+    let ping = MessageParser::parse_message::<Ping>(r#"{"type":42,"payload":"future"}"#).unwrap();
+
+    assert_eq!(ping.message_type(), MessageType::Other);
+}
+
+#[test]
+fn test_message_type_from_number() {
+    assert_eq!(MessageType::from_u8(0), MessageType::Other);
+    assert_eq!(MessageType::from_u8(1), MessageType::Invocation);
+    assert_eq!(MessageType::from_u8(7), MessageType::Close);
+    assert_eq!(MessageType::from_u8(9), MessageType::Other);
+
+    assert_eq!(MessageType::try_from("0").unwrap(), MessageType::Other);
+    assert_eq!(MessageType::try_from("3").unwrap(), MessageType::Completion);
+    assert_eq!(MessageType::try_from("9").unwrap(), MessageType::Other);
+    assert_eq!(MessageType::try_from("1000").unwrap(), MessageType::Other);
+    assert!(MessageType::try_from("ping").is_err());
+
+    let ping = MessageParser::parse_message::<Ping>(r#"{"type":0}"#).unwrap();
+    assert_eq!(ping.message_type(), MessageType::Other);
+
+    let ping = MessageParser::parse_message::<Ping>(r#"{"type":9}"#).unwrap();
+    assert_eq!(ping.message_type(), MessageType::Other);
+
+    let ping = MessageParser::parse_message::<Ping>(r#"{"type":1000}"#).unwrap();
+    assert_eq!(ping.message_type(), MessageType::Other);
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_unknown_message_handler() {
+    let received = Arc::new(Mutex::new(Vec::<String>::new()));
+    let captured = received.clone();
+    let mut storage = UpdatableActionStorage::new();
+    let message = r#"{"type":42,"payload":"future"}"#.to_string();
+
+    assert!(storage.process_message(message.clone(), MessageType::Other).is_ok());
+    assert!(received.lock().unwrap().is_empty());
+
+    storage.set_unknown_handler(Box::new(move |raw| captured.lock().unwrap().push(raw.to_string())));
+
+    assert!(storage.process_message(message.clone(), MessageType::Other).is_ok());
+    assert_eq!(*received.lock().unwrap(), vec![message]);
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+enum DeviceStatus {
+    Connected,
+    NotConnected,
+    Offline,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Device {
+    device_id: String,
+    status: DeviceStatus,
+}
+
+#[test]
+fn test_serializer_enum_representation() {
+    let device = Device { device_id: "device-1".to_string(), status: DeviceStatus::NotConnected };
+
+    let name = SerializerOptions::default().to_value(&device).unwrap();
+    assert_eq!(name["status"], "NotConnected");
+    assert_eq!(serde_json::from_value::<Device>(name).unwrap(), device);
+
+    let integer = SerializerOptions::new(EnumRepresentation::Integer).to_value(&device).unwrap();
+    assert_eq!(integer, serde_json::json!({"device_id": "device-1", "status": 1}));
+
+    let screaming = SerializerOptions::new(EnumRepresentation::ScreamingSnakeCase).to_value(&vec![DeviceStatus::NotConnected, DeviceStatus::Offline]).unwrap();
+    assert_eq!(screaming, serde_json::json!(["NOT_CONNECTED", "OFFLINE"]));
+
+    let camel = SerializerOptions::new(EnumRepresentation::CamelCase).to_value(&Some(DeviceStatus::Connected)).unwrap();
+    assert_eq!(camel, "connected");
+}
+
+#[test]
+fn test_handshake_protocol_version() {
+    let request = serde_json::to_value(HandshakeRequest::new("json", 0)).unwrap();
+    assert_eq!(request, serde_json::json!({"protocol": "json", "version": 0}));
+
+    let accepted = MessageParser::parse_message::<HandshakeResponse>("{}").unwrap();
+    assert!(accepted.verify(1).is_ok());
+
+    let same = MessageParser::parse_message::<HandshakeResponse>("{\"version\":0}").unwrap();
+    assert!(same.verify(0).is_ok());
+
+    let mismatch = MessageParser::parse_message::<HandshakeResponse>("{\"version\":1}").unwrap();
+    assert!(mismatch.verify(0).unwrap_err().contains("version mismatch"));
+
+    let rejected = MessageParser::parse_message::<HandshakeResponse>("{\"error\":\"The server does not support version 2 of the 'json' protocol.\"}").unwrap();
+    assert!(rejected.verify(2).unwrap_err().contains("rejected"));
+}
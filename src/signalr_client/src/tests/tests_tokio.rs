@@ -6,7 +6,7 @@ use crate::{execution::CallbackHandler, tests::TestEntity, SignalRClient};
 
 #[test_log::test(tokio::test)]
 async fn test_service() {
-    let mut client = SignalRClient::connect_with("localhost", "test", |c| {
+    let client = SignalRClient::connect_with("localhost", "test", |c| {
         c.with_port(5220);
         c.unsecure();
     }).await.unwrap();
@@ -37,7 +37,7 @@ async fn test_service() {
 
     assert!(push1.unwrap());
 
-    let mut secondclient = client.clone();
+    let secondclient = client.clone();
 
     let push2 = secondclient.invoke_with_args::<TestEntity, _>("PushTwoEntities".to_string(), |c| {
         c.argument(TestEntity {
@@ -86,6 +86,14 @@ async fn test_service() {
         c.argument("callback1".to_string());
     }).await;
 
+    info!("Calling callback1 with confirmation");
+
+    let triggered = client.send_and_confirm_with_args("TriggerEntityCallback".to_string(), |c| {
+        c.argument("callback1".to_string());
+    }).await;
+
+    assert!(triggered.is_ok());
+
     info!("Calling callback2");
 
     let succ = client.invoke_with_args::<bool, _>("TriggerEntityResponse".to_string(), |c| {
@@ -107,4 +115,46 @@ async fn test_service() {
     c2.unregister();
 
     client.disconnect();
-}
\ No newline at end of file
+}
+
+#[test_log::test(tokio::test)]
+async fn test_manager_shares_client() {
+    let manager = crate::HubConnectionManager::with_options("localhost", |c| {
+        c.with_port(5220);
+        c.unsecure();
+    });
+
+    let first = manager.client_for("test").await.unwrap();
+    let second = manager.client_for("test").await.unwrap();
+
+    // both clients share the connection, so the messages count together
+    let entity = second.invoke::<TestEntity>("SingleEntity".to_string()).await.unwrap();
+    assert_eq!(entity.text, "test".to_string());
+    assert_eq!(first.metrics().messages_sent, second.metrics().messages_sent);
+
+    manager.clear().await;
+}
+
+#[cfg(feature = "blocking")]
+#[test_log::test]
+fn test_blocking_service() {
+    let client = crate::blocking::BlockingSignalRClient::connect_with("localhost", "test", |c| {
+        c.with_port(5220);
+        c.unsecure();
+    }).unwrap();
+
+    let entity = client.invoke::<TestEntity>("SingleEntity".to_string()).unwrap();
+    assert_eq!(entity.text, "test".to_string());
+
+    let count = client.enumerate::<TestEntity>("HundredEntities".to_string()).count();
+    assert_eq!(count, 100);
+
+    // breaking out early drops the unfinished stream outside of the runtime
+    let first: Vec<TestEntity> = client.enumerate::<TestEntity>("HundredEntities".to_string()).take(10).collect();
+    assert_eq!(first.len(), 10);
+
+    let entity = client.invoke::<TestEntity>("SingleEntity".to_string()).unwrap();
+    assert_eq!(entity.text, "test".to_string());
+
+    client.disconnect();
+}
@@ -17,7 +17,7 @@ async fn run() {
     _ = console_log::init_with_level(log::Level::Info);
     console_error_panic_hook::set_once();
 
-    let mut client = SignalRClient::connect_with("localhost", "test", |c| {
+    let client = SignalRClient::connect_with("localhost", "test", |c| {
         c.unsecure();
         c.with_port(5220);
     }).await.unwrap();
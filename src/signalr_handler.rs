@@ -0,0 +1,272 @@
+use std::{sync::Arc, time::Duration};
+use signalr_client::{InvocationContext, SignalRClient};
+use tokio::{sync::{broadcast, Mutex}, time::Instant};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use crate::models::AppState;
+use crate::connect_to_signalr;
+use crate::handlers::refresh_access_token;
+
+// Khoảng thời gian kiểm tra kết nối SignalR
+const RECONNECTION_INTERVAL: Duration = Duration::from_secs(5);
+// Khoảng thời gian kiểm tra thiết bị không còn được hub báo về
+const PRESENCE_SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+// Thiết bị không được hub báo về quá thời gian này bị chuyển sang Offline
+const DEVICE_TTL: Duration = Duration::from_secs(60);
+// Hub method nhận SDP offer gửi tới thiết bị
+const HUB_SEND_OFFER: &str = "SendOffer";
+// Hub method nhận ICE candidate gửi tới thiết bị
+const HUB_SEND_ICE_CANDIDATE: &str = "SendIceCandidate";
+
+pub struct SignalRHandler;
+
+impl SignalRHandler {
+    // Đăng ký các callback nhận message từ hub, gọi một lần khi login
+    pub fn register_handlers(hub_connection: &SignalRClient, app_state: Arc<Mutex<AppState>>, message_sender: broadcast::Sender<String>) {
+        // Chuyển tiếp MESSAGE tới các client đang nghe /events
+        let state = app_state.clone();
+        hub_connection.register("MESSAGE".to_string(), move |ctx| {
+            match ctx.argument::<serde_json::Value>(0) {
+                Ok(message) => {
+                    println!("Nhận MESSAGE từ hub: {}", message);
+
+                    // MESSAGE của một thiết bị cũng là heartbeat của thiết bị đó
+                    if let Some(device_id) = SignalRHandler::message_device_id(&message) {
+                        let state = state.clone();
+
+                        // Callback không phải async nên cập nhật AppState trong task riêng
+                        tokio::spawn(async move {
+                            state.lock().await.mark_device_seen(&device_id);
+                        });
+                    }
+
+                    // Lỗi chỉ xảy ra khi không có client nào đang nghe
+                    let _ = message_sender.send(message.to_string());
+                }
+                Err(e) => println!("Không đọc được MESSAGE: {}", e),
+            }
+        });
+
+        // SDP answer của thiết bị cho offer đã gửi
+        let state = app_state.clone();
+        hub_connection.register_async("ANSWER".to_string(), move |ctx| {
+            let state = state.clone();
+
+            async move {
+                if let Err(e) = SignalRHandler::handle_answer(ctx, state).await {
+                    println!("Không xử lý được ANSWER: {}", e);
+                }
+            }
+        });
+
+        // ICE candidate của thiết bị (trickle ICE)
+        let state = app_state.clone();
+        hub_connection.register_async("ICE_CANDIDATE".to_string(), move |ctx| {
+            let state = state.clone();
+
+            async move {
+                if let Err(e) = SignalRHandler::handle_ice_candidate(ctx, state).await {
+                    println!("Không xử lý được ICE_CANDIDATE: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn handle_answer(ctx: InvocationContext, app_state: Arc<Mutex<AppState>>) -> Result<(), String> {
+        let device_id = ctx.argument::<String>(0)?;
+        let sdp = ctx.argument::<String>(1)?;
+
+        // Không giữ lock AppState trong lúc chờ WebRTC
+        let peer_connection = app_state.lock().await.peer_connection.clone();
+
+        match peer_connection {
+            Some(peer_connection) if peer_connection.device_id == device_id => {
+                peer_connection.set_remote_answer(sdp).await.map_err(|e| e.to_string())
+            }
+            _ => Err(format!("Không có peer connection cho thiết bị {}", device_id)),
+        }
+    }
+
+    async fn handle_ice_candidate(ctx: InvocationContext, app_state: Arc<Mutex<AppState>>) -> Result<(), String> {
+        let device_id = ctx.argument::<String>(0)?;
+        let candidate = ctx.argument::<RTCIceCandidateInit>(1)?;
+
+        // Không giữ lock AppState trong lúc chờ WebRTC
+        let peer_connection = app_state.lock().await.peer_connection.clone();
+
+        match peer_connection {
+            Some(peer_connection) if peer_connection.device_id == device_id => {
+                peer_connection.add_ice_candidate(candidate).await.map_err(|e| e.to_string())
+            }
+            _ => Err(format!("Không có peer connection cho thiết bị {}", device_id)),
+        }
+    }
+
+    // Gửi SDP offer tới thiết bị qua SignalR
+    pub async fn signal_offer(hub_connection: &SignalRClient, device_id: &str, sdp: String) -> Result<(), String> {
+        hub_connection.send_with_args(HUB_SEND_OFFER.to_string(), |c| {
+            c.str_arg(device_id).str_arg(&sdp);
+        }).await
+    }
+
+    // Gửi một ICE candidate local tới thiết bị qua SignalR (trickle ICE)
+    pub async fn signal_ice_candidate(hub_connection: &SignalRClient, device_id: &str, candidate: RTCIceCandidateInit) -> Result<(), String> {
+        hub_connection.send_with_args(HUB_SEND_ICE_CANDIDATE.to_string(), |c| {
+            c.str_arg(device_id).argument(candidate.clone());
+        }).await
+    }
+
+    fn message_device_id(message: &serde_json::Value) -> Option<String> {
+        message.get("device_id")
+            .or_else(|| message.get("deviceId"))
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+    }
+
+    fn is_auth_error(error: &str) -> bool {
+        error.contains("401") || error.contains("Unauthorized")
+    }
+
+    // Làm mới phiên khi một lời gọi hub thất bại vì JWT hết hạn, chạy nền để không chặn request hiện tại
+    pub fn refresh_on_auth_error(app_state: Arc<Mutex<AppState>>, error: &str) {
+        if SignalRHandler::is_auth_error(error) {
+            tokio::spawn(async move {
+                match SignalRHandler::refresh_session(app_state).await {
+                    Ok(_) => println!("Đã làm mới token và kết nối lại SignalR"),
+                    Err(e) => println!("Không thể làm mới token: {}", e),
+                }
+            });
+        }
+    }
+
+    // Làm mới JWT bằng refresh token, tạo kết nối SignalR mới và đăng ký lại callback
+    // Lock AppState chỉ được giữ khi đọc và ghi trạng thái, không giữ trong lúc gọi HTTP và kết nối hub
+    async fn refresh_session(app_state: Arc<Mutex<AppState>>) -> Result<(), String> {
+        let (refresh_token, message_sender) = {
+            let state = app_state.lock().await;
+            let refresh_token = state.refresh_token.clone().ok_or("Không có refresh token".to_string())?;
+
+            (refresh_token, state.message_sender.clone())
+        };
+
+        let login_data = refresh_access_token(&refresh_token).await?;
+
+        let hub_connection = connect_to_signalr(&login_data.token).await.map_err(|e| e.to_string())?;
+        SignalRHandler::register_handlers(&hub_connection, app_state.clone(), message_sender);
+
+        let replaced = {
+            let mut state = app_state.lock().await;
+
+            // Phiên đã được làm mới bởi tác vụ khác hoặc người dùng đã đăng xuất trong lúc chờ
+            if state.refresh_token.as_deref() != Some(refresh_token.as_str()) {
+                Err(hub_connection)
+            } else {
+                state.jwt_token = Some(login_data.token);
+                state.refresh_token = Some(login_data.refresh_token);
+
+                Ok(state.hub_connection.replace(hub_connection))
+            }
+        };
+
+        match replaced {
+            Ok(previous) => {
+                // Đóng kết nối cũ ngoài lock
+                if let Some(previous) = previous {
+                    previous.disconnect();
+                }
+
+                Ok(())
+            }
+            Err(unused) => {
+                unused.disconnect();
+
+                Err("Phiên đã thay đổi trong lúc làm mới token".to_string())
+            }
+        }
+    }
+
+    // Task nền chuyển các thiết bị không còn được hub báo về sang Offline
+    pub fn start_presence_sweeper(app_state: Arc<Mutex<AppState>>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PRESENCE_SWEEP_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let expired = app_state.lock().await.expire_devices(DEVICE_TTL);
+
+                for device_id in expired {
+                    println!("Thiết bị {} không còn được hub báo về, chuyển sang Offline", device_id);
+                }
+            }
+        });
+    }
+
+    // Task nền kiểm tra kết nối SignalR và kết nối lại khi bị mất
+    pub fn start_reconnection_monitor(app_state: Arc<Mutex<AppState>>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RECONNECTION_INTERVAL);
+            // Thời điểm được phép kết nối lại theo Retry-After của hub
+            let mut retry_at: Option<Instant> = None;
+
+            loop {
+                interval.tick().await;
+
+                // Không giữ lock AppState trong lúc kết nối lại, chỉ lấy bản sao của client
+                let (hub_connection, jwt_token) = {
+                    let state = app_state.lock().await;
+
+                    (state.hub_connection.clone(), state.jwt_token.clone())
+                };
+
+                let result = match hub_connection {
+                    Some(mut hub_connection) if !hub_connection.is_connected() => {
+                        // Gợi ý bị xoá khi kết nối lại nên chỉ đọc một lần trước lần thử đầu tiên
+                        if retry_at.is_none() {
+                            if let Some(delay) = hub_connection.reconnect_delay() {
+                                println!("Hub yêu cầu chờ {:?} trước khi kết nối lại", delay);
+                                retry_at = Some(Instant::now() + delay);
+                            }
+                        }
+
+                        match retry_at {
+                            Some(at) if Instant::now() < at => None,
+                            _ => {
+                                retry_at = None;
+                                println!("Mất kết nối SignalR, đang kết nối lại...");
+                                let result = hub_connection.reconnect().await;
+
+                                if result.is_ok() {
+                                    let mut state = app_state.lock().await;
+
+                                    // Chỉ ghi lại client đã kết nối nếu phiên không bị làm mới hoặc đăng xuất trong lúc chờ
+                                    if state.hub_connection.is_some() && state.jwt_token == jwt_token {
+                                        state.hub_connection = Some(hub_connection);
+                                    }
+                                }
+
+                                Some(result)
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+
+                match result {
+                    Some(Ok(_)) => println!("Kết nối lại SignalR thành công"),
+                    Some(Err(e)) => {
+                        println!("Kết nối lại SignalR thất bại: {}", e);
+
+                        // JWT hết hạn: lấy token mới và kết nối lại với access_token mới
+                        if SignalRHandler::is_auth_error(&e) {
+                            match SignalRHandler::refresh_session(app_state.clone()).await {
+                                Ok(_) => println!("Đã làm mới token và kết nối lại SignalR"),
+                                Err(e) => println!("Không thể làm mới token: {}", e),
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+        });
+    }
+}